@@ -45,19 +45,51 @@ r##"<html>
 		<script>
 			if(typeof(Module) === "undefined")
 				Module = {preRun: []};
-			Module.preRun.push(function() {ENV.RUST_BACKTRACE = "1"})
+			Module.preRun.push(function() {
+				ENV.RUST_BACKTRACE = "1";
+				if(/[?&]record\b/.test(location.search)) ENV.BOAT_RECORD = "";
+			})
+			[[module_setup]]
 		</script>
-		<script src="[[pkg_name]]/[[build_type]].js"></script>
+		<script src="[[pkg_name]]/[[target_kind]]/[[build_type]].js"></script>
 	</body>
 </html>"##;
 
+// The emscripten glue asks for the hashed name rustc gives the module
+// (e.g. boat-0123456789abcdef.wasm), so point it at the copy cargo puts
+// next to the .js instead
+const WASM_MODULE_SETUP: &'static str = 
+r##"Module.locateFile = function(file) {
+				if(file.endsWith(".wasm")) return "[[pkg_name]]/wasm/[[build_type]].wasm";
+				return file;
+			}"##;
+
 fn main() {
 	let profile = env::var("PROFILE").unwrap();
+	let target = env::var("TARGET").unwrap();
+
+	if target.contains("emscripten") {
+		write_index_html(&profile, &target);
+	}
 
+	if profile == "debug" {
+		println!("cargo:rustc-cfg=debug");
+	}
+}
+
+fn write_index_html(profile: &str, target: &str) {
 	let color = "#7a9ec6";
 
+	let (target_kind, module_setup) = if target.starts_with("wasm32") {
+		("wasm", WASM_MODULE_SETUP)
+	} else {
+		("asmjs", "")
+	};
+
 	let index_html = INDEX_HTML_TEMPLATE.to_string()
-		.replace("[[build_type]]", &profile)
+		.replace("[[module_setup]]", module_setup)
+		.replace("[[target_kind]]", target_kind)
+		.replace("[[build_type]]", profile)
 		.replace("[[theme_color]]", color)
 		.replace("[[pkg_name]]", env!("CARGO_PKG_NAME"));
 	let dest = env::var("CARGO_MANIFEST_DIR").unwrap();
@@ -65,8 +97,4 @@ fn main() {
 	let mut file = File::create(&path).unwrap();
 
 	file.write_all(index_html.as_bytes()).unwrap();
-
-	if profile == "debug" {
-		println!("cargo:rustc-cfg=debug");
-	}
 }
@@ -0,0 +1,185 @@
+use common::*;
+use boat::{BoatSim, BoatInput};
+use ocean::WaveField;
+use sail::wrap_angle;
+use rendering::mesh_builder::*;
+
+/// How close the boat has to get to a waypoint before moving on to the next
+const WAYPOINT_RADIUS: f32 = 2.0;
+/// How close to the last waypoint counts as having arrived
+const ARRIVAL_RADIUS: f32 = 1.0;
+/// Within this distance of the last waypoint, the sail comes down bit by bit
+const SLOWDOWN_RADIUS: f32 = 6.0;
+/// The least sail the autopilot will use while it still has somewhere to go
+const MIN_SAIL: f32 = 0.3;
+
+/// Longest stretch of route line drawn straight, so it follows the waves
+const ROUTE_SEGMENT_LENGTH: f32 = 1.0;
+const ROUTE_LIFT: f32 = 0.05;
+
+/// Sails the boat through a list of waypoints in order, by driving the same
+/// BoatInput the player would. Tacking is still left up to the boat
+pub struct Autopilot {
+	waypoints: Vec<Vec2>,
+}
+
+impl Autopilot {
+	pub fn new() -> Self {
+		Autopilot { waypoints: Vec::new() }
+	}
+
+	pub fn is_engaged(&self) -> bool { !self.waypoints.is_empty() }
+
+	/// The waypoints still to be reached, next first
+	pub fn waypoints(&self) -> &[Vec2] { &self.waypoints }
+
+	pub fn add_waypoint(&mut self, pos: Vec2) {
+		self.waypoints.push(pos);
+	}
+
+	/// Forgets the route, leaving the boat doing whatever it was last told
+	pub fn disengage(&mut self) {
+		self.waypoints.clear();
+	}
+
+	/// Points `input` at the next waypoint, moving on from any that have been
+	/// reached. Drops the sail on arrival at the last one
+	pub fn steer(&mut self, boat: &BoatSim, input: &mut BoatInput) {
+		while let Some(&next) = self.waypoints.first() {
+			let radius = if self.waypoints.len() > 1 { WAYPOINT_RADIUS } else { ARRIVAL_RADIUS };
+			if (next - boat.position).length() >= radius { break }
+
+			self.waypoints.remove(0);
+
+			if self.waypoints.is_empty() {
+				input.target_speed = 0.0;
+				return
+			}
+		}
+
+		let next = match self.waypoints.first() {
+			Some(&next) => next,
+			None => return,
+		};
+
+		let to_next = next - boat.position;
+		input.target_heading = (-to_next.y).atan2(to_next.x);
+
+		// Ease off while turning hard so the boat doesn't swing wide around the
+		// waypoint, and coming up to the end so it doesn't overshoot
+		let turn = wrap_angle(input.target_heading - boat.heading).abs();
+		let mut sail = 1.0 - turn / PI;
+
+		if self.waypoints.len() == 1 {
+			sail = sail.min(to_next.length() / SLOWDOWN_RADIUS);
+		}
+
+		input.target_speed = sail.max(MIN_SAIL).min(1.0);
+	}
+
+	/// Adds the route from `from` through the remaining waypoints as a line
+	/// strip draped over the waves
+	pub fn build_route_mesh<W>(&self, mb: &mut MeshBuilder<ColorVertex>, from: Vec2, waves: &W, phase: f32)
+		where W: WaveField {
+
+		if self.waypoints.is_empty() { return }
+
+		let color = Color::rgb8(240, 230, 200).into();
+		let vertex = |p: Vec2| ColorVertex(Vec3::new(p.x, waves.height_at(p, phase) + ROUTE_LIFT, p.y), color);
+
+		mb.add_vert(vertex(from));
+
+		let mut prev = from;
+		for &waypoint in self.waypoints.iter() {
+			let diff = waypoint - prev;
+			let segments = (diff.length() / ROUTE_SEGMENT_LENGTH).ceil().max(1.0) as usize;
+
+			for i in 1..segments+1 {
+				mb.add_vert(vertex(prev + diff * (i as f32 / segments as f32)));
+			}
+
+			prev = waypoint;
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use ocean::CalmSea;
+	use wind::Wind;
+
+	const DT: f32 = 1.0 / 60.0;
+
+	fn route(waypoints: &[Vec2]) -> Autopilot {
+		let mut autopilot = Autopilot::new();
+		for &waypoint in waypoints.iter() {
+			autopilot.add_waypoint(waypoint);
+		}
+
+		autopilot
+	}
+
+	#[test]
+	fn heads_for_the_next_waypoint() {
+		let boat = BoatSim::new(0.0);
+		let mut input = BoatInput::new(0.0);
+
+		// -z is a quarter turn anticlockwise of +x
+		let mut autopilot = route(&[Vec2::new(0.0, -10.0), Vec2::new(10.0, -10.0)]);
+		autopilot.steer(&boat, &mut input);
+
+		assert!(wrap_angle(input.target_heading - PI / 2.0).abs() < 1e-5, "heading {}", input.target_heading);
+		assert!(input.target_speed >= MIN_SAIL && input.target_speed < 1.0);
+		assert_eq!(autopilot.waypoints().len(), 2);
+	}
+
+	#[test]
+	fn moves_on_from_reached_waypoints() {
+		let mut boat = BoatSim::new(0.0);
+		let mut input = BoatInput::new(0.0);
+
+		let mut autopilot = route(&[Vec2::new(5.0, 0.0), Vec2::new(5.5, 0.0), Vec2::new(5.0, 10.0)]);
+		boat.position = Vec2::new(4.0, 0.5);
+		autopilot.steer(&boat, &mut input);
+
+		// Both of the first two are inside WAYPOINT_RADIUS
+		assert_eq!(autopilot.waypoints().len(), 1);
+		assert!(wrap_angle(input.target_heading + PI / 2.0).abs() < 0.2, "heading {}", input.target_heading);
+		assert!(input.target_speed > 0.0);
+	}
+
+	#[test]
+	fn sails_the_route_and_drops_the_sail() {
+		let mut wind = Wind::new(0, PI / 4.0);
+		wind.strength = 1.0;
+
+		let mut boat = BoatSim::new(0.0);
+		let mut input = BoatInput::new(0.0);
+		let waypoints = [Vec2::new(12.0, 0.0), Vec2::new(12.0, -12.0)];
+		let mut autopilot = route(&waypoints);
+
+		let mut closest_to_first = ::std::f32::INFINITY;
+		let mut steps = 0;
+
+		while autopilot.is_engaged() {
+			assert!(steps < 60 * 60, "still sailing after a minute, at {:?}", boat.position);
+
+			autopilot.steer(&boat, &mut input);
+			boat.step(&input, &CalmSea, &wind, &(), DT);
+
+			closest_to_first = closest_to_first.min((boat.position - waypoints[0]).length());
+			steps += 1;
+		}
+
+		assert!(closest_to_first < WAYPOINT_RADIUS);
+		assert!((boat.position - waypoints[1]).length() < ARRIVAL_RADIUS + 0.1, "stopped at {:?}", boat.position);
+		assert_eq!(input.target_speed, 0.0);
+
+		// And nothing changes once it's arrived
+		let before = input.target_heading;
+		autopilot.steer(&boat, &mut input);
+		assert_eq!(input.target_heading, before);
+		assert_eq!(input.target_speed, 0.0);
+	}
+}
@@ -0,0 +1,58 @@
+#![allow(non_upper_case_globals, non_camel_case_types, non_snake_case, dead_code)]
+
+// Just enough EGL to get a GLES2 context on an X11 window, for the windowed
+// native platform
+
+use std::os::raw::{c_int, c_uint, c_ulong, c_void};
+
+pub type EGLDisplay = *mut c_void;
+pub type EGLConfig = *mut c_void;
+pub type EGLSurface = *mut c_void;
+pub type EGLContext = *mut c_void;
+pub type EGLNativeDisplayType = *mut c_void;
+pub type EGLNativeWindowType = c_ulong;
+pub type EGLint = c_int;
+pub type EGLenum = c_uint;
+pub type EGLBoolean = c_uint;
+
+pub const EGL_FALSE: EGLBoolean = 0;
+pub const EGL_TRUE: EGLBoolean = 1;
+
+pub const EGL_NO_CONTEXT: EGLContext = 0 as EGLContext;
+pub const EGL_NO_SURFACE: EGLSurface = 0 as EGLSurface;
+pub const EGL_NO_DISPLAY: EGLDisplay = 0 as EGLDisplay;
+
+pub const EGL_ALPHA_SIZE: EGLint = 0x3021;
+pub const EGL_BLUE_SIZE: EGLint = 0x3022;
+pub const EGL_GREEN_SIZE: EGLint = 0x3023;
+pub const EGL_RED_SIZE: EGLint = 0x3024;
+pub const EGL_DEPTH_SIZE: EGLint = 0x3025;
+pub const EGL_SAMPLES: EGLint = 0x3031;
+pub const EGL_SAMPLE_BUFFERS: EGLint = 0x3032;
+pub const EGL_SURFACE_TYPE: EGLint = 0x3033;
+pub const EGL_NONE: EGLint = 0x3038;
+pub const EGL_RENDERABLE_TYPE: EGLint = 0x3040;
+pub const EGL_CONTEXT_CLIENT_VERSION: EGLint = 0x3098;
+
+pub const EGL_WINDOW_BIT: EGLint = 0x0004;
+pub const EGL_OPENGL_ES2_BIT: EGLint = 0x0004;
+pub const EGL_OPENGL_ES_API: EGLenum = 0x30A0;
+
+#[link(name = "EGL")]
+extern "C" {
+	pub fn eglGetDisplay(display: EGLNativeDisplayType) -> EGLDisplay;
+	pub fn eglInitialize(display: EGLDisplay, major: *mut EGLint, minor: *mut EGLint) -> EGLBoolean;
+	pub fn eglTerminate(display: EGLDisplay) -> EGLBoolean;
+	pub fn eglGetError() -> EGLint;
+	pub fn eglBindAPI(api: EGLenum) -> EGLBoolean;
+
+	pub fn eglChooseConfig(display: EGLDisplay, attribs: *const EGLint, configs: *mut EGLConfig, size: EGLint, count: *mut EGLint) -> EGLBoolean;
+	pub fn eglCreateWindowSurface(display: EGLDisplay, config: EGLConfig, window: EGLNativeWindowType, attribs: *const EGLint) -> EGLSurface;
+	pub fn eglDestroySurface(display: EGLDisplay, surface: EGLSurface) -> EGLBoolean;
+	pub fn eglCreateContext(display: EGLDisplay, config: EGLConfig, share: EGLContext, attribs: *const EGLint) -> EGLContext;
+	pub fn eglDestroyContext(display: EGLDisplay, context: EGLContext) -> EGLBoolean;
+
+	pub fn eglMakeCurrent(display: EGLDisplay, draw: EGLSurface, read: EGLSurface, context: EGLContext) -> EGLBoolean;
+	pub fn eglSwapInterval(display: EGLDisplay, interval: EGLint) -> EGLBoolean;
+	pub fn eglSwapBuffers(display: EGLDisplay, surface: EGLSurface) -> EGLBoolean;
+}
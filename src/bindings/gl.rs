@@ -416,6 +416,10 @@ pub const VERTEX_SHADER: GLenum = 0x8B31;
 pub const VIEWPORT: GLenum = 0x0BA2;
 pub const ZERO: GLenum = 0;
 
+#[cfg(not(target_os = "emscripten"))]
+pub use bindings::gl_dispatch::*;
+
+#[cfg(target_os = "emscripten")]
 extern "system" {
 #[link_name="glActiveTexture"]              pub fn ActiveTexture(texture: GLenum);
 #[link_name="glAttachShader"]               pub fn AttachShader(program: u32, shader: u32);
@@ -0,0 +1,301 @@
+#![allow(non_snake_case, dead_code)]
+
+// Off the web there is no GL context to link against, so every gl:: call the
+// rendering module makes is routed to whatever GlBackend is installed on the
+// current thread instead. Only the subset of GLES2 the crate actually uses is
+// covered here - anything new needs adding to both this file and GlBackend.
+
+use std::cell::RefCell;
+use std::ffi::CStr;
+use std::mem;
+use std::slice;
+
+use bindings::gl::*;
+
+pub trait GlBackend {
+	fn gen_buffer(&mut self) -> u32;
+	fn gen_texture(&mut self) -> u32;
+	fn gen_framebuffer(&mut self) -> u32;
+	fn create_shader(&mut self, kind: GLenum) -> u32;
+	fn create_program(&mut self) -> u32;
+
+	fn delete_buffer(&mut self, _buffer: u32) {}
+	fn delete_texture(&mut self, _texture: u32) {}
+	fn delete_framebuffer(&mut self, _framebuffer: u32) {}
+	fn delete_shader(&mut self, _shader: u32) {}
+	fn delete_program(&mut self, _program: u32) {}
+
+	fn enable(&mut self, _cap: GLenum) {}
+	fn disable(&mut self, _cap: GLenum) {}
+	fn blend_equation(&mut self, _mode: GLenum) {}
+	fn blend_func(&mut self, _sfactor: GLenum, _dfactor: GLenum) {}
+	fn depth_func(&mut self, _func: GLenum) {}
+	fn depth_mask(&mut self, _flag: bool) {}
+	fn front_face(&mut self, _mode: GLenum) {}
+	fn line_width(&mut self, _width: f32) {}
+	fn viewport(&mut self, _x: i32, _y: i32, _width: i32, _height: i32) {}
+	fn clear_color(&mut self, _r: f32, _g: f32, _b: f32, _a: f32) {}
+	fn clear(&mut self, _mask: GLbitfield) {}
+
+	fn bind_buffer(&mut self, _target: GLenum, _buffer: u32) {}
+	fn buffer_data(&mut self, _target: GLenum, _data: &[u8], _usage: GLenum) {}
+
+	fn active_texture(&mut self, _unit: GLenum) {}
+	fn bind_texture(&mut self, _target: GLenum, _texture: u32) {}
+	fn tex_parameter(&mut self, _target: GLenum, _pname: GLenum, _param: i32) {}
+	fn tex_image_2d(&mut self, _target: GLenum, _level: i32, _internal_format: i32, _width: i32, _height: i32,
+		_format: GLenum, _kind: GLenum, _pixels: Option<&[u8]>) {}
+
+	fn bind_framebuffer(&mut self, _target: GLenum, _framebuffer: u32) {}
+	fn framebuffer_texture_2d(&mut self, _target: GLenum, _attachment: GLenum, _textarget: GLenum, _texture: u32, _level: i32) {}
+
+	fn shader_source(&mut self, _shader: u32, _source: &str) {}
+	fn compile_shader(&mut self, _shader: u32) {}
+	fn attach_shader(&mut self, _program: u32, _shader: u32) {}
+	fn link_program(&mut self, _program: u32) {}
+	fn use_program(&mut self, _program: u32) {}
+
+	fn get_shader_parameter(&mut self, _shader: u32, pname: GLenum) -> i32 {
+		if pname == COMPILE_STATUS { 1 } else { 0 }
+	}
+	fn get_program_parameter(&mut self, _program: u32, pname: GLenum) -> i32 {
+		if pname == LINK_STATUS { 1 } else { 0 }
+	}
+	fn get_shader_info_log(&mut self, _shader: u32) -> String { String::new() }
+	fn get_program_info_log(&mut self, _program: u32) -> String { String::new() }
+	fn get_integer(&mut self, _pname: GLenum) -> i32 { 0 }
+	fn get_error(&mut self) -> GLenum { NO_ERROR }
+
+	fn get_uniform_location(&mut self, _program: u32, _name: &str) -> i32 { -1 }
+	fn uniform_f32s(&mut self, _location: i32, _values: &[f32]) {}
+	fn uniform_i32(&mut self, _location: i32, _value: i32) {}
+	fn uniform_matrix4(&mut self, _location: i32, _transpose: bool, _values: &[f32]) {}
+
+	fn enable_vertex_attrib_array(&mut self, _index: u32) {}
+	fn disable_vertex_attrib_array(&mut self, _index: u32) {}
+	fn vertex_attrib_pointer(&mut self, _index: u32, _size: i32, _kind: GLenum, _normalized: bool, _stride: i32, _offset: usize) {}
+
+	fn draw_arrays(&mut self, _mode: GLenum, _first: i32, _count: i32) {}
+	fn draw_elements(&mut self, _mode: GLenum, _count: i32, _kind: GLenum, _offset: usize) {}
+
+	fn read_pixels(&mut self, _x: i32, _y: i32, _width: i32, _height: i32, _format: GLenum, _kind: GLenum, _pixels: &mut [u8]) {}
+}
+
+thread_local! {
+	static BACKEND: RefCell<Option<Box<GlBackend>>> = RefCell::new(None);
+}
+
+/// Installs a backend for the current thread, returning the previous one
+pub fn set_backend(backend: Box<GlBackend>) -> Option<Box<GlBackend>> {
+	BACKEND.with(|b| mem::replace(&mut *b.borrow_mut(), Some(backend)))
+}
+
+pub fn take_backend() -> Option<Box<GlBackend>> {
+	BACKEND.with(|b| b.borrow_mut().take())
+}
+
+fn with_backend<F, R>(f: F) -> R where F: FnOnce(&mut GlBackend) -> R {
+	BACKEND.with(|b| {
+		let mut backend = b.borrow_mut();
+		let backend = backend.as_mut().expect("No GL backend installed on this thread");
+		f(&mut **backend)
+	})
+}
+
+/// For deletes, which can happen as things are dropped after the backend has
+/// been taken away. The objects went with it, so there's nothing left to do
+fn with_backend_if_installed<F>(f: F) where F: FnOnce(&mut GlBackend) {
+	BACKEND.with(|b| {
+		if let Some(ref mut backend) = *b.borrow_mut() {
+			f(&mut **backend)
+		}
+	})
+}
+
+fn pixel_size(format: GLenum, kind: GLenum) -> usize {
+	let components = match format {
+		RGBA => 4,
+		RGB => 3,
+		LUMINANCE_ALPHA => 2,
+		_ => 1,
+	};
+
+	match kind {
+		UNSIGNED_BYTE => components,
+		UNSIGNED_SHORT => components * 2,
+		_ => components * 4,
+	}
+}
+
+pub unsafe fn ActiveTexture(texture: GLenum) { with_backend(|b| b.active_texture(texture)) }
+pub unsafe fn AttachShader(program: u32, shader: u32) { with_backend(|b| b.attach_shader(program, shader)) }
+pub unsafe fn BindBuffer(target: GLenum, buffer: u32) { with_backend(|b| b.bind_buffer(target, buffer)) }
+pub unsafe fn BindFramebuffer(target: GLenum, framebuffer: u32) { with_backend(|b| b.bind_framebuffer(target, framebuffer)) }
+pub unsafe fn BindTexture(target: GLenum, texture: u32) { with_backend(|b| b.bind_texture(target, texture)) }
+pub unsafe fn BlendEquation(mode: GLenum) { with_backend(|b| b.blend_equation(mode)) }
+pub unsafe fn BlendFunc(sfactor: GLenum, dfactor: GLenum) { with_backend(|b| b.blend_func(sfactor, dfactor)) }
+
+pub unsafe fn BufferData(target: GLenum, size: i32, data: *const GLvoid, usage: GLenum) {
+	if data.is_null() {
+		let zeroed = vec![0u8; size as usize];
+		with_backend(|b| b.buffer_data(target, &zeroed, usage))
+	} else {
+		let data = slice::from_raw_parts(data as *const u8, size as usize);
+		with_backend(|b| b.buffer_data(target, data, usage))
+	}
+}
+
+pub unsafe fn Clear(mask: GLbitfield) { with_backend(|b| b.clear(mask)) }
+pub unsafe fn ClearColor(red: f32, green: f32, blue: f32, alpha: f32) { with_backend(|b| b.clear_color(red, green, blue, alpha)) }
+pub unsafe fn CompileShader(shader: u32) { with_backend(|b| b.compile_shader(shader)) }
+pub unsafe fn CreateProgram() -> u32 { with_backend(|b| b.create_program()) }
+pub unsafe fn CreateShader(type_: GLenum) -> u32 { with_backend(|b| b.create_shader(type_)) }
+
+pub unsafe fn DeleteBuffers(n: i32, buffers: *const u32) {
+	for &buffer in slice::from_raw_parts(buffers, n as usize) {
+		with_backend_if_installed(|b| b.delete_buffer(buffer))
+	}
+}
+
+pub unsafe fn DeleteFramebuffers(n: i32, framebuffers: *const u32) {
+	for &framebuffer in slice::from_raw_parts(framebuffers, n as usize) {
+		with_backend_if_installed(|b| b.delete_framebuffer(framebuffer))
+	}
+}
+
+pub unsafe fn DeleteTextures(n: i32, textures: *const u32) {
+	for &texture in slice::from_raw_parts(textures, n as usize) {
+		with_backend_if_installed(|b| b.delete_texture(texture))
+	}
+}
+
+pub unsafe fn DeleteProgram(program: u32) { with_backend_if_installed(|b| b.delete_program(program)) }
+pub unsafe fn DeleteShader(shader: u32) { with_backend_if_installed(|b| b.delete_shader(shader)) }
+pub unsafe fn DepthFunc(func: GLenum) { with_backend(|b| b.depth_func(func)) }
+pub unsafe fn DepthMask(flag: GLboolean) { with_backend(|b| b.depth_mask(flag != FALSE)) }
+pub unsafe fn Disable(cap: GLenum) { with_backend(|b| b.disable(cap)) }
+pub unsafe fn DisableVertexAttribArray(index: u32) { with_backend(|b| b.disable_vertex_attrib_array(index)) }
+pub unsafe fn DrawArrays(mode: GLenum, first: i32, count: i32) { with_backend(|b| b.draw_arrays(mode, first, count)) }
+
+pub unsafe fn DrawElements(mode: GLenum, count: i32, type_: GLenum, indices: *const GLvoid) {
+	with_backend(|b| b.draw_elements(mode, count, type_, indices as usize))
+}
+
+pub unsafe fn Enable(cap: GLenum) { with_backend(|b| b.enable(cap)) }
+pub unsafe fn EnableVertexAttribArray(index: u32) { with_backend(|b| b.enable_vertex_attrib_array(index)) }
+
+pub unsafe fn FramebufferTexture2D(target: GLenum, attachment: GLenum, textarget: GLenum, texture: u32, level: i32) {
+	with_backend(|b| b.framebuffer_texture_2d(target, attachment, textarget, texture, level))
+}
+
+pub unsafe fn FrontFace(mode: GLenum) { with_backend(|b| b.front_face(mode)) }
+
+pub unsafe fn GenBuffers(n: i32, buffers: *mut u32) {
+	for buffer in slice::from_raw_parts_mut(buffers, n as usize) {
+		*buffer = with_backend(|b| b.gen_buffer());
+	}
+}
+
+pub unsafe fn GenFramebuffers(n: i32, framebuffers: *mut u32) {
+	for framebuffer in slice::from_raw_parts_mut(framebuffers, n as usize) {
+		*framebuffer = with_backend(|b| b.gen_framebuffer());
+	}
+}
+
+pub unsafe fn GenTextures(n: i32, textures: *mut u32) {
+	for texture in slice::from_raw_parts_mut(textures, n as usize) {
+		*texture = with_backend(|b| b.gen_texture());
+	}
+}
+
+pub unsafe fn GetError() -> GLenum { with_backend(|b| b.get_error()) }
+pub unsafe fn GetIntegerv(pname: GLenum, data: *mut i32) { *data = with_backend(|b| b.get_integer(pname)) }
+pub unsafe fn GetProgramiv(program: u32, pname: GLenum, params: *mut i32) { *params = with_backend(|b| b.get_program_parameter(program, pname)) }
+pub unsafe fn GetShaderiv(shader: u32, pname: GLenum, params: *mut i32) { *params = with_backend(|b| b.get_shader_parameter(shader, pname)) }
+
+unsafe fn write_info_log(log: String, buf_size: i32, length: *mut i32, info_log: *mut GLchar) {
+	if buf_size <= 0 { return }
+
+	let len = log.len().min(buf_size as usize - 1);
+	let out = slice::from_raw_parts_mut(info_log as *mut u8, len + 1);
+	out[..len].copy_from_slice(&log.as_bytes()[..len]);
+	out[len] = 0;
+
+	if !length.is_null() {
+		*length = len as i32;
+	}
+}
+
+pub unsafe fn GetProgramInfoLog(program: u32, bufSize: i32, length: *mut i32, infoLog: *mut GLchar) {
+	let log = with_backend(|b| b.get_program_info_log(program));
+	write_info_log(log, bufSize, length, infoLog);
+}
+
+pub unsafe fn GetShaderInfoLog(shader: u32, bufSize: i32, length: *mut i32, infoLog: *mut GLchar) {
+	let log = with_backend(|b| b.get_shader_info_log(shader));
+	write_info_log(log, bufSize, length, infoLog);
+}
+
+pub unsafe fn GetUniformLocation(program: u32, name: *const GLchar) -> i32 {
+	let name = CStr::from_ptr(name).to_string_lossy();
+	with_backend(|b| b.get_uniform_location(program, &name))
+}
+
+pub unsafe fn LineWidth(width: f32) { with_backend(|b| b.line_width(width)) }
+pub unsafe fn LinkProgram(program: u32) { with_backend(|b| b.link_program(program)) }
+
+pub unsafe fn ReadPixels(x: i32, y: i32, width: i32, height: i32, format: GLenum, type_: GLenum, pixels: *mut GLvoid) {
+	let size = (width * height) as usize * pixel_size(format, type_);
+	let pixels = slice::from_raw_parts_mut(pixels as *mut u8, size);
+	with_backend(|b| b.read_pixels(x, y, width, height, format, type_, pixels))
+}
+
+pub unsafe fn ShaderSource(shader: u32, count: i32, string: *const *const GLchar, length: *const i32) {
+	let mut source = String::new();
+
+	for i in 0..count as isize {
+		let s = *string.offset(i);
+
+		if length.is_null() || *length.offset(i) < 0 {
+			source.push_str(&CStr::from_ptr(s).to_string_lossy());
+		} else {
+			let bytes = slice::from_raw_parts(s as *const u8, *length.offset(i) as usize);
+			source.push_str(&String::from_utf8_lossy(bytes));
+		}
+	}
+
+	with_backend(|b| b.shader_source(shader, &source))
+}
+
+pub unsafe fn TexImage2D(target: GLenum, level: i32, internalformat: i32, width: i32, height: i32, border: i32, format: GLenum, type_: GLenum, pixels: *const GLvoid) {
+	let _ = border;
+
+	if pixels.is_null() {
+		with_backend(|b| b.tex_image_2d(target, level, internalformat, width, height, format, type_, None))
+	} else {
+		let size = (width * height) as usize * pixel_size(format, type_);
+		let pixels = slice::from_raw_parts(pixels as *const u8, size);
+		with_backend(|b| b.tex_image_2d(target, level, internalformat, width, height, format, type_, Some(pixels)))
+	}
+}
+
+pub unsafe fn TexParameteri(target: GLenum, pname: GLenum, param: i32) { with_backend(|b| b.tex_parameter(target, pname, param)) }
+
+pub unsafe fn Uniform1f(location: i32, v0: f32) { with_backend(|b| b.uniform_f32s(location, &[v0])) }
+pub unsafe fn Uniform1i(location: i32, v0: i32) { with_backend(|b| b.uniform_i32(location, v0)) }
+pub unsafe fn Uniform2f(location: i32, v0: f32, v1: f32) { with_backend(|b| b.uniform_f32s(location, &[v0, v1])) }
+pub unsafe fn Uniform3f(location: i32, v0: f32, v1: f32, v2: f32) { with_backend(|b| b.uniform_f32s(location, &[v0, v1, v2])) }
+pub unsafe fn Uniform4f(location: i32, v0: f32, v1: f32, v2: f32, v3: f32) { with_backend(|b| b.uniform_f32s(location, &[v0, v1, v2, v3])) }
+
+pub unsafe fn UniformMatrix4fv(location: i32, count: i32, transpose: GLboolean, value: *const f32) {
+	let values = slice::from_raw_parts(value, count as usize * 16);
+	with_backend(|b| b.uniform_matrix4(location, transpose != FALSE, values))
+}
+
+pub unsafe fn UseProgram(program: u32) { with_backend(|b| b.use_program(program)) }
+
+pub unsafe fn VertexAttribPointer(index: u32, size: i32, type_: GLenum, normalized: GLboolean, stride: i32, pointer: *const GLvoid) {
+	with_backend(|b| b.vertex_attrib_pointer(index, size, type_, normalized != FALSE, stride, pointer as usize))
+}
+
+pub unsafe fn Viewport(x: i32, y: i32, width: i32, height: i32) { with_backend(|b| b.viewport(x, y, width, height)) }
@@ -1,5 +1,15 @@
-
+#[cfg(target_os = "emscripten")]
 #[macro_use]
 pub mod emscripten;
+#[cfg(target_os = "emscripten")]
 pub mod console;
-pub mod gl;
\ No newline at end of file
+
+pub mod gl;
+
+#[cfg(not(target_os = "emscripten"))]
+pub mod gl_dispatch;
+
+#[cfg(all(feature = "windowed", not(target_os = "emscripten")))]
+pub mod xlib;
+#[cfg(all(feature = "windowed", not(target_os = "emscripten")))]
+pub mod egl;
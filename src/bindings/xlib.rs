@@ -0,0 +1,188 @@
+#![allow(non_upper_case_globals, non_camel_case_types, non_snake_case, dead_code)]
+
+// Just enough of Xlib to open a window and read input from it, for the
+// windowed native platform
+
+use std::os::raw::{c_char, c_int, c_long, c_uint, c_ulong, c_void};
+
+pub type Display = c_void;
+pub type Window = c_ulong;
+pub type Atom = c_ulong;
+pub type KeySym = c_ulong;
+pub type Bool = c_int;
+
+pub const False: Bool = 0;
+pub const True: Bool = 1;
+
+pub const KeyPress: c_int = 2;
+pub const KeyRelease: c_int = 3;
+pub const ButtonPress: c_int = 4;
+pub const ButtonRelease: c_int = 5;
+pub const MotionNotify: c_int = 6;
+pub const ConfigureNotify: c_int = 22;
+pub const ClientMessage: c_int = 33;
+
+pub const KeyPressMask: c_long = 1 << 0;
+pub const KeyReleaseMask: c_long = 1 << 1;
+pub const ButtonPressMask: c_long = 1 << 2;
+pub const ButtonReleaseMask: c_long = 1 << 3;
+pub const PointerMotionMask: c_long = 1 << 6;
+pub const StructureNotifyMask: c_long = 1 << 17;
+pub const SubstructureNotifyMask: c_long = 1 << 19;
+pub const SubstructureRedirectMask: c_long = 1 << 20;
+
+pub const ControlMask: c_uint = 1 << 2;
+pub const Mod1Mask: c_uint = 1 << 3;
+pub const Mod4Mask: c_uint = 1 << 6;
+
+pub const Button1: c_uint = 1;
+pub const Button4: c_uint = 4;
+pub const Button5: c_uint = 5;
+
+/// Big enough for any event. Read it through one of the specific event types
+/// below once `kind` says which it is
+#[repr(C)]
+pub struct XEvent {
+	pub pad: [c_long; 24],
+}
+
+impl XEvent {
+	pub fn kind(&self) -> c_int {
+		unsafe { *(self as *const XEvent as *const c_int) }
+	}
+
+	pub unsafe fn cast<T>(&self) -> &T {
+		&*(self as *const XEvent as *const T)
+	}
+}
+
+#[repr(C)]
+pub struct XButtonEvent {
+	pub kind: c_int,
+	pub serial: c_ulong,
+	pub send_event: Bool,
+	pub display: *mut Display,
+	pub window: Window,
+	pub root: Window,
+	pub subwindow: Window,
+	pub time: c_ulong,
+	pub x: c_int,
+	pub y: c_int,
+	pub x_root: c_int,
+	pub y_root: c_int,
+	pub state: c_uint,
+	pub button: c_uint,
+	pub same_screen: Bool,
+}
+
+#[repr(C)]
+pub struct XMotionEvent {
+	pub kind: c_int,
+	pub serial: c_ulong,
+	pub send_event: Bool,
+	pub display: *mut Display,
+	pub window: Window,
+	pub root: Window,
+	pub subwindow: Window,
+	pub time: c_ulong,
+	pub x: c_int,
+	pub y: c_int,
+	pub x_root: c_int,
+	pub y_root: c_int,
+	pub state: c_uint,
+	pub is_hint: c_char,
+	pub same_screen: Bool,
+}
+
+#[repr(C)]
+pub struct XKeyEvent {
+	pub kind: c_int,
+	pub serial: c_ulong,
+	pub send_event: Bool,
+	pub display: *mut Display,
+	pub window: Window,
+	pub root: Window,
+	pub subwindow: Window,
+	pub time: c_ulong,
+	pub x: c_int,
+	pub y: c_int,
+	pub x_root: c_int,
+	pub y_root: c_int,
+	pub state: c_uint,
+	pub keycode: c_uint,
+	pub same_screen: Bool,
+}
+
+#[repr(C)]
+pub struct XConfigureEvent {
+	pub kind: c_int,
+	pub serial: c_ulong,
+	pub send_event: Bool,
+	pub display: *mut Display,
+	pub event: Window,
+	pub window: Window,
+	pub x: c_int,
+	pub y: c_int,
+	pub width: c_int,
+	pub height: c_int,
+	pub border_width: c_int,
+	pub above: Window,
+	pub override_redirect: Bool,
+}
+
+#[repr(C)]
+pub struct XClientMessageEvent {
+	pub kind: c_int,
+	pub serial: c_ulong,
+	pub send_event: Bool,
+	pub display: *mut Display,
+	pub window: Window,
+	pub message_type: Atom,
+	pub format: c_int,
+	pub data: [c_long; 5],
+}
+
+// Keysyms for the keys that have DOM codes the input config can bind
+pub const XK_BackSpace: KeySym = 0xff08;
+pub const XK_Tab: KeySym = 0xff09;
+pub const XK_Return: KeySym = 0xff0d;
+pub const XK_Escape: KeySym = 0xff1b;
+pub const XK_Left: KeySym = 0xff51;
+pub const XK_Up: KeySym = 0xff52;
+pub const XK_Right: KeySym = 0xff53;
+pub const XK_Down: KeySym = 0xff54;
+pub const XK_Shift_L: KeySym = 0xffe1;
+pub const XK_Shift_R: KeySym = 0xffe2;
+pub const XK_Control_L: KeySym = 0xffe3;
+pub const XK_Control_R: KeySym = 0xffe4;
+pub const XK_Alt_L: KeySym = 0xffe9;
+pub const XK_Alt_R: KeySym = 0xffea;
+pub const XK_space: KeySym = 0x20;
+pub const XK_0: KeySym = 0x30;
+pub const XK_9: KeySym = 0x39;
+pub const XK_a: KeySym = 0x61;
+pub const XK_z: KeySym = 0x7a;
+
+#[link(name = "X11")]
+extern "C" {
+	pub fn XOpenDisplay(name: *const c_char) -> *mut Display;
+	pub fn XCloseDisplay(display: *mut Display) -> c_int;
+	pub fn XDefaultRootWindow(display: *mut Display) -> Window;
+
+	pub fn XCreateSimpleWindow(display: *mut Display, parent: Window, x: c_int, y: c_int, width: c_uint, height: c_uint,
+		border_width: c_uint, border: c_ulong, background: c_ulong) -> Window;
+	pub fn XDestroyWindow(display: *mut Display, window: Window) -> c_int;
+	pub fn XStoreName(display: *mut Display, window: Window, name: *const c_char) -> c_int;
+	pub fn XSelectInput(display: *mut Display, window: Window, mask: c_long) -> c_int;
+	pub fn XMapWindow(display: *mut Display, window: Window) -> c_int;
+
+	pub fn XInternAtom(display: *mut Display, name: *const c_char, only_if_exists: Bool) -> Atom;
+	pub fn XSetWMProtocols(display: *mut Display, window: Window, protocols: *mut Atom, count: c_int) -> c_int;
+	pub fn XSendEvent(display: *mut Display, window: Window, propagate: Bool, mask: c_long, event: *mut XEvent) -> c_int;
+
+	pub fn XPending(display: *mut Display) -> c_int;
+	pub fn XNextEvent(display: *mut Display, event: *mut XEvent) -> c_int;
+	pub fn XLookupKeysym(event: *mut XKeyEvent, index: c_int) -> KeySym;
+	pub fn XkbSetDetectableAutoRepeat(display: *mut Display, detectable: Bool, supported: *mut Bool) -> Bool;
+	pub fn XFlush(display: *mut Display) -> c_int;
+}
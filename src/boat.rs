@@ -0,0 +1,371 @@
+use common::*;
+use collision::{self, CollisionWorld, HULL_FOOTPRINT};
+use ocean::WaveField;
+use sail::{self, TackState};
+use wind::Wind;
+use rendering::mesh_builder::*;
+
+// Points on the hull the water height is sampled at, in boat space (x, z).
+// The bow points along +x, so +z is starboard
+const HULL_BOW: (f32, f32) = (0.8, 0.0);
+const HULL_STERN: (f32, f32) = (-0.5, 0.0);
+const HULL_PORT: (f32, f32) = (0.0,-0.35);
+const HULL_STARBOARD: (f32, f32) = (0.0, 0.35);
+
+const BUOYANCY_STIFFNESS: f32 = 40.0;
+const BUOYANCY_DAMPING: f32 = 8.0;
+
+/// World units per second at full speed
+const BOAT_TOP_SPEED: f32 = 3.0;
+/// How quickly the water drags the boat's velocity into line with its heading
+const WATER_DRAG: f32 = 1.5;
+/// How quickly the boat loses way while scraping along land or obstacles
+const GROUNDING_DRAG: f32 = 3.0;
+
+/// What the player (or anything else driving the boat) wants it to do
+#[derive(Copy, Clone, Debug)]
+pub struct BoatInput {
+	pub target_heading: f32,
+	/// How much sail to put up, from 0 to 1. How fast that actually
+	/// makes the boat go depends on the wind
+	pub target_speed: f32,
+}
+
+impl BoatInput {
+	pub fn new(target_heading: f32) -> Self {
+		BoatInput { target_heading, target_speed: 0.0 }
+	}
+}
+
+/// Owns all of the boat's simulation state. Knows nothing about rendering
+/// or the platform, so it can be stepped anywhere.
+#[derive(Copy, Clone, Debug)]
+pub struct BoatSim {
+	pub heading: f32,
+	pub heading_rate: f32,
+	pub speed: f32,
+
+	pub position: Vec2,
+	pub velocity: Vec2,
+
+	pub heave: f32,
+	pub pitch: f32,
+	pub roll: f32,
+
+	heave_vel: f32,
+	pitch_vel: f32,
+	roll_vel: f32,
+
+	pub wave_phase: f32,
+
+	pub tack: TackState,
+}
+
+impl BoatSim {
+	pub fn new(heading: f32) -> Self {
+		BoatSim {
+			heading,
+			heading_rate: 0.0,
+			speed: 0.0,
+
+			position: Vec2::zero(),
+			velocity: Vec2::zero(),
+
+			heave: 0.0,
+			pitch: 0.0,
+			roll: 0.0,
+
+			heave_vel: 0.0,
+			pitch_vel: 0.0,
+			roll_vel: 0.0,
+
+			wave_phase: 0.0,
+
+			tack: TackState::new(),
+		}
+	}
+
+	pub fn step<W, C>(&mut self, input: &BoatInput, waves: &W, wind: &Wind, world: &C, dt: f32)
+		where W: WaveField, C: CollisionWorld {
+
+		let target_speed = input.target_speed * sail::thrust(self.heading, wind);
+		self.speed += (target_speed - self.speed) * dt;
+
+		let target_heading = self.tack.resolve_heading(input.target_heading, self.heading, wind, dt);
+
+		let mut heading_diff = target_heading - self.heading;
+		while heading_diff.abs() > PI {
+			heading_diff -= 2.0 * PI * heading_diff.signum();
+		}
+
+		let heading_factor = 2.0 * dt;
+
+		self.heading_rate *= 1.0 - heading_factor;
+		self.heading_rate += heading_diff.max(-PI/6.0).min(PI/6.0) * heading_factor;
+		self.heading += (1.0 - (1.0 - self.heading_rate/PI).powf(1.2)) * PI * dt;
+
+		let target_velocity = self.forward() * self.speed * BOAT_TOP_SPEED;
+		self.velocity = self.velocity + (target_velocity - self.velocity) * (WATER_DRAG * dt).min(1.0);
+		self.position = self.position + self.velocity * dt;
+
+		self.resolve_collisions(world, dt);
+
+		self.wave_phase += dt;
+
+		self.update_buoyancy(waves, dt);
+	}
+
+	/// Pushes the hull back out of anything it has run into, and takes away
+	/// any velocity into it, so the boat slides along the shore instead of
+	/// sailing through it
+	fn resolve_collisions<C>(&mut self, world: &C, dt: f32) where C: CollisionWorld {
+		let footprint: Vec<_> = HULL_FOOTPRINT.iter()
+			.map(|&(x, z, radius)| (self.hull_to_world(x, z), radius))
+			.collect();
+
+		// Backing out is the best guess at the way clear of anything hit dead on
+		let backwards = self.forward() * -1.0;
+
+		let contact = match collision::resolve(&footprint, backwards, world) {
+			Some(contact) => contact,
+			None => return,
+		};
+
+		self.position = self.position + contact.push;
+
+		let into = self.velocity.dot(contact.normal);
+		if into < 0.0 {
+			self.velocity = self.velocity - contact.normal * into;
+		}
+
+		let drag = 1.0 - (GROUNDING_DRAG * dt).min(1.0);
+		self.velocity = self.velocity * drag;
+		self.speed *= drag;
+	}
+
+	fn update_buoyancy<W>(&mut self, waves: &W, dt: f32) where W: WaveField {
+		let bow = self.sample_hull(waves, HULL_BOW);
+		let stern = self.sample_hull(waves, HULL_STERN);
+		let port = self.sample_hull(waves, HULL_PORT);
+		let starboard = self.sample_hull(waves, HULL_STARBOARD);
+
+		let target_heave = (bow + stern + port + starboard) / 4.0;
+		let target_pitch = (bow - stern).atan2(HULL_BOW.0 - HULL_STERN.0);
+		let target_roll = (port - starboard).atan2(HULL_STARBOARD.1 - HULL_PORT.1);
+
+		spring(&mut self.heave, &mut self.heave_vel, target_heave, dt);
+		spring(&mut self.pitch, &mut self.pitch_vel, target_pitch, dt);
+		spring(&mut self.roll, &mut self.roll_vel, target_roll, dt);
+	}
+
+	fn sample_hull<W>(&self, waves: &W, (x, z): (f32, f32)) -> f32 where W: WaveField {
+		waves.height_at(self.hull_to_world(x, z), self.wave_phase)
+	}
+
+	/// Transforms a point on the hull into a position on the sea
+	pub fn hull_to_world(&self, x: f32, z: f32) -> Vec2 {
+		let (s, c) = self.heading.sin_cos();
+		self.position + Vec2::new(x*c + z*s, z*c - x*s)
+	}
+
+	/// Unit vector pointing out of the bow, on the XZ plane
+	pub fn forward(&self) -> Vec2 {
+		let (s, c) = self.heading.sin_cos();
+		Vec2::new(c, -s)
+	}
+
+	/// Blends between two simulation states, for rendering in between fixed steps
+	pub fn interpolate(&self, next: &BoatSim, alpha: f32) -> BoatSim {
+		let lerp = |a: f32, b: f32| a + (b - a) * alpha;
+
+		BoatSim {
+			heading: lerp(self.heading, next.heading),
+			heading_rate: lerp(self.heading_rate, next.heading_rate),
+			speed: lerp(self.speed, next.speed),
+
+			position: self.position + (next.position - self.position) * alpha,
+			velocity: self.velocity + (next.velocity - self.velocity) * alpha,
+
+			heave: lerp(self.heave, next.heave),
+			pitch: lerp(self.pitch, next.pitch),
+			roll: lerp(self.roll, next.roll),
+
+			heave_vel: next.heave_vel,
+			pitch_vel: next.pitch_vel,
+			roll_vel: next.roll_vel,
+
+			wave_phase: lerp(self.wave_phase, next.wave_phase),
+
+			tack: next.tack,
+		}
+	}
+
+	pub fn model_mat(&self) -> Mat4 {
+		Mat4::translate(Vec3::new(self.position.x, self.heave, self.position.y))
+			* Mat4::yrot(self.heading)
+			* Mat4::xrot(self.roll)
+			* Mat4::zrot(self.pitch)
+	}
+}
+
+/// Where the mast stands in boat space. The sail swings about it
+fn mast_offset() -> Vec3 { Vec3::new(0.5, 0.0, 0.0) }
+
+/// Builds the hull and mast into `hull`, and the sail into `sail`, since that
+/// swings about the mast on its own. Both are in boat space
+pub fn build_boat_mesh(hull: &mut MeshBuilder<ColorVertex>, sail: &mut MeshBuilder<ColorVertex>) {
+	use rendering::mesh_builder::ColorVertex as V;
+
+	let mast_offset = mast_offset();
+	let color = Color::rgb8(211, 126, 78).into();
+	let color2 = Color::rgb8(187, 97, 53).into();
+
+	let vs = [
+		V(Vec3::new(-0.5, 0.2, 0.5), color),
+		V(Vec3::new( 0.9, 0.2, 0.0), color),
+		V(Vec3::new(-0.5, 0.2,-0.5), color),
+
+		V(Vec3::new(-0.1,-0.3, 0.0), color2),
+	];
+
+	let es = [
+		0, 1, 2,
+
+		0, 3, 1,
+		1, 3, 2,
+		2, 3, 0,
+	];
+
+	hull.add_direct(&vs, &es);
+
+	let mast_color = Color::rgb8(187, 112, 70).into();
+	let sail_color = Color::rgb8(171, 220, 107).into();
+
+	let mast_width = 0.02 * 2.0f32.sqrt();
+	let mast_height = 1.5;
+
+	hull.add_quad(&[
+		V(Vec3::new(-mast_width,        0.18,-mast_width) + mast_offset, mast_color),
+		V(Vec3::new( mast_width,        0.18, mast_width) + mast_offset, mast_color),
+		V(Vec3::new( mast_width, mast_height, mast_width) + mast_offset, mast_color),
+		V(Vec3::new(-mast_width, mast_height,-mast_width) + mast_offset, mast_color),
+	]);
+
+	hull.add_quad(&[
+		V(Vec3::new(-mast_width,        0.18, mast_width) + mast_offset, mast_color),
+		V(Vec3::new( mast_width,        0.18,-mast_width) + mast_offset, mast_color),
+		V(Vec3::new( mast_width, mast_height,-mast_width) + mast_offset, mast_color),
+		V(Vec3::new(-mast_width, mast_height, mast_width) + mast_offset, mast_color),
+	]);
+
+	sail.add_convex_poly(&[
+		V(Vec3::new(-0.0, mast_height, 0.0) + mast_offset, sail_color),
+
+		V(Vec3::new(-0.45, 0.25, 0.0), sail_color),
+		V(Vec3::new(-0.1, 0.25, 0.1), sail_color),
+		V(Vec3::new(-0.0, 0.25, 0.0) + mast_offset, sail_color),
+	]);
+}
+
+/// Places the sail mesh in boat space, with the boom swung out to `boom_angle`
+pub fn sail_mat(boom_angle: f32) -> Mat4 {
+	Mat4::translate(mast_offset())
+		* Mat4::yrot(boom_angle)
+		* Mat4::translate(mast_offset() * -1.0)
+}
+
+/// Damped spring pulling `value` towards `target`, so the hull settles onto
+/// the water rather than snapping to it
+fn spring(value: &mut f32, vel: &mut f32, target: f32, dt: f32) {
+	*vel += ((target - *value) * BUOYANCY_STIFFNESS - *vel * BUOYANCY_DAMPING) * dt;
+	*value += *vel * dt;
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use ocean::CalmSea;
+	use sail::wrap_angle;
+
+	const DT: f32 = 1.0 / 60.0;
+
+	/// A wind that stays put, blowing towards `direction`
+	fn steady_wind(direction: f32, strength: f32) -> Wind {
+		let mut wind = Wind::new(0, direction);
+		wind.direction = direction;
+		wind.strength = strength;
+		wind
+	}
+
+	/// Steps `boat` for `seconds` on calm open water, returning every heading it passed through
+	fn sail(boat: &mut BoatSim, input: &BoatInput, wind: &Wind, seconds: f32) -> Vec<f32> {
+		let steps = (seconds / DT) as usize;
+		let mut headings = Vec::with_capacity(steps);
+
+		for _ in 0..steps {
+			boat.step(input, &CalmSea, wind, &(), DT);
+			headings.push(boat.heading);
+		}
+
+		headings
+	}
+
+	#[test]
+	fn heading_converges_on_target() {
+		// Running downwind the whole way round, so tacking never gets involved
+		let wind = steady_wind(1.0, 1.0);
+		let input = BoatInput { target_heading: 1.0, target_speed: 1.0 };
+		let mut boat = BoatSim::new(0.0);
+
+		sail(&mut boat, &input, &wind, 15.0);
+
+		assert!(wrap_angle(boat.heading - 1.0).abs() < 0.02, "heading {} didn't settle on 1.0", boat.heading);
+		assert!(boat.heading_rate.abs() < 0.02, "still turning at {}", boat.heading_rate);
+	}
+
+	#[test]
+	fn speed_follows_thrust() {
+		// Wind across the beam gives full thrust
+		let wind = steady_wind(PI / 2.0, 0.8);
+		let mut input = BoatInput { target_heading: 0.0, target_speed: 1.0 };
+		let mut boat = BoatSim::new(0.0);
+
+		sail(&mut boat, &input, &wind, 10.0);
+
+		assert!((boat.speed - 0.8).abs() < 0.01, "speed {} should approach the wind strength", boat.speed);
+
+		let expected_velocity = boat.forward() * boat.speed * BOAT_TOP_SPEED;
+		assert!((boat.velocity - expected_velocity).length() < 0.05);
+		assert!(boat.position.x > 0.0 && boat.position.y.abs() < 0.01);
+
+		// Half the sail, half the speed
+		input.target_speed = 0.5;
+		sail(&mut boat, &input, &wind, 10.0);
+		assert!((boat.speed - 0.4).abs() < 0.01, "speed {} should halve", boat.speed);
+
+		input.target_speed = 0.0;
+		sail(&mut boat, &input, &wind, 10.0);
+		assert!(boat.speed < 0.01);
+	}
+
+	#[test]
+	fn turns_the_short_way_across_pi() {
+		let wind = steady_wind(PI, 1.0);
+
+		// Anticlockwise through PI
+		let input = BoatInput { target_heading: -PI + 0.1, target_speed: 1.0 };
+		let mut boat = BoatSim::new(PI - 0.1);
+		let headings = sail(&mut boat, &input, &wind, 15.0);
+
+		assert!(headings.iter().all(|&h| h > PI - 0.15), "turned the long way round");
+		assert!(wrap_angle(boat.heading - input.target_heading).abs() < 0.02);
+
+		// And clockwise back again
+		let input = BoatInput { target_heading: PI - 0.1, target_speed: 1.0 };
+		let mut boat = BoatSim::new(-PI + 0.1);
+		let headings = sail(&mut boat, &input, &wind, 15.0);
+
+		assert!(headings.iter().all(|&h| h < -PI + 0.15), "turned the long way round");
+		assert!(wrap_angle(boat.heading - input.target_heading).abs() < 0.02);
+	}
+}
@@ -0,0 +1,345 @@
+use common::*;
+use rendering::Viewport;
+use sail::wrap_angle;
+
+const CAMERA_PITCH: f32 = PI/8.0;
+const CAMERA_FOV: f32 = PI/4.0;
+const CAMERA_NEAR: f32 = 1.0;
+const CAMERA_FAR: f32 = 100.0;
+
+const CAMERA_MIN_DISTANCE: f32 = 5.0;
+const CAMERA_MAX_DISTANCE: f32 = 30.0;
+
+/// How quickly the camera catches up with where it's meant to be, per second
+const ORBIT_SMOOTHING: f32 = 6.0;
+const FOLLOW_SMOOTHING: f32 = 1.5;
+const FOCUS_SMOOTHING: f32 = 10.0;
+
+/// How far from the centre of the screen, in GL units, to look when turning a
+/// screen space direction into a world space one
+const STEERING_PROBE: f32 = 0.1;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum CameraMode {
+	/// Stays at whatever angle it's been orbited to, only tracking the boat's position
+	Orbit,
+	/// Swings round to sit behind the boat. Orbiting offsets it from there
+	Follow,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Ray {
+	pub origin: Vec3,
+	pub dir: Vec3,
+}
+
+impl Ray {
+	pub fn point_at(&self, t: f32) -> Vec3 {
+		self.origin + self.dir * t
+	}
+
+	/// Where the ray comes down through the horizontal plane at `height`,
+	/// if it ever does
+	pub fn hit_height(&self, height: f32) -> Option<Vec3> {
+		if self.dir.y >= 0.0 { return None }
+
+		let t = (height - self.origin.y) / self.dir.y;
+		if t < 0.0 { return None }
+
+		Some(self.point_at(t))
+	}
+}
+
+/// Looks down at a point on the sea from some distance and angle. Orbiting and
+/// zooming set where the camera wants to be, and it eases there over time
+pub struct Camera {
+	pub mode: CameraMode,
+	pub viewport: Viewport,
+
+	yaw: f32,
+	distance: f32,
+	focus: Vec2,
+
+	/// The yaw to orbit to, or in Follow mode the offset from behind the boat
+	target_yaw: f32,
+	target_distance: f32,
+	target_focus: Vec2,
+
+	/// The yaw that puts the camera behind the boat, as of the last update
+	behind_yaw: f32,
+}
+
+impl Camera {
+	pub fn new(yaw: f32, distance: f32) -> Self {
+		Camera {
+			mode: CameraMode::Orbit,
+			viewport: Viewport::new(),
+
+			yaw,
+			distance,
+			focus: Vec2::zero(),
+
+			target_yaw: yaw,
+			target_distance: distance,
+			target_focus: Vec2::zero(),
+
+			behind_yaw: yaw,
+		}
+	}
+
+	pub fn yaw(&self) -> f32 { self.yaw }
+	pub fn distance(&self) -> f32 { self.distance }
+	pub fn focus(&self) -> Vec2 { self.focus }
+
+	pub fn set_viewport_size(&mut self, size: Vec2i) {
+		self.viewport.size = size;
+	}
+
+	pub fn orbit(&mut self, angle: f32) {
+		self.target_yaw += angle;
+	}
+
+	/// Zooms in by a factor of e^`amount`
+	pub fn zoom(&mut self, amount: f32) {
+		self.target_distance = (self.target_distance / amount.exp())
+			.max(CAMERA_MIN_DISTANCE)
+			.min(CAMERA_MAX_DISTANCE);
+	}
+
+	/// Switches between Orbit and Follow. Follow starts off straight behind the
+	/// boat, Orbit from wherever Follow had got to, and either way the camera
+	/// eases round rather than cutting
+	pub fn toggle_mode(&mut self) {
+		match self.mode {
+			CameraMode::Orbit => {
+				self.mode = CameraMode::Follow;
+				self.target_yaw = 0.0;
+			}
+
+			CameraMode::Follow => {
+				self.mode = CameraMode::Orbit;
+				self.target_yaw += self.behind_yaw;
+			}
+		}
+	}
+
+	/// Moves towards looking at `focus`. `heading` is that of whatever is
+	/// being followed
+	pub fn update(&mut self, focus: Vec2, heading: f32, dt: f32) {
+		// Screen up points along (sin yaw, -cos yaw), which is the boat's
+		// forward direction when yaw = PI/2 - heading
+		self.behind_yaw = PI/2.0 - heading;
+		self.target_focus = focus;
+
+		let (desired_yaw, yaw_smoothing) = match self.mode {
+			CameraMode::Orbit => (self.target_yaw, ORBIT_SMOOTHING),
+			CameraMode::Follow => (self.behind_yaw + self.target_yaw, FOLLOW_SMOOTHING),
+		};
+
+		let ease = |rate: f32| (rate * dt).min(1.0);
+
+		self.yaw += wrap_angle(desired_yaw - self.yaw) * ease(yaw_smoothing);
+		self.distance += (self.target_distance - self.distance) * ease(ORBIT_SMOOTHING);
+		self.focus = self.focus + (self.target_focus - self.focus) * ease(FOCUS_SMOOTHING);
+	}
+
+	/// Puts the camera straight where it's meant to be
+	pub fn snap(&mut self) {
+		self.yaw = match self.mode {
+			CameraMode::Orbit => self.target_yaw,
+			CameraMode::Follow => self.behind_yaw + self.target_yaw,
+		};
+
+		self.distance = self.target_distance;
+		self.focus = self.target_focus;
+	}
+
+	pub fn view_mat(&self) -> Mat4 {
+		Mat4::translate(Vec3::new(0.0, 0.0,-self.distance))
+			* Mat4::xrot(CAMERA_PITCH)
+			* Mat4::yrot(self.yaw)
+			* Mat4::translate(Vec3::new(-self.focus.x, 0.0, -self.focus.y))
+	}
+
+	pub fn proj_mat(&self) -> Mat4 {
+		Mat4::perspective(CAMERA_FOV, self.viewport.get_aspect(), CAMERA_NEAR, CAMERA_FAR)
+	}
+
+	/// Takes a direction in camera space back out into the world. The inverse
+	/// of the rotation part of view_mat
+	fn camera_to_world(&self, v: Vec3) -> Vec3 {
+		let (sp, cp) = CAMERA_PITCH.sin_cos();
+		let (sy, cy) = self.yaw.sin_cos();
+
+		let v = Vec3::new(v.x, v.y*cp + v.z*sp, v.z*cp - v.y*sp);
+		Vec3::new(v.x*cy - v.z*sy, v.y, v.x*sy + v.z*cy)
+	}
+
+	pub fn position(&self) -> Vec3 {
+		Vec3::new(self.focus.x, 0.0, self.focus.y) + self.camera_to_world(Vec3::new(0.0, 0.0, self.distance))
+	}
+
+	/// The ray from the camera through a point given in GL coordinates, as
+	/// returned by Viewport::client_to_gl_coords
+	pub fn gl_to_ray(&self, gl_pos: Vec2) -> Ray {
+		let half_height = (CAMERA_FOV / 2.0).tan();
+		let dir = Vec3::new(gl_pos.x * half_height, gl_pos.y * half_height, -1.0);
+
+		Ray {
+			origin: self.position(),
+			dir: self.camera_to_world(dir).normalize(),
+		}
+	}
+
+	/// The ray from the camera through a point on the screen, in client coordinates
+	pub fn screen_to_ray(&self, pos: Vec2i) -> Ray {
+		self.gl_to_ray(self.viewport.client_to_gl_coords(pos))
+	}
+
+	/// The heading that points the way `dir` does on screen, from the middle of
+	/// it. `dir` is in client coordinates, so +y is down
+	pub fn heading_for_screen_dir(&self, dir: Vec2) -> Option<f32> {
+		if dir.length() <= 0.0 { return None }
+
+		// GL coordinates are scaled by aspect horizontally, so pixels map to
+		// them uniformly apart from the flip
+		let gl_dir = Vec2::new(dir.x, -dir.y).normalize() * STEERING_PROBE;
+
+		let from = self.gl_to_ray(Vec2::zero()).hit_height(0.0);
+		let to = self.gl_to_ray(gl_dir).hit_height(0.0);
+
+		match (from, to) {
+			(Some(from), Some(to)) => Some(heading_between(from, to)),
+			_ => None,
+		}
+	}
+
+	/// The heading from the spot on the sea under `start` towards the one
+	/// under `end`, so a drag points exactly where it looks like it does.
+	/// Falls back to heading_for_screen_dir if either is above the horizon
+	pub fn heading_for_drag(&self, start: Vec2i, end: Vec2i) -> Option<f32> {
+		let from = self.screen_to_ray(start).hit_height(0.0);
+		let to = self.screen_to_ray(end).hit_height(0.0);
+
+		match (from, to) {
+			(Some(from), Some(to)) if (to - from).length() > 0.0 => Some(heading_between(from, to)),
+			_ => self.heading_for_screen_dir((end - start).to_vec2()),
+		}
+	}
+}
+
+/// The heading that points from `from` towards `to`, ignoring height
+pub fn heading_between(from: Vec3, to: Vec3) -> f32 {
+	let diff = to - from;
+	(-diff.z).atan2(diff.x)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn camera_at(focus: Vec2, yaw: f32) -> Camera {
+		let mut camera = Camera::new(yaw, 12.0);
+		camera.set_viewport_size(Vec2i::new(800, 600));
+		camera.update(focus, 0.0, 0.0);
+		camera.snap();
+		camera
+	}
+
+	fn near(a: f32, b: f32, tolerance: f32) -> bool { (a - b).abs() <= tolerance }
+
+	#[test]
+	fn screen_centre_looks_at_the_focus() {
+		for &yaw in [0.0, 1.0, PI, -2.5].iter() {
+			let focus = Vec2::new(3.0, -7.0);
+			let camera = camera_at(focus, yaw);
+
+			let hit = camera.screen_to_ray(Vec2i::new(400, 300)).hit_height(0.0).unwrap();
+			assert!(near(hit.x, focus.x, 1e-3) && near(hit.z, focus.y, 1e-3), "yaw {}: hit {:?}", yaw, hit);
+
+			let offset = camera.position() - Vec3::new(focus.x, 0.0, focus.y);
+			assert!(near(offset.length(), camera.distance(), 1e-3));
+		}
+	}
+
+	#[test]
+	fn rays_above_the_horizon_miss_the_sea() {
+		let camera = camera_at(Vec2::zero(), 0.0);
+		let up = Ray { origin: camera.position(), dir: Vec3::new(0.0, 1.0, 0.0) };
+
+		assert!(up.hit_height(0.0).is_none());
+		assert!(camera.screen_to_ray(Vec2i::new(400, 300)).dir.y < 0.0);
+	}
+
+	#[test]
+	fn zoom_is_clamped() {
+		let mut camera = camera_at(Vec2::zero(), 0.0);
+
+		camera.zoom(10.0);
+		camera.snap();
+		assert_eq!(camera.distance(), CAMERA_MIN_DISTANCE);
+
+		camera.zoom(-10.0);
+		camera.snap();
+		assert_eq!(camera.distance(), CAMERA_MAX_DISTANCE);
+	}
+
+	#[test]
+	fn orbiting_eases_round_the_short_way() {
+		let mut camera = camera_at(Vec2::zero(), PI - 0.1);
+		camera.orbit(0.2);
+
+		// Part way there after a frame, without swinging the long way round
+		camera.update(Vec2::zero(), 0.0, 1.0 / 60.0);
+		let moved = wrap_angle(camera.yaw() - (PI - 0.1));
+		assert!(moved > 0.0 && moved < 0.2, "moved {}", moved);
+
+		// A long frame doesn't overshoot
+		camera.update(Vec2::zero(), 0.0, 10.0);
+		assert!(near(wrap_angle(camera.yaw() - (PI + 0.1)), 0.0, 1e-4));
+	}
+
+	#[test]
+	fn focus_eases_towards_the_boat() {
+		let mut camera = camera_at(Vec2::zero(), 0.0);
+
+		camera.update(Vec2::new(10.0, 0.0), 0.0, 1.0 / 60.0);
+		assert!(camera.focus().x > 0.0 && camera.focus().x < 10.0);
+
+		for _ in 0..120 {
+			camera.update(Vec2::new(10.0, 0.0), 0.0, 1.0 / 60.0);
+		}
+
+		assert!(near(camera.focus().x, 10.0, 1e-2));
+	}
+
+	#[test]
+	fn follow_mode_swings_behind_the_boat() {
+		let mut camera = camera_at(Vec2::zero(), 0.0);
+		let heading = 0.7;
+
+		camera.toggle_mode();
+		assert_eq!(camera.mode, CameraMode::Follow);
+
+		// Follows more lazily than orbiting
+		camera.update(Vec2::zero(), heading, 1.0 / 60.0);
+		let first_step = wrap_angle(camera.yaw());
+		assert!(first_step > 0.0 && first_step < (PI/2.0 - heading) * 6.0 / 60.0);
+
+		for _ in 0..600 {
+			camera.update(Vec2::zero(), heading, 1.0 / 60.0);
+		}
+
+		assert!(near(wrap_angle(camera.yaw() - (PI/2.0 - heading)), 0.0, 1e-3));
+
+		// From behind the boat, up the screen is straight ahead
+		let ahead = camera.heading_for_screen_dir(Vec2::new(0.0, -1.0)).unwrap();
+		assert!(near(wrap_angle(ahead - heading), 0.0, 1e-3), "{} vs {}", ahead, heading);
+
+		// Going back to Orbit stays put
+		camera.toggle_mode();
+		let yaw = camera.yaw();
+		camera.update(Vec2::zero(), heading, 1.0);
+		assert!(near(wrap_angle(camera.yaw() - yaw), 0.0, 1e-3));
+	}
+}
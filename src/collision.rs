@@ -0,0 +1,214 @@
+use common::*;
+
+/// Anything taller than this is land as far as the hull is concerned
+pub const GROUNDING_HEIGHT: f32 = -0.15;
+
+/// The hull's footprint on the XZ plane as a row of circles along the keel,
+/// in boat space (x, z, radius)
+pub const HULL_FOOTPRINT: [(f32, f32, f32); 3] = [
+	( 0.55, 0.0, 0.2),
+	( 0.05, 0.0, 0.35),
+	(-0.3, 0.0, 0.45),
+];
+
+const TERRAIN_SAMPLES: usize = 8;
+const GRADIENT_EPSILON: f32 = 0.1;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Obstacle {
+	/// Rocks, posts, buoys
+	Circle { centre: Vec2, radius: f32 },
+	/// Piers and jetties - a line segment with some thickness
+	Capsule { a: Vec2, b: Vec2, radius: f32 },
+}
+
+impl Obstacle {
+	/// The point on the obstacle's centre line closest to `p`, and the obstacle's radius
+	fn closest_point(&self, p: Vec2) -> (Vec2, f32) {
+		match *self {
+			Obstacle::Circle { centre, radius } => (centre, radius),
+			Obstacle::Capsule { a, b, radius } => {
+				let ab = b - a;
+				let t = ((p - a).dot(ab) / ab.dot(ab)).max(0.0).min(1.0);
+				(a + ab * t, radius)
+			}
+		}
+	}
+}
+
+/// The static parts of the world a boat can run into
+pub trait CollisionWorld {
+	fn terrain_height(&self, pos: Vec2) -> f32;
+
+	/// Appends any obstacles that might be within `radius` of `pos` to `out`
+	fn obstacles_near(&self, pos: Vec2, radius: f32, out: &mut Vec<Obstacle>);
+}
+
+/// Open water in every direction
+impl CollisionWorld for () {
+	fn terrain_height(&self, _: Vec2) -> f32 { -1.0 }
+	fn obstacles_near(&self, _: Vec2, _: f32, _: &mut Vec<Obstacle>) {}
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Contact {
+	/// How far to move the hull to get it clear
+	pub push: Vec2,
+	/// Which way is away from whatever was hit
+	pub normal: Vec2,
+}
+
+/// Tests a footprint, given as world space circles, against the world.
+/// Returns None if the footprint is entirely in open water.
+///
+/// The circles are resolved one at a time, each starting from where the ones
+/// before it pushed the footprint to, so overlapping circles touching the
+/// same thing don't each push it out again. Anything exactly on an
+/// obstacle's centre line gets pushed along `fallback` instead
+pub fn resolve<C>(footprint: &[(Vec2, f32)], fallback: Vec2, world: &C) -> Option<Contact> where C: CollisionWorld {
+	let mut push = Vec2::zero();
+	let mut obstacles = Vec::new();
+
+	// One query for the whole footprint rather than one per circle
+	if let Some(&(first, _)) = footprint.first() {
+		let extent = footprint.iter()
+			.map(|&(centre, radius)| (centre - first).length() + radius)
+			.fold(0.0, f32::max);
+
+		world.obstacles_near(first, extent, &mut obstacles);
+	}
+
+	for &(centre, radius) in footprint.iter() {
+		push = push + terrain_push(centre + push, radius, world);
+
+		for obstacle in obstacles.iter() {
+			let centre = centre + push;
+			let (closest, obstacle_radius) = obstacle.closest_point(centre);
+			let diff = centre - closest;
+			let dist = diff.length();
+			let penetration = radius + obstacle_radius - dist;
+
+			if penetration <= 0.0 { continue }
+
+			let away = if dist > 0.0 { diff * (1.0 / dist) } else { fallback };
+			push = push + away * penetration;
+		}
+	}
+
+	if push.length() <= 0.0 {
+		return None
+	}
+
+	Some(Contact { push, normal: push.normalize() })
+}
+
+fn terrain_push<C>(centre: Vec2, radius: f32, world: &C) -> Vec2 where C: CollisionWorld {
+	let mut max_excess = 0.0f32;
+	let mut deepest = centre;
+
+	for i in 0..=TERRAIN_SAMPLES {
+		let sample = if i == TERRAIN_SAMPLES {
+			centre
+		} else {
+			let angle = i as f32 / TERRAIN_SAMPLES as f32 * 2.0 * PI;
+			centre + Vec2::new(angle.cos(), angle.sin()) * radius
+		};
+
+		let excess = world.terrain_height(sample) - GROUNDING_HEIGHT;
+		if excess > max_excess {
+			max_excess = excess;
+			deepest = sample;
+		}
+	}
+
+	if max_excess <= 0.0 {
+		return Vec2::zero()
+	}
+
+	// Downhill from wherever is most aground is the quickest way back to the
+	// water. The centre can be on flat ground, or the wrong side of a ridge
+	let e = GRADIENT_EPSILON;
+	let gradient = Vec2::new(
+		world.terrain_height(deepest + Vec2::new(e, 0.0)) - world.terrain_height(deepest - Vec2::new(e, 0.0)),
+		world.terrain_height(deepest + Vec2::new(0.0, e)) - world.terrain_height(deepest - Vec2::new(0.0, e)),
+	) * (0.5 / e);
+
+	let slope = gradient.length();
+	if slope <= 0.0 {
+		return Vec2::zero()
+	}
+
+	let depth = (max_excess / slope).min(radius);
+	gradient * (-depth / slope)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Flat sea bed with a bank rising out of it beyond x = 0.3
+	struct Bank;
+
+	impl CollisionWorld for Bank {
+		fn terrain_height(&self, pos: Vec2) -> f32 {
+			-1.0 + (pos.x - 0.3).max(0.0) * 8.0
+		}
+
+		fn obstacles_near(&self, _: Vec2, _: f32, _: &mut Vec<Obstacle>) {}
+	}
+
+	#[test]
+	fn pushed_downhill_from_the_deepest_point() {
+		// The centre is on flat sea bed, but the rim is up on the bank
+		let push = terrain_push(Vec2::zero(), 0.5, &Bank);
+
+		assert!(push.x < 0.0, "push {:?} doesn't lead off the bank", push);
+		assert!(push.y.abs() < 1e-4);
+	}
+
+	#[test]
+	fn open_water_doesnt_push() {
+		assert!(resolve(&[(Vec2::new(-2.0, 0.0), 0.5)], BACKWARDS, &Bank).is_none());
+		assert!(resolve(&[(Vec2::zero(), 0.5)], BACKWARDS, &()).is_none());
+	}
+
+	/// A single rock of radius 0.5 at (1, 0)
+	struct Rock;
+
+	impl CollisionWorld for Rock {
+		fn terrain_height(&self, _: Vec2) -> f32 { -1.0 }
+
+		fn obstacles_near(&self, _: Vec2, _: f32, out: &mut Vec<Obstacle>) {
+			out.push(Obstacle::Circle { centre: Vec2::new(1.0, 0.0), radius: 0.5 });
+		}
+	}
+
+	const BACKWARDS: Vec2 = Vec2 { x: -1.0, y: 0.0 };
+
+	#[test]
+	fn pushed_out_of_obstacles() {
+		let contact = resolve(&[(Vec2::new(0.2, 0.0), 0.5), (Vec2::new(-2.0, 0.0), 0.5)], BACKWARDS, &Rock).unwrap();
+
+		assert!((contact.push.x + 0.2).abs() < 1e-4, "push {:?}", contact.push);
+		assert!(contact.normal.x < 0.0);
+	}
+
+	#[test]
+	fn overlapping_circles_dont_push_twice() {
+		// Both circles are 0.2 into the rock, but once one is out so is the other
+		let footprint = [(Vec2::new(0.2, 0.0), 0.5), (Vec2::new(0.2, 0.0), 0.5)];
+		let contact = resolve(&footprint, BACKWARDS, &Rock).unwrap();
+
+		assert!((contact.push.x + 0.2).abs() < 1e-4, "push {:?}", contact.push);
+		assert!(contact.push.y.abs() < 1e-4);
+	}
+
+	#[test]
+	fn dead_centre_falls_back() {
+		let sideways = Vec2::new(0.0, 1.0);
+		let contact = resolve(&[(Vec2::new(1.0, 0.0), 0.5)], sideways, &Rock).unwrap();
+
+		assert!((contact.push - sideways).length() < 1e-4, "push {:?}", contact.push);
+		assert!((contact.normal - sideways).length() < 1e-4);
+	}
+}
@@ -1,8 +1,16 @@
 #![allow(unused_variables, unused_imports, dead_code)]
 
 use std::collections::HashMap;
+
+#[cfg(target_os = "emscripten")]
 use bindings::console::*;
 
+// There's nowhere to put the console off the web, so the native versions just
+// keep the state around and drop the text
+#[cfg(not(target_os = "emscripten"))] unsafe fn init_console() {}
+#[cfg(not(target_os = "emscripten"))] unsafe fn set_console_text(_: *const i8) {}
+#[cfg(not(target_os = "emscripten"))] unsafe fn set_console_color(_: *const i8) {}
+
 enum ConsoleDirtiness {
 	Clean,
 	Buffer,
@@ -48,6 +56,11 @@ pub fn set_section<S, S2>(sect: S, s: S2) where S: Into<String>, S2: Into<String
 	}
 }
 
+/// Makes `s` safe to show in a section as plain text
+pub fn escape_html(s: &str) -> String {
+	s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
 pub fn set_color<S>(s: S) where S: Into<Vec<u8>> {
 	#[cfg(debug)] unsafe {
 		use std::ffi::CString;
@@ -1,30 +1,4 @@
 use std::ops::{Generator, GeneratorState};
-use bindings::emscripten::*;
-
-pub fn set_coro_as_main_loop<T>(coro: T) -> ! where T: Generator<Yield=(), Return=()> {
-	unsafe {
-		let coro: Box<Generator<Yield=(), Return=()>> = box coro;
-		emscripten_set_main_loop_arg(Some(resume_main_coro), Box::into_raw(box coro) as _, 0, 1)
-	}
-}
-
-extern "C" fn resume_main_coro(ctx: *mut CVoid) {
-	use std::mem::transmute;
-	use std::ops::GeneratorState::*;
-
-	let coro: &mut Box<Generator<Yield=(), Return=()>> = unsafe{ transmute(ctx) };
-
-	match coro.resume() {
-		Yielded(()) => {}
-		Complete(()) => unsafe {
-			println!("Main coro has returned");
-
-			Box::from_raw(ctx as _);
-
-			emscripten_cancel_main_loop();
-		}
-	}
-}
 
 pub struct Coro<Y> {
 	pub value: Option<Y>,
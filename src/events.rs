@@ -1,127 +1,113 @@
-use bindings::emscripten::*;
-use common::Vec2i;
-use console;
+use std::cell::RefCell;
+use std::fmt;
+use std::mem;
+use std::str;
 
-use std::mem::transmute;
-use std::ptr::null;
+use common::Vec2i;
 
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum Event {
 	Resize(Vec2i),
 
 	Down(Vec2i),
 	Up(Vec2i),
 	Move(Vec2i),
-}
-
-pub fn init_event_queue(queue: *mut Vec<Event>) {
-	unsafe {
-		let evt_ptr = transmute(queue);
+	/// Scroll wheel movement in notches. Positive is scrolling down
+	Wheel(f32),
 
-		on_resize(0, null(), evt_ptr);
-		emscripten_set_resize_callback(null(), evt_ptr, 0, Some(on_resize));
+	/// Touches carry the id the browser gave them, which stays the same from
+	/// start to end. Ids are only unique among touches currently down
+	TouchStart(u32, Vec2i),
+	TouchMove(u32, Vec2i),
+	TouchEnd(u32, Vec2i),
 
-		emscripten_set_mousemove_callback(null(), evt_ptr, 0, Some(on_mouse_move));
-		emscripten_set_mousedown_callback(null(), evt_ptr, 0, Some(on_mouse_down));
-		emscripten_set_mouseup_callback(null(), evt_ptr, 0, Some(on_mouse_up));
+	KeyDown(Key),
+	KeyUp(Key),
 
-		emscripten_set_touchstart_callback(null(), evt_ptr, 0, Some(on_touch_start));
-		emscripten_set_touchmove_callback(null(), evt_ptr, 0, Some(on_touch_move));
-		emscripten_set_touchend_callback(null(), evt_ptr, 0, Some(on_touch_end));
-		emscripten_set_touchcancel_callback(null(), evt_ptr, 0, Some(on_touch_end));
-	}
+	GamepadConnected(u32),
+	GamepadDisconnected(u32),
 }
 
-unsafe extern "C"
-fn on_resize(_: i32, _e: *const EmscriptenUiEvent, ud: *mut CVoid) -> i32 {
-	let event_queue: &mut Vec<Event> = transmute(ud);
+/// Room for the longest DOM `KeyboardEvent.code`, which emscripten caps at 31
+/// bytes anyway
+const MAX_KEY_CODE_LEN: usize = 32;
+
+/// A physical key, named by its DOM `KeyboardEvent.code` ("KeyW", "ArrowUp",
+/// "Space", ...). Codes name physical keys, so WASD stays in the same place on
+/// non-QWERTY layouts. The code is stored inline so events stay Copy
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Key {
+	len: u8,
+	code: [u8; MAX_KEY_CODE_LEN],
+}
 
-	js! { b"Module.canvas = document.getElementById('canvas')\0" };
+impl Key {
+	/// None for codes that are empty, too long, or couldn't be written to a
+	/// config file or replay log as a single word
+	pub fn from_code(code: &str) -> Option<Key> {
+		if code.is_empty() || code.len() > MAX_KEY_CODE_LEN { return None }
+		if code.chars().any(|c| c.is_whitespace() || c == '#') { return None }
 
-	let mut screen_size = Vec2i::zero();
-	screen_size.x = js! { b"return (Module.canvas.width = Module.canvas.style.width = window.innerWidth)\0" };
-	screen_size.y = js! { b"return (Module.canvas.height = Module.canvas.style.height = window.innerHeight)\0" };
+		let mut key = Key { len: code.len() as u8, code: [0; MAX_KEY_CODE_LEN] };
+		key.code[..code.len()].copy_from_slice(code.as_bytes());
+		Some(key)
+	}
 
-	event_queue.push(Event::Resize(screen_size));
-	
-	0
+	pub fn code(&self) -> &str {
+		// Only ever filled from a whole &str
+		str::from_utf8(&self.code[..self.len as usize]).unwrap_or("")
+	}
 }
 
-unsafe extern "C"
-fn on_mouse_move(_: i32, e: *const EmscriptenMouseEvent, ud: *mut CVoid) -> i32 {
-	let event_queue: &mut Vec<Event> = transmute(ud);
-	let e: &EmscriptenMouseEvent = transmute(e);
-
-	event_queue.push(Event::Move(Vec2i::new(e.clientX as _, e.clientY as _)));
-	
-	1
-}
-unsafe extern "C"
-fn on_mouse_down(_: i32, e: *const EmscriptenMouseEvent, ud: *mut CVoid) -> i32 {
-	let event_queue: &mut Vec<Event> = transmute(ud);
-	let e: &EmscriptenMouseEvent = transmute(e);
-
-	event_queue.push(Event::Down(Vec2i::new(e.clientX as _, e.clientY as _)));
-	
-	1
-}
-unsafe extern "C"
-fn on_mouse_up(_: i32, e: *const EmscriptenMouseEvent, ud: *mut CVoid) -> i32 {
-	let event_queue: &mut Vec<Event> = transmute(ud);
-	let e: &EmscriptenMouseEvent = transmute(e);
-
-	event_queue.push(Event::Up(Vec2i::new(e.clientX as _, e.clientY as _)));
-	
-	1
+impl fmt::Debug for Key {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "Key({})", self.code())
+	}
 }
 
-
-unsafe extern "C"
-fn on_touch_move(_: i32, e: *const EmscriptenTouchEvent, ud: *mut CVoid) -> i32 {
-	let event_queue: &mut Vec<Event> = transmute(ud);
-	let e: &EmscriptenTouchEvent = transmute(e);
-
-	if e.touches[0].identifier != 0 { return 0 }
-
-	let pos = Vec2i::new(e.touches[0].clientX as _, e.touches[0].clientY as _);
-	event_queue.push(Event::Move(pos));
-	
-	1
+/// Collects events pushed by the platform between frames. The platform creates
+/// it, hooks up whatever callbacks feed it, and unhooks them again when it's
+/// dropped, so nothing can push into a queue that no longer exists
+pub struct EventQueue {
+	// Boxed so the address handed to callbacks stays put when the queue moves
+	events: Box<RefCell<Vec<Event>>>,
+	teardown: Option<fn()>,
 }
 
-unsafe extern "C"
-fn on_touch_start(_: i32, e: *const EmscriptenTouchEvent, ud: *mut CVoid) -> i32 {
-	let event_queue: &mut Vec<Event> = transmute(ud);
-	let e: &EmscriptenTouchEvent = transmute(e);
-
-	if e.touches[0].identifier != 0 { return 0 }
+impl EventQueue {
+	pub fn new() -> Self {
+		EventQueue {
+			events: box RefCell::new(Vec::new()),
+			teardown: None,
+		}
+	}
 
-	let pos = Vec2i::new(e.touches[0].clientX as _, e.touches[0].clientY as _);
-	event_queue.push(Event::Down(pos));
-	
-	1
-}
+	pub fn push(&self, event: Event) {
+		self.events.borrow_mut().push(event);
+	}
 
-unsafe extern "C"
-fn on_touch_end(_: i32, e: *const EmscriptenTouchEvent, ud: *mut CVoid) -> i32 {
-	let event_queue: &mut Vec<Event> = transmute(ud);
-	let e: &EmscriptenTouchEvent = transmute(e);
+	/// Takes every event pushed since the last drain
+	pub fn drain(&self) -> Vec<Event> {
+		mem::replace(&mut *self.events.borrow_mut(), Vec::new())
+	}
 
-	if e.numTouches > 2 {
-		use std::mem::uninitialized;
+	/// The queue's storage, for platforms that have to hand callbacks a pointer.
+	/// Only valid for as long as the queue is alive
+	pub fn storage_ptr(&self) -> *const RefCell<Vec<Event>> {
+		&*self.events
+	}
 
-		let mut fs_state: EmscriptenFullscreenChangeEvent = uninitialized();
-		emscripten_get_fullscreen_status(&mut fs_state);
+	/// Called when the queue is dropped, to unregister anything still holding
+	/// on to storage_ptr
+	pub fn set_teardown(&mut self, teardown: fn()) {
+		self.teardown = Some(teardown);
+	}
+}
 
-		if fs_state.isFullscreen == 0 {
-			js! {{ b"Module.requestFullscreen(1, 1)\0" }};
-			console::set_section("Fullscreen requested", "");
+impl Drop for EventQueue {
+	fn drop(&mut self) {
+		if let Some(teardown) = self.teardown.take() {
+			teardown();
 		}
 	}
-
-	if e.touches[0].identifier != 0 { return 0 }
-
-	let pos = Vec2i::new(e.touches[0].clientX as _, e.touches[0].clientY as _);
-	event_queue.push(Event::Up(pos));
-	
-	1
 }
@@ -0,0 +1,173 @@
+use common::*;
+
+pub const MAX_GAMEPAD_AXES: usize = 8;
+pub const MAX_GAMEPAD_BUTTONS: usize = 20;
+
+/// A snapshot of one connected gamepad, as polled by the platform each frame.
+/// Axes run from -1 to 1 and buttons from 0 to 1, following the
+/// standard gamepad layout where the browser knows it
+#[derive(Copy, Clone, Debug)]
+pub struct GamepadState {
+	pub index: u32,
+	pub axes: [f32; MAX_GAMEPAD_AXES],
+	pub buttons: [f32; MAX_GAMEPAD_BUTTONS],
+}
+
+impl GamepadState {
+	pub fn new(index: u32) -> Self {
+		GamepadState {
+			index,
+			axes: [0.0; MAX_GAMEPAD_AXES],
+			buttons: [0.0; MAX_GAMEPAD_BUTTONS],
+		}
+	}
+
+	pub fn axis(&self, axis: usize) -> f32 {
+		self.axes.get(axis).cloned().unwrap_or(0.0)
+	}
+
+	pub fn button(&self, button: usize) -> f32 {
+		self.buttons.get(button).cloned().unwrap_or(0.0)
+	}
+}
+
+/// Which axes and buttons steer the boat. Defaults to the left stick for
+/// direction and the right trigger for sail, as laid out on the standard mapping
+#[derive(Copy, Clone, Debug)]
+pub struct GamepadMapping {
+	pub steer_x_axis: usize,
+	pub steer_y_axis: usize,
+	pub invert_y: bool,
+
+	/// If set, this button (usually a trigger) decides how much sail to put
+	/// up whenever it's pressed. Otherwise how far the stick is pushed does
+	pub throttle_button: Option<usize>,
+
+	/// Stick and trigger values below this are treated as zero. From 0 up to,
+	/// but not including, 1
+	pub dead_zone: f32,
+}
+
+impl Default for GamepadMapping {
+	fn default() -> Self {
+		GamepadMapping {
+			steer_x_axis: 0,
+			steer_y_axis: 1,
+			invert_y: false,
+			throttle_button: Some(7),
+			dead_zone: 0.2,
+		}
+	}
+}
+
+impl GamepadMapping {
+	/// Reads a pad the same way the drag gesture is read - a direction in
+	/// screen space and an amount of sail from 0 to 1. Returns None while the
+	/// stick is resting in the dead zone
+	pub fn steering(&self, pad: &GamepadState) -> Option<(Vec2, f32)> {
+		let y = pad.axis(self.steer_y_axis);
+		let stick = Vec2::new(pad.axis(self.steer_x_axis), if self.invert_y { -y } else { y });
+
+		let amount = self.apply_dead_zone(stick.length().min(1.0));
+		if amount <= 0.0 { return None }
+
+		let throttle = match self.throttle_button.map(|b| self.apply_dead_zone(pad.button(b))) {
+			Some(t) if t > 0.0 => t,
+			_ => amount,
+		};
+
+		Some((stick, throttle))
+	}
+
+	/// Rescales `value` so it starts from zero at the edge of the dead zone,
+	/// rather than jumping straight to dead_zone
+	pub fn apply_dead_zone(&self, value: f32) -> f32 {
+		let live_range = 1.0 - self.dead_zone;
+		if live_range <= 0.0 { return 0.0 }
+
+		((value - self.dead_zone) / live_range).max(0.0)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn pad(axes: &[f32], buttons: &[f32]) -> GamepadState {
+		let mut pad = GamepadState::new(0);
+		pad.axes[..axes.len()].copy_from_slice(axes);
+		pad.buttons[..buttons.len()].copy_from_slice(buttons);
+		pad
+	}
+
+	fn approx(a: f32, b: f32) -> bool { (a - b).abs() < 1e-5 }
+
+	#[test]
+	fn dead_zone_rescales_from_its_edge() {
+		let mapping = GamepadMapping { dead_zone: 0.2, ..GamepadMapping::default() };
+
+		assert_eq!(mapping.apply_dead_zone(0.1), 0.0);
+		assert_eq!(mapping.apply_dead_zone(0.2), 0.0);
+		assert!(approx(mapping.apply_dead_zone(0.6), 0.5));
+		assert!(approx(mapping.apply_dead_zone(1.0), 1.0));
+	}
+
+	#[test]
+	fn full_dead_zone_reads_as_zero() {
+		let mapping = GamepadMapping { dead_zone: 1.0, ..GamepadMapping::default() };
+
+		let value = mapping.apply_dead_zone(1.0);
+		assert!(value.is_finite());
+		assert_eq!(value, 0.0);
+	}
+
+	#[test]
+	fn resting_stick_doesnt_steer() {
+		let mapping = GamepadMapping::default();
+		assert!(mapping.steering(&pad(&[0.1, -0.1], &[])).is_none());
+	}
+
+	#[test]
+	fn stick_maps_to_direction_and_amount() {
+		let mapping = GamepadMapping { throttle_button: None, ..GamepadMapping::default() };
+		let (dir, amount) = mapping.steering(&pad(&[0.0, -1.0], &[])).unwrap();
+
+		assert_eq!((dir.x, dir.y), (0.0, -1.0));
+		assert!(approx(amount, 1.0));
+
+		let inverted = GamepadMapping { invert_y: true, ..mapping };
+		let (dir, _) = inverted.steering(&pad(&[0.0, -1.0], &[])).unwrap();
+		assert_eq!(dir.y, 1.0);
+	}
+
+	#[test]
+	fn mapped_axes_are_used() {
+		let mapping = GamepadMapping { steer_x_axis: 2, steer_y_axis: 3, throttle_button: None, ..GamepadMapping::default() };
+
+		assert!(mapping.steering(&pad(&[1.0, 1.0], &[])).is_none());
+
+		let (dir, _) = mapping.steering(&pad(&[0.0, 0.0, 1.0, 0.0], &[])).unwrap();
+		assert_eq!((dir.x, dir.y), (1.0, 0.0));
+	}
+
+	#[test]
+	fn pressed_trigger_sets_the_sail() {
+		let mapping = GamepadMapping::default();
+		let mut buttons = [0.0; 8];
+
+		// Released, the stick decides
+		let (_, amount) = mapping.steering(&pad(&[1.0, 0.0], &buttons)).unwrap();
+		assert!(approx(amount, 1.0));
+
+		buttons[7] = 0.6;
+		let (_, amount) = mapping.steering(&pad(&[1.0, 0.0], &buttons)).unwrap();
+		assert!(approx(amount, 0.5));
+	}
+
+	#[test]
+	fn missing_axes_and_buttons_read_as_zero() {
+		let pad = GamepadState::new(0);
+		assert_eq!(pad.axis(MAX_GAMEPAD_AXES), 0.0);
+		assert_eq!(pad.button(MAX_GAMEPAD_BUTTONS + 3), 0.0);
+	}
+}
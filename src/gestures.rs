@@ -0,0 +1,289 @@
+use std::collections::HashMap;
+
+use common::*;
+use events::Event;
+
+/// Touches that end sooner than this, without moving further than
+/// TAP_SLOP pixels, count as taps
+const TAP_DURATION: f64 = 0.3;
+const TAP_SLOP: f32 = 20.0;
+
+#[derive(Copy, Clone, Debug)]
+pub enum Gesture {
+	/// A single finger drag, reported the same way as a mouse drag. A drag is
+	/// ended early if a second finger comes down
+	DragStart(Vec2i),
+	DragMove(Vec2i),
+	DragEnd(Vec2i),
+
+	/// Every finger that was down came up again quickly without moving
+	Tap { pos: Vec2i, fingers: usize },
+
+	/// Two fingers moved apart (> 1) or together (< 1) by this ratio since the
+	/// last move
+	Pinch(f32),
+
+	/// Two fingers twisted clockwise on screen by this many radians since the
+	/// last move
+	Rotate(f32),
+}
+
+#[derive(Copy, Clone)]
+struct Touch {
+	start: Vec2i,
+	pos: Vec2i,
+}
+
+/// Turns raw per-id touch events into gestures. Nothing here depends on the
+/// ids the browser hands out, only on how many touches are down
+pub struct GestureRecognizer {
+	touches: HashMap<u32, Touch>,
+	gestures: Vec<Gesture>,
+
+	dragging: Option<u32>,
+
+	// State for the current tap, if it could still be one
+	tap_start: f64,
+	tap_fingers: usize,
+	tap_valid: bool,
+}
+
+impl GestureRecognizer {
+	pub fn new() -> Self {
+		GestureRecognizer {
+			touches: HashMap::new(),
+			gestures: Vec::new(),
+
+			dragging: None,
+
+			tap_start: 0.0,
+			tap_fingers: 0,
+			tap_valid: false,
+		}
+	}
+
+	/// Gestures recognised since the last clear
+	pub fn gestures(&self) -> &[Gesture] { &self.gestures }
+
+	pub fn clear(&mut self) { self.gestures.clear(); }
+
+	/// Feeds in an event. Anything that isn't a touch is ignored
+	pub fn handle_event(&mut self, event: &Event, now: f64) {
+		match *event {
+			Event::TouchStart(id, pos) => self.touch_start(id, pos, now),
+			Event::TouchMove(id, pos) => self.touch_move(id, pos),
+			Event::TouchEnd(id, pos) => self.touch_end(id, pos, now),
+			_ => {}
+		}
+	}
+
+	fn touch_start(&mut self, id: u32, pos: Vec2i, now: f64) {
+		if self.touches.is_empty() {
+			self.tap_start = now;
+			self.tap_fingers = 0;
+			self.tap_valid = true;
+		}
+
+		self.touches.insert(id, Touch { start: pos, pos });
+		self.tap_fingers = self.tap_fingers.max(self.touches.len());
+
+		match self.touches.len() {
+			1 => {
+				self.dragging = Some(id);
+				self.gestures.push(Gesture::DragStart(pos));
+			}
+
+			_ => if let Some(drag_id) = self.dragging.take() {
+				let drag_pos = self.touches[&drag_id].pos;
+				self.gestures.push(Gesture::DragEnd(drag_pos));
+			}
+		}
+	}
+
+	fn touch_move(&mut self, id: u32, pos: Vec2i) {
+		let before = self.two_finger_span();
+
+		match self.touches.get_mut(&id) {
+			Some(touch) => touch.pos = pos,
+			None => return,
+		}
+
+		if self.touches.values().any(|t| (t.pos - t.start).length() > TAP_SLOP) {
+			self.tap_valid = false;
+		}
+
+		if self.dragging == Some(id) {
+			self.gestures.push(Gesture::DragMove(pos));
+		}
+
+		if let (Some(before), Some(after)) = (before, self.two_finger_span()) {
+			let (before_len, after_len) = (before.length(), after.length());
+
+			if before_len > 0.0 && after_len > 0.0 {
+				self.gestures.push(Gesture::Pinch(after_len / before_len));
+
+				let mut twist = after.to_angle() - before.to_angle();
+				if twist > PI { twist -= 2.0 * PI }
+				if twist < -PI { twist += 2.0 * PI }
+				self.gestures.push(Gesture::Rotate(twist));
+			}
+		}
+	}
+
+	fn touch_end(&mut self, id: u32, pos: Vec2i, now: f64) {
+		if self.touches.remove(&id).is_none() { return }
+
+		if self.dragging == Some(id) {
+			self.dragging = None;
+			self.gestures.push(Gesture::DragEnd(pos));
+		}
+
+		if self.touches.is_empty() && self.tap_valid && now - self.tap_start < TAP_DURATION {
+			self.gestures.push(Gesture::Tap { pos, fingers: self.tap_fingers });
+		}
+	}
+
+	/// The vector between the two touches, if exactly two are down
+	fn two_finger_span(&self) -> Option<Vec2> {
+		if self.touches.len() != 2 { return None }
+
+		let mut touches = self.touches.values();
+		let a = touches.next().unwrap().pos;
+		let b = touches.next().unwrap().pos;
+
+		Some((b - a).to_vec2())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn p(x: i32, y: i32) -> Vec2i { Vec2i::new(x, y) }
+
+	/// Feeds `events` in, all at time `now`, and takes whatever they made
+	fn feed(recognizer: &mut GestureRecognizer, events: &[Event], now: f64) -> Vec<Gesture> {
+		for event in events.iter() {
+			recognizer.handle_event(event, now);
+		}
+
+		let gestures = recognizer.gestures().to_vec();
+		recognizer.clear();
+		gestures
+	}
+
+	fn taps(gestures: &[Gesture]) -> Vec<usize> {
+		gestures.iter().filter_map(|g| match *g { Gesture::Tap { fingers, .. } => Some(fingers), _ => None }).collect()
+	}
+
+	#[test]
+	fn first_touch_drags_whatever_its_id() {
+		let mut r = GestureRecognizer::new();
+
+		let gestures = feed(&mut r, &[
+			Event::TouchStart(42, p(10, 10)),
+			Event::TouchMove(42, p(60, 10)),
+			Event::TouchEnd(42, p(60, 10)),
+		], 0.0);
+
+		match (gestures[0], gestures[1], gestures[2]) {
+			(Gesture::DragStart(a), Gesture::DragMove(b), Gesture::DragEnd(c)) => {
+				assert_eq!((a.x, b.x, c.x), (10, 60, 60));
+			}
+
+			_ => panic!("expected a whole drag, got {:?}", gestures),
+		}
+
+		assert_eq!(gestures.len(), 3, "moved too far to be a tap");
+	}
+
+	#[test]
+	fn touches_are_tracked_by_id() {
+		let mut r = GestureRecognizer::new();
+		feed(&mut r, &[Event::TouchStart(7, p(0, 0))], 0.0);
+
+		// An id that never started is ignored
+		assert!(feed(&mut r, &[Event::TouchMove(3, p(50, 50)), Event::TouchEnd(3, p(50, 50))], 0.0).is_empty());
+
+		// A second finger ends the drag, and only the first one's moves were ever drags
+		let gestures = feed(&mut r, &[Event::TouchStart(9, p(100, 0))], 0.0);
+		match gestures.as_slice().first() {
+			Some(&Gesture::DragEnd(pos)) => assert_eq!((pos.x, pos.y), (0, 0)),
+			_ => panic!("expected the drag to end, got {:?}", gestures),
+		}
+
+		let gestures = feed(&mut r, &[Event::TouchMove(9, p(101, 0))], 0.0);
+		assert!(gestures.iter().all(|g| match *g { Gesture::DragMove(_) => false, _ => true }));
+	}
+
+	#[test]
+	fn quick_still_touches_tap() {
+		let mut r = GestureRecognizer::new();
+
+		let gestures = feed(&mut r, &[Event::TouchStart(1, p(20, 20)), Event::TouchEnd(1, p(22, 21))], 0.0);
+		assert_eq!(taps(&gestures), vec![1]);
+
+		// Both fingers have to come up before it counts
+		feed(&mut r, &[Event::TouchStart(4, p(0, 0)), Event::TouchStart(5, p(80, 0))], 1.0);
+		assert!(taps(&feed(&mut r, &[Event::TouchEnd(4, p(0, 0))], 1.1)).is_empty());
+		assert_eq!(taps(&feed(&mut r, &[Event::TouchEnd(5, p(80, 0))], 1.1)), vec![2]);
+	}
+
+	#[test]
+	fn moving_cancels_a_tap() {
+		let mut r = GestureRecognizer::new();
+
+		// Even coming back to where it started
+		let gestures = feed(&mut r, &[
+			Event::TouchStart(1, p(20, 20)),
+			Event::TouchMove(1, p(60, 20)),
+			Event::TouchMove(1, p(20, 20)),
+			Event::TouchEnd(1, p(20, 20)),
+		], 0.0);
+
+		assert!(taps(&gestures).is_empty());
+	}
+
+	#[test]
+	fn slow_touches_dont_tap() {
+		let mut r = GestureRecognizer::new();
+
+		feed(&mut r, &[Event::TouchStart(1, p(20, 20))], 0.0);
+		assert!(taps(&feed(&mut r, &[Event::TouchEnd(1, p(20, 20))], TAP_DURATION + 0.1)).is_empty());
+	}
+
+	#[test]
+	fn spreading_fingers_pinches() {
+		let mut r = GestureRecognizer::new();
+		feed(&mut r, &[Event::TouchStart(7, p(0, 0)), Event::TouchStart(9, p(100, 0))], 0.0);
+
+		let gestures = feed(&mut r, &[Event::TouchMove(9, p(200, 0))], 0.0);
+		let pinch = gestures.iter().filter_map(|g| match *g { Gesture::Pinch(s) => Some(s), _ => None }).next();
+
+		assert!((pinch.unwrap() - 2.0).abs() < 1e-5, "{:?}", gestures);
+	}
+
+	fn rotation(gestures: &[Gesture]) -> f32 {
+		gestures.iter().filter_map(|g| match *g { Gesture::Rotate(a) => Some(a), _ => None }).next().unwrap()
+	}
+
+	#[test]
+	fn twisting_rotates() {
+		let mut r = GestureRecognizer::new();
+		feed(&mut r, &[Event::TouchStart(1, p(0, 0)), Event::TouchStart(2, p(1000, 0))], 0.0);
+
+		let angle = rotation(&feed(&mut r, &[Event::TouchMove(2, p(1000, 100))], 0.0));
+		assert!((angle - (0.1f32).atan()).abs() < 1e-4, "{}", angle);
+	}
+
+	#[test]
+	fn rotation_doesnt_jump_across_pi() {
+		let mut r = GestureRecognizer::new();
+
+		// The span between the fingers points just short of π, then just past it
+		feed(&mut r, &[Event::TouchStart(1, p(1500, 500)), Event::TouchStart(2, p(501, 550))], 0.0);
+		let angle = rotation(&feed(&mut r, &[Event::TouchMove(2, p(501, 450))], 0.0));
+
+		assert!((angle - 0.1).abs() < 1e-3, "{}", angle);
+	}
+}
@@ -0,0 +1,98 @@
+// Turns each frame's input into orders for the boat - aiming, tapping out a
+// route, steering, trimming the sail. It only reads Input, so a replay run
+// through it steers exactly the way the game did when it was recorded.
+
+use common::*;
+use autopilot::Autopilot;
+use boat::{BoatSim, BoatInput};
+use camera::Camera;
+use collision::CollisionWorld;
+use input::{Input, Axis, Action};
+use picking::{pick_screen, PickHit};
+
+/// How far ahead of the boat's heading holding the steer axis aims
+const STEER_LEAD: f32 = PI/4.0;
+/// How much sail a fully held throttle axis raises or lowers per second
+const THROTTLE_RATE: f32 = 0.5;
+
+pub struct Helm {
+	/// What the boat is currently being told to do
+	pub orders: BoatInput,
+	pub autopilot: Autopilot,
+
+	was_steering: bool,
+}
+
+impl Helm {
+	pub fn new(heading: f32) -> Self {
+		Helm {
+			orders: BoatInput::new(heading),
+			autopilot: Autopilot::new(),
+			was_steering: false,
+		}
+	}
+
+	/// Takes in a frame of input. Returns whatever a tap or click landed on,
+	/// whether or not anything came of it
+	pub fn update<C>(&mut self, input: &Input, camera: &Camera, world: &C, boat: &BoatSim, frame_dt: f32) -> Option<PickHit>
+		where C: CollisionWorld {
+
+		// While following a route, only a proper drag takes back control
+		if let Some(aim) = input.aim() {
+			if !self.autopilot.is_engaged() || aim.amount > 0.0 {
+				self.autopilot.disengage();
+				self.orders.target_speed = aim.amount;
+
+				if aim.amount > 0.0 {
+					let heading = match aim.drag {
+						Some((start, end)) => camera.heading_for_drag(start, end),
+						None => camera.heading_for_screen_dir(aim.direction),
+					};
+
+					if let Some(heading) = heading {
+						self.orders.target_heading = heading;
+					}
+				}
+			}
+		}
+
+		// Tapping the sea sails there, by way of any waypoints already set
+		let pick = input.point().and_then(|pos| pick_screen(camera, pos, world));
+
+		if let Some(PickHit::Sea(target)) = pick {
+			self.autopilot.add_waypoint(target);
+		}
+
+		// Heading increases anticlockwise, so steering right means aiming lower.
+		// Once steering stops, hold whatever heading the boat had come round to
+		let steer = input.axis(Axis::Steer);
+		if steer != 0.0 {
+			self.autopilot.disengage();
+			self.orders.target_heading = boat.heading - steer * STEER_LEAD;
+		} else if self.was_steering {
+			self.orders.target_heading = boat.heading;
+		}
+
+		self.was_steering = steer != 0.0;
+
+		let throttle = input.axis(Axis::Throttle);
+		if throttle != 0.0 {
+			let target_speed = self.orders.target_speed + throttle * THROTTLE_RATE * frame_dt;
+			self.orders.target_speed = target_speed.max(0.0).min(1.0);
+		}
+
+		if input.action(Action::DropSail) {
+			self.autopilot.disengage();
+			self.orders.target_speed = 0.0;
+		}
+
+		pick
+	}
+
+	/// Lets the autopilot steer, if it's engaged. Runs before every simulation step
+	pub fn step(&mut self, boat: &BoatSim) {
+		if self.autopilot.is_engaged() {
+			self.autopilot.steer(boat, &mut self.orders);
+		}
+	}
+}
@@ -0,0 +1,692 @@
+// Turns raw events from the platform - mouse, touch, keyboard and gamepad - into
+// the handful of things gameplay actually cares about. Which device does what
+// is decided by InputBindings, loaded from assets/input.cfg, so gameplay code
+// never has to look at a device event.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use common::*;
+use events::{Event, Key};
+use gamepad::{GamepadMapping, GamepadState};
+use gestures::{Gesture, GestureRecognizer};
+use console;
+
+/// A mouse button that comes back up within this many pixels of where it
+/// went down is a click rather than a drag
+const CLICK_SLOP: f32 = 10.0;
+
+/// Inputs that hold a value every frame
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Axis {
+	/// Turn to port (-1) or starboard (1)
+	Steer,
+	/// Take in (-1) or let out (1) sail
+	Throttle,
+	/// Radians to swing the camera around the boat by this frame
+	CameraOrbit,
+	/// How much to zoom in this frame, as the log of the zoom factor
+	CameraZoom,
+}
+
+const NUM_AXES: usize = 4;
+
+impl Axis {
+	fn from_name(name: &str) -> Option<Axis> {
+		match name {
+			"steer" => Some(Axis::Steer),
+			"throttle" => Some(Axis::Throttle),
+			"camera_orbit" => Some(Axis::CameraOrbit),
+			"camera_zoom" => Some(Axis::CameraZoom),
+			_ => None,
+		}
+	}
+
+	/// Whether key and stick bindings should be scaled by frame time, rather
+	/// than just held
+	fn is_rate(&self) -> bool {
+		match *self {
+			Axis::Steer | Axis::Throttle => false,
+			Axis::CameraOrbit | Axis::CameraZoom => true,
+		}
+	}
+}
+
+/// Inputs that happen once
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Action {
+	DropSail,
+	Fullscreen,
+	/// Print everything recorded so far, for attaching to bug reports
+	DumpReplay,
+	ToggleCamera,
+	/// Fetch and rebuild shaders. Only does anything in debug builds
+	ReloadShaders,
+}
+
+impl Action {
+	fn from_name(name: &str) -> Option<Action> {
+		match name {
+			"drop_sail" => Some(Action::DropSail),
+			"fullscreen" => Some(Action::Fullscreen),
+			"dump_replay" => Some(Action::DumpReplay),
+			"toggle_camera" => Some(Action::ToggleCamera),
+			"reload_shaders" => Some(Action::ReloadShaders),
+			_ => None,
+		}
+	}
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Binding {
+	Key(Key),
+	Tap(usize),
+	Pinch,
+	Twist,
+	Wheel,
+	Click,
+	Drag,
+	Stick,
+	GamepadAxis(usize),
+	GamepadButton(usize),
+}
+
+impl Binding {
+	fn parse(s: &str) -> Option<Binding> {
+		let mut parts = s.splitn(2, ':');
+		let kind = parts.next().unwrap_or("");
+		let arg = parts.next();
+
+		let index = || arg.and_then(|a| a.parse().ok());
+
+		match kind {
+			"key" => arg.and_then(Key::from_code).map(Binding::Key),
+			"tap" => index().map(Binding::Tap),
+			"pinch" => Some(Binding::Pinch),
+			"twist" => Some(Binding::Twist),
+			"wheel" => Some(Binding::Wheel),
+			"click" => Some(Binding::Click),
+			"drag" => Some(Binding::Drag),
+			"stick" => Some(Binding::Stick),
+			"pad_axis" => index().map(Binding::GamepadAxis),
+			"pad_button" => index().map(Binding::GamepadButton),
+			_ => None,
+		}
+	}
+}
+
+/// Where the player is pointing the boat, in screen space - from a drag or a
+/// stick. `amount` is how much sail they want, from 0 to 1
+#[derive(Copy, Clone, Debug)]
+pub struct Aim {
+	pub direction: Vec2,
+	pub amount: f32,
+	/// The screen points a drag went from and to, so it can be projected
+	/// into the world
+	pub drag: Option<(Vec2i, Vec2i)>,
+}
+
+/// A line of input.cfg that couldn't be understood
+#[derive(Clone, Debug, PartialEq)]
+pub struct BindingError {
+	pub line: usize,
+	pub text: String,
+}
+
+impl fmt::Display for BindingError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "input.cfg:{}: couldn't understand '{}'", self.line, self.text)
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct InputBindings {
+	pub axes: Vec<(Axis, Binding, f32)>,
+	pub actions: Vec<(Action, Binding)>,
+	pub aim: Vec<Binding>,
+	/// What picks a point on the screen - taps and clicks
+	pub point: Vec<Binding>,
+
+	/// How far a drag has to go, in pixels, before it raises any sail
+	pub drag_threshold: f32,
+	pub gamepad: GamepadMapping,
+}
+
+impl InputBindings {
+	pub fn empty() -> Self {
+		InputBindings {
+			axes: Vec::new(),
+			actions: Vec::new(),
+			aim: Vec::new(),
+			point: Vec::new(),
+
+			drag_threshold: 50.0,
+			gamepad: GamepadMapping::default(),
+		}
+	}
+
+	/// Reads bindings in the format described in assets/input.cfg. Lines that
+	/// don't make sense are skipped and handed back, so one typo doesn't take
+	/// every other control down with it
+	pub fn parse(src: &str) -> (Self, Vec<BindingError>) {
+		let mut bindings = InputBindings::empty();
+		let mut errors = Vec::new();
+
+		for (line_no, line) in src.lines().enumerate() {
+			let line = line.split('#').next().unwrap_or("").trim();
+			if line.is_empty() { continue }
+
+			if !bindings.parse_line(line) {
+				errors.push(BindingError { line: line_no + 1, text: line.to_string() });
+			}
+		}
+
+		(bindings, errors)
+	}
+
+	fn parse_line(&mut self, line: &str) -> bool {
+		let words: Vec<&str> = line.split_whitespace().collect();
+		let word = |i: usize| words.get(i).cloned().unwrap_or("");
+
+		match (word(0), words.len()) {
+			("axis", 3) | ("axis", 4) => {
+				let scale = if words.len() == 4 { word(3).parse().ok() } else { Some(1.0) };
+
+				match (Axis::from_name(word(1)), Binding::parse(word(2)), scale) {
+					(Some(axis), Some(binding), Some(scale)) => { self.axes.push((axis, binding, scale)); true }
+					_ => false,
+				}
+			}
+
+			("action", 3) => {
+				match (Action::from_name(word(1)), Binding::parse(word(2))) {
+					(Some(action), Some(binding)) => { self.actions.push((action, binding)); true }
+					_ => false,
+				}
+			}
+
+			("aim", 2) => {
+				match Binding::parse(word(1)) {
+					Some(binding @ Binding::Drag) | Some(binding @ Binding::Stick) => { self.aim.push(binding); true }
+					_ => false,
+				}
+			}
+
+			("point", 2) => {
+				match Binding::parse(word(1)) {
+					Some(binding @ Binding::Click) | Some(binding @ Binding::Tap(_)) => { self.point.push(binding); true }
+					_ => false,
+				}
+			}
+
+			("drag_threshold", 2) => {
+				word(1).parse().map(|v| self.drag_threshold = v).is_ok()
+			}
+
+			("gamepad", 3) => self.parse_gamepad_setting(word(1), word(2)),
+
+			_ => false,
+		}
+	}
+
+	fn parse_gamepad_setting(&mut self, setting: &str, value: &str) -> bool {
+		let pad = &mut self.gamepad;
+
+		match setting {
+			"steer_x_axis" => value.parse().map(|v| pad.steer_x_axis = v).is_ok(),
+			"steer_y_axis" => value.parse().map(|v| pad.steer_y_axis = v).is_ok(),
+			"invert_y" => value.parse().map(|v| pad.invert_y = v).is_ok(),
+			"dead_zone" => match value.parse() {
+				Ok(v) if v >= 0.0 && v < 1.0 => { pad.dead_zone = v; true }
+				_ => false,
+			},
+			"throttle_button" => match value {
+				"none" => { pad.throttle_button = None; true }
+				_ => value.parse().map(|v| pad.throttle_button = Some(v)).is_ok(),
+			},
+			_ => false,
+		}
+	}
+
+	fn has_aim(&self, binding: Binding) -> bool {
+		self.aim.iter().any(|&b| b == binding)
+	}
+}
+
+/// The input state for one frame. Call update once per frame with that
+/// frame's events, then read axes, actions and aim from it
+pub struct Input {
+	pub bindings: InputBindings,
+
+	gestures: GestureRecognizer,
+	held_keys: HashSet<Key>,
+	gamepads: Vec<GamepadState>,
+	prev_gamepads: Vec<GamepadState>,
+
+	mouse_drag_start: Option<Vec2i>,
+	touch_drag_start: Option<Vec2i>,
+
+	axes: [f32; NUM_AXES],
+	actions: Vec<Action>,
+	aim: Option<Aim>,
+	point: Option<Vec2i>,
+}
+
+impl Input {
+	pub fn new(bindings: InputBindings) -> Self {
+		Input {
+			bindings,
+
+			gestures: GestureRecognizer::new(),
+			held_keys: HashSet::new(),
+			gamepads: Vec::new(),
+			prev_gamepads: Vec::new(),
+
+			mouse_drag_start: None,
+			touch_drag_start: None,
+
+			axes: [0.0; NUM_AXES],
+			actions: Vec::new(),
+			aim: None,
+			point: None,
+		}
+	}
+
+	pub fn axis(&self, axis: Axis) -> f32 { self.axes[axis as usize] }
+
+	/// Whether `action` was triggered this frame
+	pub fn action(&self, action: Action) -> bool { self.actions.contains(&action) }
+
+	/// Set on frames where a drag moved, or while a stick is pushed
+	pub fn aim(&self) -> Option<Aim> { self.aim }
+
+	/// Where on the screen the player tapped or clicked this frame, if they did
+	pub fn point(&self) -> Option<Vec2i> { self.point }
+
+	/// `gamepads` is this frame's poll of every connected pad
+	pub fn update(&mut self, events: &[Event], gamepads: &[GamepadState], now: f64, dt: f32) {
+		self.axes = [0.0; NUM_AXES];
+		self.actions.clear();
+		self.aim = None;
+		self.point = None;
+
+		for event in events.iter() {
+			self.gestures.handle_event(event, now);
+			self.handle_event(event);
+		}
+
+		for gesture in self.gestures.gestures().to_vec() {
+			self.handle_gesture(gesture);
+		}
+
+		self.gestures.clear();
+
+		::std::mem::swap(&mut self.gamepads, &mut self.prev_gamepads);
+		self.gamepads.clear();
+		self.gamepads.extend_from_slice(gamepads);
+
+		self.update_held(dt);
+	}
+
+	fn handle_event(&mut self, event: &Event) {
+		match *event {
+			Event::Down(pos) => self.mouse_drag_start = Some(pos),
+			Event::Up(pos) => {
+				let is_click = self.mouse_drag_start
+					.map_or(false, |start| (pos - start).length() <= CLICK_SLOP);
+
+				if is_click && self.bindings.point.contains(&Binding::Click) {
+					self.point = Some(pos);
+				}
+
+				self.mouse_drag_start = None;
+			}
+
+			Event::Move(pos) => if self.bindings.has_aim(Binding::Drag) {
+				if let Some(start) = self.mouse_drag_start {
+					self.aim = Some(self.drag_aim(start, pos));
+				}
+			}
+
+			Event::Wheel(notches) => self.add_to_axes(Binding::Wheel, notches),
+
+			Event::KeyDown(key) => {
+				self.held_keys.insert(key);
+				self.trigger_actions(Binding::Key(key));
+			}
+
+			Event::KeyUp(key) => {
+				self.held_keys.remove(&key);
+			}
+
+			Event::GamepadConnected(index) => {
+				console::set_section(format!("Gamepad {}", index), "connected");
+			}
+
+			Event::GamepadDisconnected(index) => {
+				console::set_section(format!("Gamepad {}", index), "disconnected");
+			}
+
+			_ => {}
+		}
+	}
+
+	fn handle_gesture(&mut self, gesture: Gesture) {
+		match gesture {
+			Gesture::DragStart(pos) => self.touch_drag_start = Some(pos),
+			Gesture::DragEnd(_) => self.touch_drag_start = None,
+
+			Gesture::DragMove(pos) => if self.bindings.has_aim(Binding::Drag) {
+				if let Some(start) = self.touch_drag_start {
+					self.aim = Some(self.drag_aim(start, pos));
+				}
+			}
+
+			Gesture::Tap { pos, fingers } => {
+				self.trigger_actions(Binding::Tap(fingers));
+
+				if self.bindings.point.contains(&Binding::Tap(fingers)) {
+					self.point = Some(pos);
+				}
+			}
+
+			Gesture::Pinch(ratio) => self.add_to_axes(Binding::Pinch, ratio.ln()),
+			Gesture::Rotate(angle) => self.add_to_axes(Binding::Twist, angle),
+		}
+	}
+
+	/// Keys and gamepads contribute for as long as they're held
+	fn update_held(&mut self, dt: f32) {
+		for i in 0..self.bindings.axes.len() {
+			let (axis, binding, scale) = self.bindings.axes[i];
+
+			let value = match binding {
+				Binding::Key(key) => if self.held_keys.contains(&key) { 1.0 } else { 0.0 },
+				Binding::GamepadAxis(index) => self.gamepad_axis(index),
+				_ => continue,
+			};
+
+			let value = if axis.is_rate() { value * dt } else { value };
+			self.axes[axis as usize] += value * scale;
+		}
+
+		for axis in [Axis::Steer, Axis::Throttle].iter() {
+			let value = &mut self.axes[*axis as usize];
+			*value = value.max(-1.0).min(1.0);
+		}
+
+		for i in 0..self.bindings.actions.len() {
+			if let (action, Binding::GamepadButton(index)) = self.bindings.actions[i] {
+				if self.gamepad_button_pressed(index) {
+					self.actions.push(action);
+				}
+			}
+		}
+
+		if self.bindings.has_aim(Binding::Stick) {
+			let mapping = &self.bindings.gamepad;
+
+			// The first pad pushed out of its dead zone wins
+			if let Some((direction, amount)) = self.gamepads.iter().filter_map(|pad| mapping.steering(pad)).next() {
+				self.aim = Some(Aim { direction, amount, drag: None });
+			}
+		}
+	}
+
+	fn trigger_actions(&mut self, trigger: Binding) {
+		for &(action, binding) in self.bindings.actions.iter() {
+			if binding == trigger {
+				self.actions.push(action);
+			}
+		}
+	}
+
+	fn add_to_axes(&mut self, source: Binding, value: f32) {
+		for &(axis, binding, scale) in self.bindings.axes.iter() {
+			if binding == source {
+				self.axes[axis as usize] += value * scale;
+			}
+		}
+	}
+
+	/// The further past the threshold a drag goes, the more sail goes up
+	fn drag_aim(&self, start: Vec2i, pos: Vec2i) -> Aim {
+		let threshold = self.bindings.drag_threshold;
+		let diff = pos - start;
+		let dist = diff.length();
+
+		Aim {
+			direction: diff.to_vec2(),
+			amount: ((dist - threshold).min(100.0) / 100.0).max(0.0),
+			drag: Some((start, pos)),
+		}
+	}
+
+	/// The largest deflection of `index` across all pads, past the dead zone
+	fn gamepad_axis(&self, index: usize) -> f32 {
+		let mapping = &self.bindings.gamepad;
+
+		self.gamepads.iter()
+			.map(|pad| {
+				let value = pad.axis(index);
+				mapping.apply_dead_zone(value.abs()) * value.signum()
+			})
+			.fold(0.0, |acc: f32, v| if v.abs() > acc.abs() { v } else { acc })
+	}
+
+	/// Whether `index` went down this frame on any pad
+	fn gamepad_button_pressed(&self, index: usize) -> bool {
+		let is_down = |pad: &GamepadState| pad.button(index) > 0.5;
+
+		self.gamepads.iter().any(|pad| {
+			let was_down = self.prev_gamepads.iter()
+				.find(|prev| prev.index == pad.index)
+				.map_or(false, |prev| is_down(prev));
+
+			is_down(pad) && !was_down
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use resources as res;
+
+	fn bindings(src: &str) -> InputBindings {
+		let (bindings, errors) = InputBindings::parse(src);
+		assert_eq!(errors, Vec::new());
+		bindings
+	}
+
+	fn key(code: &str) -> Key { Key::from_code(code).unwrap() }
+
+	/// Runs one frame of `events` at 60Hz
+	fn frame(input: &mut Input, events: &[Event]) {
+		input.update(events, &[], 0.0, 1.0 / 60.0);
+	}
+
+	#[test]
+	fn parses_every_kind_of_line() {
+		let b = bindings("
+			axis steer key:ArrowRight
+			axis camera_zoom wheel -0.15
+			action drop_sail key:Space
+			action fullscreen tap:3
+			aim drag
+			point click
+			drag_threshold 30
+			gamepad throttle_button none
+		");
+
+		assert_eq!(b.axes, vec![
+			(Axis::Steer, Binding::Key(key("ArrowRight")), 1.0),
+			(Axis::CameraZoom, Binding::Wheel, -0.15),
+		]);
+		assert_eq!(b.actions, vec![(Action::DropSail, Binding::Key(key("Space"))), (Action::Fullscreen, Binding::Tap(3))]);
+		assert_eq!(b.aim, vec![Binding::Drag]);
+		assert_eq!(b.point, vec![Binding::Click]);
+		assert_eq!(b.drag_threshold, 30.0);
+		assert_eq!(b.gamepad.throttle_button, None);
+	}
+
+	#[test]
+	fn the_shipped_config_parses_cleanly() {
+		bindings(res::config::INPUT);
+	}
+
+	#[test]
+	fn comments_and_blank_lines_are_skipped() {
+		let b = bindings("# a comment\n\n   \naction drop_sail key:Space # trailing comment\n");
+		assert_eq!(b.actions, vec![(Action::DropSail, Binding::Key(key("Space")))]);
+	}
+
+	#[test]
+	fn bad_lines_are_reported_and_skipped() {
+		let (b, errors) = InputBindings::parse("axis steer key:KeyD\naxis sideways key:KeyA\naim key:KeyW\naction drop_sail\npoint pinch\nbogus\naction drop_sail key:Space");
+
+		let lines: Vec<usize> = errors.iter().map(|e| e.line).collect();
+		assert_eq!(lines, vec![2, 3, 4, 5, 6]);
+		assert_eq!(errors[0].to_string(), "input.cfg:2: couldn't understand 'axis sideways key:KeyA'");
+
+		// Everything around them still gets through
+		assert_eq!(b.axes.len(), 1);
+		assert_eq!(b.actions.len(), 1);
+	}
+
+	#[test]
+	fn dead_zone_must_be_below_one() {
+		let default = GamepadMapping::default().dead_zone;
+
+		for bad in ["1", "1.5", "-0.1", "NaN"].iter() {
+			let (bindings, errors) = InputBindings::parse(&format!("gamepad dead_zone {}", bad));
+			assert_eq!(bindings.gamepad.dead_zone, default, "accepted a dead zone of {}", bad);
+			assert_eq!(errors.len(), 1);
+		}
+
+		assert_eq!(bindings("gamepad dead_zone 0.5").gamepad.dead_zone, 0.5);
+		assert_eq!(bindings("gamepad dead_zone 0").gamepad.dead_zone, 0.0);
+	}
+
+	#[test]
+	fn held_keys_drive_axes_until_released() {
+		let mut input = Input::new(bindings("axis steer key:ArrowRight 1\naxis steer key:ArrowLeft -1"));
+
+		frame(&mut input, &[Event::KeyDown(key("ArrowRight"))]);
+		assert_eq!(input.axis(Axis::Steer), 1.0);
+
+		frame(&mut input, &[]);
+		assert_eq!(input.axis(Axis::Steer), 1.0);
+
+		// Opposing keys cancel out
+		frame(&mut input, &[Event::KeyDown(key("ArrowLeft"))]);
+		assert_eq!(input.axis(Axis::Steer), 0.0);
+
+		frame(&mut input, &[Event::KeyUp(key("ArrowRight"))]);
+		assert_eq!(input.axis(Axis::Steer), -1.0);
+
+		frame(&mut input, &[Event::KeyUp(key("ArrowLeft"))]);
+		assert_eq!(input.axis(Axis::Steer), 0.0);
+	}
+
+	#[test]
+	fn steer_and_throttle_are_clamped() {
+		let mut input = Input::new(bindings("axis throttle key:KeyW 1\naxis throttle key:ArrowUp 1"));
+
+		frame(&mut input, &[Event::KeyDown(key("KeyW")), Event::KeyDown(key("ArrowUp"))]);
+		assert_eq!(input.axis(Axis::Throttle), 1.0);
+	}
+
+	#[test]
+	fn rate_axes_scale_with_frame_time() {
+		let mut input = Input::new(bindings("axis camera_orbit key:KeyQ 1.5\naxis camera_zoom wheel -0.15"));
+
+		input.update(&[Event::KeyDown(key("KeyQ")), Event::Wheel(2.0)], &[], 0.0, 0.5);
+		assert_eq!(input.axis(Axis::CameraOrbit), 0.75);
+
+		// Wheel notches are per event, not per second
+		assert!((input.axis(Axis::CameraZoom) - -0.3).abs() < 1e-6);
+	}
+
+	#[test]
+	fn actions_fire_once_per_press() {
+		let mut input = Input::new(bindings("action drop_sail key:Space"));
+
+		frame(&mut input, &[Event::KeyDown(key("Space"))]);
+		assert!(input.action(Action::DropSail));
+
+		frame(&mut input, &[]);
+		assert!(!input.action(Action::DropSail));
+	}
+
+	#[test]
+	fn rebinding_moves_the_control() {
+		let mut input = Input::new(bindings("axis steer key:KeyJ 1\naction drop_sail key:KeyK"));
+
+		frame(&mut input, &[Event::KeyDown(key("ArrowRight")), Event::KeyDown(key("Space"))]);
+		assert_eq!(input.axis(Axis::Steer), 0.0);
+		assert!(!input.action(Action::DropSail));
+
+		frame(&mut input, &[Event::KeyDown(key("KeyJ")), Event::KeyDown(key("KeyK"))]);
+		assert_eq!(input.axis(Axis::Steer), 1.0);
+		assert!(input.action(Action::DropSail));
+	}
+
+	#[test]
+	fn gamepad_buttons_fire_on_the_press() {
+		let mut input = Input::new(bindings("action drop_sail pad_button:1"));
+		let mut pad = GamepadState::new(0);
+		pad.buttons[1] = 1.0;
+
+		input.update(&[], &[pad], 0.0, 1.0 / 60.0);
+		assert!(input.action(Action::DropSail));
+
+		input.update(&[], &[pad], 0.0, 1.0 / 60.0);
+		assert!(!input.action(Action::DropSail), "held buttons shouldn't repeat");
+	}
+
+	#[test]
+	fn click_within_the_slop_points() {
+		let mut input = Input::new(bindings("point click\naim drag"));
+		let (down, up) = (Vec2i::new(100, 100), Vec2i::new(106, 108));
+
+		frame(&mut input, &[Event::Down(down), Event::Up(up)]);
+		assert_eq!(input.point().map(|p| (p.x, p.y)), Some((106, 108)));
+	}
+
+	#[test]
+	fn drag_past_the_slop_doesnt_point() {
+		let mut input = Input::new(bindings("point click\naim drag"));
+		let (down, up) = (Vec2i::new(100, 100), Vec2i::new(120, 100));
+
+		frame(&mut input, &[Event::Down(down), Event::Move(up), Event::Up(up)]);
+		assert!(input.point().is_none());
+	}
+
+	#[test]
+	fn mouse_drags_aim() {
+		let mut input = Input::new(bindings("aim drag\ndrag_threshold 50"));
+		let start = Vec2i::new(100, 100);
+
+		// Short of the threshold, it points without raising sail
+		frame(&mut input, &[Event::Down(start), Event::Move(Vec2i::new(130, 100))]);
+		let aim = input.aim().unwrap();
+		assert_eq!(aim.amount, 0.0);
+		assert_eq!(aim.drag.map(|(s, e)| (s.x, e.x)), Some((100, 130)));
+
+		frame(&mut input, &[Event::Move(Vec2i::new(250, 100))]);
+		assert_eq!(input.aim().unwrap().amount, 1.0);
+
+		// Once the button's up, moving doesn't aim
+		frame(&mut input, &[Event::Up(Vec2i::new(250, 100)), Event::Move(Vec2i::new(300, 100))]);
+		assert!(input.aim().is_none());
+	}
+
+	#[test]
+	fn unbound_drags_dont_aim() {
+		let mut input = Input::new(bindings(""));
+
+		frame(&mut input, &[Event::Down(Vec2i::new(0, 0)), Event::Move(Vec2i::new(200, 0))]);
+		assert!(input.aim().is_none());
+	}
+}
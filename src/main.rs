@@ -19,49 +19,69 @@ pub mod mut_rc;
 pub mod resources;
 pub mod rendering;
 pub mod console;
-pub mod webgl;
-
-mod events;
-
-use coro_util::*;
-use webgl::*;
+pub mod platform;
+pub mod events;
+pub mod boat;
+pub mod timestep;
+pub mod ocean;
+pub mod wind;
+pub mod sail;
+pub mod terrain;
+pub mod world;
+pub mod collision;
+pub mod gamepad;
+pub mod gestures;
+pub mod input;
+pub mod replay;
+pub mod camera;
+pub mod picking;
+pub mod autopilot;
+pub mod helm;
+pub mod shader_reload;
+
+use platform::*;
+use boat::*;
+use timestep::*;
+use ocean::*;
+use wind::*;
+use world::*;
+use input::*;
+use replay::*;
+use camera::*;
+use picking::*;
+use helm::*;
+use shader_reload::*;
 
 use rendering::*;
 use rendering::mesh_builder::*;
+#[cfg(debug)] use rendering::leaks::GlResource;
 
 use std::time::Instant;
 
-#[repr(C)]
-#[derive(Copy, Clone)]
-pub struct ColorVertex(Vec3, Vec3);
-
-impl Vertex for ColorVertex {
-	fn get_layout() -> VertexLayout {
-		VertexLayout::new::<Self>()
-			.add_binding(0, 3, 0)
-			.add_binding(1, 3, 12)
-	}
-}
-
-const CAMERA_PITCH: f32 = PI/8.0;
 const CAMERA_YAW: f32 = PI/4.0;
-const CAMERA_FOV: f32 = PI/4.0;
 const CAMERA_DISTANCE: f32 = 12.0;
 
+const SIM_STEP: f64 = 1.0 / 60.0;
+
+const WORLD_SEED: usize = 0x5eab0a7;
+const WORLD_VIEW_RADIUS: i32 = 3;
+const CHUNKS_PER_FRAME: usize = 1;
+
 // #[link_args = "-s ASSERTIONS=1"] extern "C" {}
 // #[link_args = "-g4"] extern "C" {}
 
 fn main() {
-	set_coro_as_main_loop(|| {
+	let platform = DefaultPlatform::new();
+	let main_loop = platform.clone();
+
+	main_loop.set_coro_as_main_loop(move || {
 		console::init();
 		console::set_color("#222");
 
-		let gl_ctx = WebGLContext::new();
+		let gl_ctx = platform.create_context();
 		gl_ctx.set_background(Color::grey_a(0.0, 0.0));
 
-		let mut event_queue = Vec::new();
-
-		events::init_event_queue(&mut event_queue);
+		let event_queue = platform.create_event_queue();
 
 		unsafe {
 			gl::Enable(gl::DEPTH_TEST);
@@ -70,185 +90,259 @@ fn main() {
 			gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
 		}
 
-		let boat_mesh: Mesh = {
-			use ColorVertex as V;
-
-			let mut mb = MeshBuilder::new();
-			let color = Color::rgb8(211, 126, 78).into();
-			let color2 = Color::rgb8(187, 97, 53).into();
-
-			let vs = [
-				V(Vec3::new(-0.5, 0.2, 0.5), color),
-				V(Vec3::new( 0.9, 0.2, 0.0), color),
-				V(Vec3::new(-0.5, 0.2,-0.5), color),
-
-				V(Vec3::new(-0.1,-0.3, 0.0), color2),
-			];
-
-			let es = [
-				0, 1, 2,
-
-				0, 3, 1,
-				1, 3, 2,
-				2, 3, 0,
-			];
-
-			mb.add_direct(&vs, &es);
-
-			let mast_color = Color::rgb8(187, 112, 70).into();
-			let sail_color = Color::rgb8(171, 220, 107).into();
-
-			let mast_width = 0.02 * 2.0f32.sqrt();
-			let mast_offset = Vec3::new(0.5, 0.0, 0.0);
-			let mast_height = 1.5;
-
-			mb.add_quad(&[
-				V(Vec3::new(-mast_width,        0.18,-mast_width) + mast_offset, mast_color),
-				V(Vec3::new( mast_width,        0.18, mast_width) + mast_offset, mast_color),
-				V(Vec3::new( mast_width, mast_height, mast_width) + mast_offset, mast_color),
-				V(Vec3::new(-mast_width, mast_height,-mast_width) + mast_offset, mast_color),
-			]);
-
-			mb.add_quad(&[
-				V(Vec3::new(-mast_width,        0.18, mast_width) + mast_offset, mast_color),
-				V(Vec3::new( mast_width,        0.18,-mast_width) + mast_offset, mast_color),
-				V(Vec3::new( mast_width, mast_height,-mast_width) + mast_offset, mast_color),
-				V(Vec3::new(-mast_width, mast_height, mast_width) + mast_offset, mast_color),
-			]);
-
-			mb.add_convex_poly(&[
-				V(Vec3::new(-0.0, mast_height, 0.0) + mast_offset, sail_color),
-
-				V(Vec3::new(-0.45, 0.25, 0.0), sail_color),
-				V(Vec3::new(-0.1, 0.25, 0.1), sail_color),
-				V(Vec3::new(-0.0, 0.25, 0.0) + mast_offset, sail_color),
-			]);
-
-			mb.into()
+		let (boat_mesh, sail_mesh): (Mesh, Mesh) = {
+			let (mut mb, mut sail_mb) = (MeshBuilder::new(), MeshBuilder::new());
+			build_boat_mesh(&mut mb, &mut sail_mb);
+			(mb.into(), sail_mb.into())
 		};
 
-		let sea_mesh: Mesh = {
+		let marker_mesh: Mesh = {
 			use ColorVertex as V;
 
+			let color = Color::rgb8(220, 90, 80).into();
 			let mut mb = MeshBuilder::new();
-			let color = Color::rgb8(122, 158, 198).into();
 
-			mb.add_quad(&[
-				V(Vec3::new(-1.0, 0.0,-1.0), color),
-				V(Vec3::new( 1.0, 0.0,-1.0), color),
-				V(Vec3::new( 1.0, 0.0, 1.0), color),
-				V(Vec3::new(-1.0, 0.0, 1.0), color),
+			mb.add_convex_poly(&[
+				V(Vec3::new( 0.0, 0.05,-0.3), color),
+				V(Vec3::new(-0.3, 0.05, 0.0), color),
+				V(Vec3::new( 0.0, 0.05, 0.3), color),
+				V(Vec3::new( 0.3, 0.05, 0.0), color),
 			]);
 
 			mb.into()
 		};
 
-		let view_mat = Mat4::translate(Vec3::new(0.0, 0.0,-CAMERA_DISTANCE))
-			* Mat4::xrot(CAMERA_PITCH)
-			* Mat4::yrot(CAMERA_YAW);
-
-		let shader = Shader::new(res::shaders::BASIC_VS, res::shaders::BASIC_FS);
+		let ocean = Ocean::new(WORLD_SEED);
+		let mut wind = Wind::new(WORLD_SEED, -PI / 3.0);
+		let mut sea_mb = MeshBuilder::new();
+		let mut sea_mesh = Mesh::new();
+		let mut route_mb = MeshBuilder::new();
+		let mut route_mesh = Mesh::new();
+
+		let mut world = ChunkManager::new(WORLD_SEED, WORLD_VIEW_RADIUS, CHUNKS_PER_FRAME);
+
+		let mut camera = Camera::new(CAMERA_YAW, CAMERA_DISTANCE);
+
+		let mut shader = match ReloadableShader::new(&platform,
+			res::shaders::BASIC_VS_PATH, res::shaders::BASIC_FS_PATH,
+			res::shaders::BASIC_VS, res::shaders::BASIC_FS)
+		{
+			Ok(shader) => shader,
+			Err(e) => {
+				console::set_color("#c22");
+				console::set_section("shader", format!("<pre>{}</pre>", console::escape_html(&e.to_string())));
+				console::update();
+				return
+			}
+		};
 		shader.use_program();
 		shader.set_view(&Mat4::ident());
 
-		let drag_threshold = 50.0;
-		let mut drag_start = None;
+		let (bindings, binding_errors) = InputBindings::parse(res::config::INPUT);
+		if !binding_errors.is_empty() {
+			let errors: Vec<String> = binding_errors.iter().map(|e| e.to_string()).collect();
+			console::set_color("#c22");
+			console::set_section("input", format!("<pre>{}</pre>", console::escape_html(&errors.join("\n"))));
+		}
+
+		let mut input = Input::new(bindings);
+		let mut helm = Helm::new(-3.0 * CAMERA_YAW / 2.0);
+		let mut boat = BoatSim::new(helm.orders.target_heading);
+		let mut prev_boat = boat;
+
+		let mut timestep = FixedTimestep::new(SIM_STEP);
+		let mut last_frame_time = None;
 
-		let mut wave_phase = 0.0;
+		let mut replay_errors = Vec::new();
+		let mut recorder = Recorder::from_env().unwrap_or_else(|e| { replay_errors.push(e); Recorder::new() });
+		let mut replay = Replay::from_env().unwrap_or_else(|e| { replay_errors.push(e); None });
+		let mut trajectory = TrajectoryLog::from_env().unwrap_or_else(|e| { replay_errors.push(e); TrajectoryLog::new() });
 
-		let mut target_heading = -3.0 * CAMERA_YAW / 2.0;
-		let mut target_speed = 0.0;
+		if !replay_errors.is_empty() {
+			console::set_color("#c22");
+			console::set_section("replay", format!("<pre>{}</pre>", console::escape_html(&replay_errors.join("\n"))));
+		}
 
-		let mut boat_heading_rate = 0.0;
-		let mut boat_heading = target_heading;
-		let mut boat_speed = 0.0;
+		let mut gamepads = Vec::new();
+		let mut frame = 0;
 
 		loop {
 			let frame_start = Instant::now();
 
-			use events::Event;
+			// Either live input or the next frame of a replay, but from here on
+			// gameplay can't tell which
+			let frame_input = match replay {
+				Some(ref mut replay) => match replay.next_frame() {
+					Some(frame_input) => frame_input.clone(),
+					None => break,
+				},
+
+				None => {
+					platform.poll_gamepads(&mut gamepads);
+
+					FrameInput {
+						frame,
+						time: platform.now(),
+						events: event_queue.drain(),
+						gamepads: gamepads.clone(),
+					}
+				}
+			};
 
-			for e in event_queue.iter() {
-				match *e {
-					Event::Resize(sz) => unsafe {
-						gl::Viewport(0, 0, sz.x, sz.y);
+			// Anything that arrived while replaying is dropped, not saved for later
+			event_queue.drain();
+			if let Err(e) = recorder.record(&frame_input) {
+				console::set_color("#c22");
+				console::set_section("replay", format!("<pre>{}</pre>", console::escape_html(&e)));
+			}
 
-						let aspect = sz.x as f32 / sz.y as f32;
-						let proj_mat = Mat4::perspective(CAMERA_FOV, aspect, 1.0, 100.0);
-						let proj_view = proj_mat * view_mat;
+			frame += 1;
 
-						shader.set_proj(&proj_view);
-					}
+			let now = frame_input.time;
+			let frame_dt = last_frame_time.map_or(0.0, |last| (now - last) as f32);
+			last_frame_time = Some(now);
 
-					Event::Down(pos) => {
-						drag_start = Some(pos);
-					}
+			use events::Event;
 
-					Event::Move(pos) => if drag_start.is_some() {
-						let drag_start = drag_start.unwrap_or(pos);
-						let diff = pos - drag_start;
-						let dist = diff.length();
+			for e in frame_input.events.iter() {
+				if let Event::Resize(sz) = *e {
+					unsafe { gl::Viewport(0, 0, sz.x, sz.y); }
+					camera.set_viewport_size(sz);
+				}
+			}
 
-						if dist > drag_threshold {
-							target_speed = (dist - drag_threshold).min(100.0) / 100.0;
-							target_heading = diff.to_vec2().to_angle() - CAMERA_YAW;
+			input.update(&frame_input.events, &frame_input.gamepads, now, frame_dt);
 
-						} else {
-							target_speed = 0.0;
-						}
-					}
+			match helm.update(&input, &camera, &world, &boat, frame_dt) {
+				Some(PickHit::Sea(target)) => console::set_section("tap", format!("{:.1}, {:.1}", target.x, target.y)),
+				Some(PickHit::Land(_)) => console::set_section("tap", "can't sail onto land"),
+				None => {}
+			}
 
-					Event::Up(_) => {
-						drag_start = None;
-					}
+			if input.action(Action::Fullscreen) {
+				platform.request_fullscreen();
+			}
+
+			if input.action(Action::DumpReplay) {
+				match recorder.dump() {
+					Ok(place) => console::set_section("replay", format!("replay {}", place)),
+					Err(e) => console::set_section("replay", console::escape_html(&e)),
 				}
 			}
 
-			event_queue.clear();
+			if input.action(Action::ToggleCamera) {
+				camera.toggle_mode();
+			}
+
+			if input.action(Action::ReloadShaders) {
+				shader.reload(&platform);
+			}
+
+			match shader.update() {
+				Some(ReloadResult::Reloaded) => {
+					shader.use_program();
+					console::set_color("#222");
+					console::set_section("shader", "reloaded");
+				}
 
-			boat_speed += (target_speed - boat_speed) / 60.0;
+				// Also what a reload right after startup usually finds
+				Some(ReloadResult::Unchanged) => {
+					console::set_color("#222");
+					console::set_section("shader", "unchanged");
+				}
+
+				Some(ReloadResult::Failed(e)) => {
+					console::set_color("#c22");
+					console::set_section("shader", format!("<pre>{}</pre>", console::escape_html(&e.to_string())));
+				}
 
-			let mut heading_diff = target_heading - boat_heading;
-			if heading_diff.abs() > PI {
-				heading_diff -= 2.0 * PI * heading_diff.signum();
+				Some(ReloadResult::Missing(e)) => {
+					console::set_section("shader", console::escape_html(&format!("Couldn't reload shaders: {}", e)));
+				}
+
+				None => {}
 			}
 
-			let heading_factor = 1.0 / 30.0;
+			camera.orbit(input.axis(Axis::CameraOrbit));
+			camera.zoom(input.axis(Axis::CameraZoom));
 
-			boat_heading_rate *= 1.0 - heading_factor;
-			boat_heading_rate += heading_diff.max(-PI/6.0).min(PI/6.0) * heading_factor;
-			boat_heading += (1.0 - (1.0 - boat_heading_rate/PI).powf(1.2)) * PI / 60.0;
+			for _ in 0..timestep.advance_to(now) {
+				wind.step(timestep.step());
 
-			let wave_phase_offset = (wave_phase*PI/5.0).sin();
-			let wave_omega = wave_phase*PI/3.0 + wave_phase_offset;
+				helm.step(&boat);
 
-			let wave_translate = wave_omega.sin();
-			let wave_slope = wave_omega.cos() * (PI/5.0 * (wave_phase*PI/5.0).cos() + PI/3.0);
-			// wolfram alpha: d sin(sin(pi x/5) + pi x/3) / dx
+				prev_boat = boat;
+				boat.step(&helm.orders, &ocean, &wind, &world, timestep.step());
+				trajectory.record(&boat);
+			}
 
-			let boat_roll = boat_heading_rate / 3.0;
-			let boat_translate = 0.05 * wave_translate - 0.6 * boat_roll.abs() / PI;
+			let render_boat = prev_boat.interpolate(&boat, timestep.alpha());
 
-			wave_phase += 1.0/60.0 + boat_speed * 1.0 / 60.0;
+			console::set_section("boat_heading_rate", format!("{}", render_boat.heading_rate));
+			console::set_section("boat_heading", format!("{}", render_boat.heading));
+			console::set_section("boat_speed", format!("{}", render_boat.speed));
+			console::set_section("boat_position", format!("{:.1}, {:.1}", render_boat.position.x, render_boat.position.y));
+			console::set_section("autopilot", format!("{} waypoints", helm.autopilot.waypoints().len()));
+			console::set_section("boat_roll", format!("{}", render_boat.roll));
+			console::set_section("wind", format!("{:.0}° @ {:.2}{}", wind.direction.to_degrees(), wind.strength,
+				if render_boat.tack.is_tacking() { " (tacking)" } else { "" }));
 
-			console::set_section("boat_heading_rate", format!("{}", boat_heading_rate));
-			console::set_section("boat_heading", format!("{}", boat_heading));
-			console::set_section("boat_speed", format!("{}", boat_speed));
-			console::set_section("boat_roll", format!("{}", boat_roll));
+			let boat_pos = render_boat.position;
 
-			let boat_model_mat = Mat4::translate(Vec3::new(0.0, boat_translate, 0.0))
-				* Mat4::yrot(boat_heading)
-				* Mat4::xrot(boat_roll)
-				* Mat4::zrot(PI / 64.0 * wave_slope);
+			camera.update(boat_pos, render_boat.heading, frame_dt);
+			shader.set_proj(&(camera.proj_mat() * camera.view_mat()));
+
+			let boat_model_mat = render_boat.model_mat();
+			let boom_angle = sail::boom_angle(render_boat.heading, &wind);
 
 			shader.set_view(&boat_model_mat);
 			boat_mesh.bind();
 			boat_mesh.draw(gl::TRIANGLES);
 
+			shader.set_view(&(boat_model_mat * sail_mat(boom_angle)));
+			sail_mesh.bind();
+			sail_mesh.draw(gl::TRIANGLES);
+
+			marker_mesh.bind();
+			for waypoint in helm.autopilot.waypoints() {
+				shader.set_view(&Mat4::translate(Vec3::new(waypoint.x, 0.0, waypoint.y)));
+				marker_mesh.draw(gl::TRIANGLES);
+			}
+
+			sea_mb.clear();
+			build_sea_mesh(&ocean, &mut sea_mb, boat_pos, render_boat.wave_phase);
+			sea_mb.upload_to(&mut sea_mesh);
+
 			shader.set_view(&Mat4::ident());
 			sea_mesh.bind();
 			sea_mesh.draw(gl::TRIANGLES);
 
+			route_mb.clear();
+			helm.autopilot.build_route_mesh(&mut route_mb, boat_pos, &ocean, render_boat.wave_phase);
+			route_mb.upload_to(&mut route_mesh);
+
+			route_mesh.bind();
+			route_mesh.draw(gl::LINE_STRIP);
+
+			world.update(boat_pos);
+			console::set_section("chunks", format!("{}", world.num_chunks()));
+
+			// Only debug builds keep count
+			#[cfg(debug)]
+			console::set_section("gl_objects", format!("{} buffers, {} textures, {} framebuffers, {} programs",
+				leaks::live_count(GlResource::Buffer), leaks::live_count(GlResource::Texture),
+				leaks::live_count(GlResource::Framebuffer), leaks::live_count(GlResource::Program)));
+
+			for chunk in world.chunks() {
+				chunk.sea.bind();
+				chunk.sea.draw(gl::TRIANGLES);
+
+				if let Some(ref terrain) = chunk.terrain {
+					terrain.bind();
+					terrain.draw(gl::TRIANGLES);
+				}
+			}
+
 			let now = Instant::now();
 			if now > frame_start {
 				let dur = now - frame_start;
@@ -260,12 +354,3 @@ fn main() {
 		}
 	});
 }
-
-#[allow(dead_code)]
-fn screen_to_gl(screen_size: Vec2i, v: Vec2i) -> Vec2{
-	let sz = screen_size.to_vec2();
-	let aspect = sz.x as f32 / sz.y as f32;
-
-	let norm = v.to_vec2() / screen_size.to_vec2() * 2.0 - Vec2::splat(1.0);
-	norm * Vec2::new(aspect, -1.0)
-}
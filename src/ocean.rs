@@ -0,0 +1,97 @@
+use common::*;
+use noise::{NoiseModule, Perlin, Seedable};
+
+use rendering::mesh_builder::*;
+
+pub const SEA_HALF_EXTENT: f32 = 8.0;
+pub const SEA_RESOLUTION: usize = 32;
+
+const NOISE_OCTAVES: usize = 3;
+const NOISE_AMPLITUDE: f32 = 0.12;
+const NOISE_FREQUENCY: f32 = 0.25;
+const NOISE_SPEED: f32 = 0.15;
+
+const SWELL_AMPLITUDE: f32 = 0.05;
+const SWELL_WAVELENGTH: f32 = 6.0;
+
+pub trait WaveField {
+	/// Height of the water surface above sea level at `pos` (on the XZ plane)
+	fn height_at(&self, pos: Vec2, phase: f32) -> f32;
+}
+
+/// Perfectly flat water, for when the waves would just get in the way
+pub struct CalmSea;
+
+impl WaveField for CalmSea {
+	fn height_at(&self, _: Vec2, _: f32) -> f32 { 0.0 }
+}
+
+/// The water surface - a few octaves of noise drifting over time, plus
+/// a long rolling swell
+pub struct Ocean {
+	noise: Perlin,
+	swell_dir: Vec2,
+}
+
+impl Ocean {
+	pub fn new(seed: usize) -> Self {
+		Ocean {
+			noise: Perlin::new().set_seed(seed),
+			swell_dir: Vec2::new(1.0, 0.3).normalize(),
+		}
+	}
+}
+
+/// Tessellates the surface of `waves` around `centre` into `mb`, one flat shaded
+/// triangle at a time to match the low poly look of everything else.
+/// The grid is snapped to whole cells so it doesn't swim as `centre` moves
+pub fn build_sea_mesh<W: WaveField>(waves: &W, mb: &mut MeshBuilder<ColorVertex>, centre: Vec2, phase: f32) {
+	let cell_size = SEA_HALF_EXTENT * 2.0 / SEA_RESOLUTION as f32;
+	let snapped = Vec2::new((centre.x / cell_size).floor(), (centre.y / cell_size).floor()) * cell_size;
+	let origin = snapped - Vec2::splat(SEA_HALF_EXTENT);
+	let stride = SEA_RESOLUTION + 1;
+	let color = Color::rgb8(122, 158, 198).into();
+
+	let mut points = Vec::with_capacity(stride * stride);
+
+	for z in 0..stride {
+		for x in 0..stride {
+			let pos = origin + Vec2::new(x as f32, z as f32) * cell_size;
+			points.push(Vec3::new(pos.x, waves.height_at(pos, phase), pos.y));
+		}
+	}
+
+	for z in 0..SEA_RESOLUTION {
+		for x in 0..SEA_RESOLUTION {
+			let i = z * stride + x;
+
+			let (p0, p1) = (points[i], points[i + 1]);
+			let (p2, p3) = (points[i + stride + 1], points[i + stride]);
+
+			mb.add_shaded_tri(p0, p3, p2, color);
+			mb.add_shaded_tri(p0, p2, p1, color);
+		}
+	}
+}
+
+impl WaveField for Ocean {
+	fn height_at(&self, pos: Vec2, phase: f32) -> f32 {
+		let mut height = 0.0;
+		let mut amplitude = NOISE_AMPLITUDE;
+		let mut frequency = NOISE_FREQUENCY;
+
+		for _ in 0..NOISE_OCTAVES {
+			height += amplitude * self.noise.get([pos.x * frequency, pos.y * frequency, phase * NOISE_SPEED]);
+			amplitude *= 0.5;
+			frequency *= 2.0;
+		}
+
+		let swell_phase = phase - pos.dot(self.swell_dir) / SWELL_WAVELENGTH;
+		height + SWELL_AMPLITUDE * swell_omega(swell_phase).sin()
+	}
+}
+
+/// The rolling part of the wave, as a phase angle
+pub fn swell_omega(phase: f32) -> f32 {
+	phase*PI/3.0 + (phase*PI/5.0).sin()
+}
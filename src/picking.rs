@@ -0,0 +1,159 @@
+use common::*;
+use camera::{Camera, Ray};
+use collision::{CollisionWorld, GROUNDING_HEIGHT};
+
+/// How far along a ray to look for terrain before giving up
+const PICK_MAX_DISTANCE: f32 = 120.0;
+/// Step size when marching along a ray. Small enough not to skip over the
+/// beach of the smallest island
+const PICK_STEP: f32 = 0.5;
+const PICK_REFINE_ITERATIONS: usize = 8;
+
+#[derive(Copy, Clone, Debug)]
+pub enum PickHit {
+	/// Open water - somewhere the boat could sail to
+	Sea(Vec2),
+	/// Terrain poking out of the sea
+	Land(Vec3),
+}
+
+impl PickHit {
+	pub fn position(&self) -> Vec2 {
+		match *self {
+			PickHit::Sea(pos) => pos,
+			PickHit::Land(pos) => Vec2::new(pos.x, pos.z),
+		}
+	}
+}
+
+/// Finds what's under a point on the screen
+pub fn pick_screen<C>(camera: &Camera, pos: Vec2i, world: &C) -> Option<PickHit> where C: CollisionWorld {
+	pick(&camera.screen_to_ray(pos), world)
+}
+
+/// Finds the first thing a ray hits - land, or failing that, the sea. Water
+/// too shallow for the boat counts as land
+pub fn pick<C>(ray: &Ray, world: &C) -> Option<PickHit> where C: CollisionWorld {
+	let surface = |p: Vec3| world.terrain_height(Vec2::new(p.x, p.z)).max(0.0);
+	let above = |t: f32| {
+		let p = ray.point_at(t);
+		p.y > surface(p)
+	};
+
+	let sea_hit = ray.hit_height(0.0);
+	let max_t = match sea_hit {
+		Some(hit) => (hit - ray.origin).length().min(PICK_MAX_DISTANCE),
+		None => PICK_MAX_DISTANCE,
+	};
+
+	// March until the ray dips below the surface, then bisect to find
+	// roughly where it crossed
+	let mut t = 0.0;
+	while t < max_t {
+		let next = (t + PICK_STEP).min(max_t);
+
+		if !above(next) {
+			let (mut lo, mut hi) = (t, next);
+
+			for _ in 0..PICK_REFINE_ITERATIONS {
+				let mid = (lo + hi) / 2.0;
+				if above(mid) { lo = mid } else { hi = mid }
+			}
+
+			return Some(classify(ray.point_at(hi), world))
+		}
+
+		t = next;
+	}
+
+	// Only reached if the sea is further away than PICK_MAX_DISTANCE
+	sea_hit.map(|p| classify(p, world))
+}
+
+fn classify<C>(p: Vec3, world: &C) -> PickHit where C: CollisionWorld {
+	let pos = Vec2::new(p.x, p.z);
+
+	if world.terrain_height(pos) > GROUNDING_HEIGHT {
+		PickHit::Land(p)
+	} else {
+		PickHit::Sea(pos)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use collision::Obstacle;
+
+	/// A round hill at the origin, topping out 3 above the sea
+	struct Hill;
+
+	impl CollisionWorld for Hill {
+		fn terrain_height(&self, pos: Vec2) -> f32 {
+			3.0 - pos.length()
+		}
+
+		fn obstacles_near(&self, _: Vec2, _: f32, _: &mut Vec<Obstacle>) {}
+	}
+
+	fn ray_between(from: Vec3, to: Vec3) -> Ray {
+		Ray { origin: from, dir: (to - from).normalize() }
+	}
+
+	#[test]
+	fn flat_sea_is_hit_where_the_ray_meets_it() {
+		let ray = ray_between(Vec3::new(1.0, 10.0, 2.0), Vec3::new(4.0, 0.0, 4.0));
+
+		match pick(&ray, &()) {
+			Some(PickHit::Sea(pos)) => assert!((pos - Vec2::new(4.0, 4.0)).length() < 1e-3, "hit {:?}", pos),
+			hit => panic!("expected sea, got {:?}", hit),
+		}
+	}
+
+	#[test]
+	fn distant_sea_is_still_hit() {
+		// Far beyond PICK_MAX_DISTANCE, so the marching never gets there
+		let ray = ray_between(Vec3::new(0.0, 10.0, 0.0), Vec3::new(500.0, 0.0, 0.0));
+
+		match pick(&ray, &()) {
+			Some(PickHit::Sea(pos)) => assert!((pos - Vec2::new(500.0, 0.0)).length() < 1e-2, "hit {:?}", pos),
+			hit => panic!("expected sea, got {:?}", hit),
+		}
+	}
+
+	#[test]
+	fn hill_is_land() {
+		// Aimed at the sea beyond the hill, so it has to clip the hill first
+		let ray = ray_between(Vec3::new(0.0, 10.0, 20.0), Vec3::new(0.0, 0.0, -5.0));
+
+		match pick(&ray, &Hill) {
+			Some(PickHit::Land(p)) => {
+				let height = Hill.terrain_height(Vec2::new(p.x, p.z));
+				assert!(p.z > 0.0 && p.z < 3.0, "hit {:?}", p);
+				assert!((p.y - height).abs() < 0.05, "hit {:?} isn't on the ground", p);
+			}
+
+			hit => panic!("expected land, got {:?}", hit),
+		}
+	}
+
+	#[test]
+	fn shallows_count_as_land() {
+		// Straight down onto the hill's foot, below sea level but too shallow to sail
+		let foot = Vec2::new(3.0 - GROUNDING_HEIGHT / 2.0, 0.0);
+		let ray = ray_between(Vec3::new(foot.x, 10.0, 0.0), Vec3::new(foot.x, 0.0, 0.0));
+
+		match pick(&ray, &Hill) {
+			Some(PickHit::Land(_)) => {}
+			hit => panic!("expected land, got {:?}", hit),
+		}
+	}
+
+	#[test]
+	fn looking_at_the_sky_misses() {
+		let ray = ray_between(Vec3::new(0.0, 10.0, 0.0), Vec3::new(10.0, 11.0, 0.0));
+
+		assert!(pick(&ray, &()).is_none());
+		assert!(pick(&ray, &Hill).is_none());
+	}
+}
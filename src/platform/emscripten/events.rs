@@ -0,0 +1,237 @@
+use bindings::emscripten::*;
+use common::Vec2i;
+use events::{Event, EventQueue, Key};
+
+use std::cell::RefCell;
+use std::mem::transmute;
+use std::ptr::{null, null_mut};
+use std::sync::atomic::{AtomicBool, Ordering, ATOMIC_BOOL_INIT};
+
+/// Callbacks are registered on the page as a whole, and registering again
+/// replaces whatever was there, so only one queue can be live at a time.
+/// Dropping it unregisters everything and frees the slot
+static QUEUE_LIVE: AtomicBool = ATOMIC_BOOL_INIT;
+
+pub fn create_event_queue() -> EventQueue {
+	let already_live = QUEUE_LIVE.swap(true, Ordering::SeqCst);
+	assert!(!already_live, "Only one event queue can be live at a time");
+
+	let mut queue = EventQueue::new();
+
+	unsafe {
+		let ud = queue.storage_ptr() as *mut CVoid;
+
+		on_resize(0, null(), ud);
+		emscripten_set_resize_callback(null(), ud, 0, Some(on_resize));
+
+		emscripten_set_mousemove_callback(null(), ud, 0, Some(on_mouse_move));
+		emscripten_set_mousedown_callback(null(), ud, 0, Some(on_mouse_down));
+		emscripten_set_mouseup_callback(null(), ud, 0, Some(on_mouse_up));
+		emscripten_set_wheel_callback(null(), ud, 0, Some(on_wheel));
+
+		emscripten_set_touchstart_callback(null(), ud, 0, Some(on_touch_start));
+		emscripten_set_touchmove_callback(null(), ud, 0, Some(on_touch_move));
+		emscripten_set_touchend_callback(null(), ud, 0, Some(on_touch_end));
+		emscripten_set_touchcancel_callback(null(), ud, 0, Some(on_touch_end));
+
+		emscripten_set_keydown_callback(null(), ud, 0, Some(on_key_down));
+		emscripten_set_keyup_callback(null(), ud, 0, Some(on_key_up));
+
+		emscripten_set_gamepadconnected_callback(ud, 0, Some(on_gamepad_connected));
+		emscripten_set_gamepaddisconnected_callback(ud, 0, Some(on_gamepad_disconnected));
+	}
+
+	queue.set_teardown(unregister_callbacks);
+	queue
+}
+
+fn unregister_callbacks() {
+	let ud = null_mut();
+
+	unsafe {
+		emscripten_set_resize_callback(null(), ud, 0, None);
+
+		emscripten_set_mousemove_callback(null(), ud, 0, None);
+		emscripten_set_mousedown_callback(null(), ud, 0, None);
+		emscripten_set_mouseup_callback(null(), ud, 0, None);
+		emscripten_set_wheel_callback(null(), ud, 0, None);
+
+		emscripten_set_touchstart_callback(null(), ud, 0, None);
+		emscripten_set_touchmove_callback(null(), ud, 0, None);
+		emscripten_set_touchend_callback(null(), ud, 0, None);
+		emscripten_set_touchcancel_callback(null(), ud, 0, None);
+
+		emscripten_set_keydown_callback(null(), ud, 0, None);
+		emscripten_set_keyup_callback(null(), ud, 0, None);
+
+		emscripten_set_gamepadconnected_callback(ud, 0, None);
+		emscripten_set_gamepaddisconnected_callback(ud, 0, None);
+	}
+
+	QUEUE_LIVE.store(false, Ordering::SeqCst);
+}
+
+/// Pushes onto the queue a callback was registered with. Callbacks only ever
+/// run between frames, so the queue is never borrowed by the game at the time
+unsafe fn push_event(ud: *mut CVoid, event: Event) {
+	let queue = &*(ud as *const RefCell<Vec<Event>>);
+	queue.borrow_mut().push(event);
+}
+
+unsafe extern "C"
+fn on_resize(_: i32, _e: *const EmscriptenUiEvent, ud: *mut CVoid) -> i32 {
+
+	js! { b"Module.canvas = document.getElementById('canvas')\0" };
+
+	let mut screen_size = Vec2i::zero();
+	screen_size.x = js! { b"return (Module.canvas.width = Module.canvas.style.width = window.innerWidth)\0" };
+	screen_size.y = js! { b"return (Module.canvas.height = Module.canvas.style.height = window.innerHeight)\0" };
+
+	push_event(ud, Event::Resize(screen_size));
+	
+	0
+}
+
+unsafe extern "C"
+fn on_mouse_move(_: i32, e: *const EmscriptenMouseEvent, ud: *mut CVoid) -> i32 {
+	let e: &EmscriptenMouseEvent = transmute(e);
+
+	push_event(ud, Event::Move(Vec2i::new(e.clientX as _, e.clientY as _)));
+	
+	1
+}
+unsafe extern "C"
+fn on_mouse_down(_: i32, e: *const EmscriptenMouseEvent, ud: *mut CVoid) -> i32 {
+	let e: &EmscriptenMouseEvent = transmute(e);
+
+	push_event(ud, Event::Down(Vec2i::new(e.clientX as _, e.clientY as _)));
+	
+	1
+}
+unsafe extern "C"
+fn on_mouse_up(_: i32, e: *const EmscriptenMouseEvent, ud: *mut CVoid) -> i32 {
+	let e: &EmscriptenMouseEvent = transmute(e);
+
+	push_event(ud, Event::Up(Vec2i::new(e.clientX as _, e.clientY as _)));
+	
+	1
+}
+
+unsafe extern "C"
+fn on_wheel(_: i32, e: *const EmscriptenWheelEvent, ud: *mut CVoid) -> i32 {
+	let e: &EmscriptenWheelEvent = transmute(e);
+
+	// deltaMode says whether deltaY is in pixels, lines or pages. Roughly
+	// normalise them all to notches of a typical wheel
+	let notches = match e.deltaMode {
+		0 => e.deltaY / 100.0,
+		1 => e.deltaY / 3.0,
+		_ => e.deltaY,
+	};
+
+	push_event(ud, Event::Wheel(notches as f32));
+
+	1
+}
+
+unsafe extern "C"
+fn on_key_down(_: i32, e: *const EmscriptenKeyboardEvent, ud: *mut CVoid) -> i32 {
+	let e: &EmscriptenKeyboardEvent = transmute(e);
+
+	// Browser shortcuts aren't ours. Only presses are skipped though, so a key
+	// that's let go while ctrl is held doesn't stay stuck down
+	if e.ctrlKey != 0 || e.altKey != 0 || e.metaKey != 0 { return 0 }
+
+	let key = match key_from_event(e) {
+		Some(key) => key,
+		None => return 0,
+	};
+
+	// Held keys repeat, but the game only wants to hear about the first press
+	if e.repeat == 0 {
+		push_event(ud, Event::KeyDown(key));
+	}
+
+	1
+}
+
+unsafe extern "C"
+fn on_key_up(_: i32, e: *const EmscriptenKeyboardEvent, ud: *mut CVoid) -> i32 {
+	let e: &EmscriptenKeyboardEvent = transmute(e);
+
+	let key = match key_from_event(e) {
+		Some(key) => key,
+		None => return 0,
+	};
+
+	push_event(ud, Event::KeyUp(key));
+
+	1
+}
+
+unsafe extern "C"
+fn on_gamepad_connected(_: i32, e: *const EmscriptenGamepadEvent, ud: *mut CVoid) -> i32 {
+	let e: &EmscriptenGamepadEvent = transmute(e);
+
+	push_event(ud, Event::GamepadConnected(e.index as u32));
+
+	1
+}
+
+unsafe extern "C"
+fn on_gamepad_disconnected(_: i32, e: *const EmscriptenGamepadEvent, ud: *mut CVoid) -> i32 {
+	let e: &EmscriptenGamepadEvent = transmute(e);
+
+	push_event(ud, Event::GamepadDisconnected(e.index as u32));
+
+	1
+}
+
+/// Any key can be bound, so everything is passed on apart from function keys,
+/// which are left for the browser
+unsafe fn key_from_event(e: &EmscriptenKeyboardEvent) -> Option<Key> {
+	use std::ffi::CStr;
+
+	let code = match CStr::from_ptr(e.code.as_ptr()).to_str() {
+		Ok(code) => code,
+		Err(_) => return None,
+	};
+
+	let is_function_key = code.len() > 1 && code.starts_with('F')
+		&& code[1..].chars().all(|c| c.is_digit(10));
+
+	if is_function_key { return None }
+
+	Key::from_code(code)
+}
+
+
+unsafe extern "C"
+fn on_touch_start(_: i32, e: *const EmscriptenTouchEvent, ud: *mut CVoid) -> i32 {
+	push_changed_touches(e, ud, Event::TouchStart)
+}
+
+unsafe extern "C"
+fn on_touch_move(_: i32, e: *const EmscriptenTouchEvent, ud: *mut CVoid) -> i32 {
+	push_changed_touches(e, ud, Event::TouchMove)
+}
+
+unsafe extern "C"
+fn on_touch_end(_: i32, e: *const EmscriptenTouchEvent, ud: *mut CVoid) -> i32 {
+	push_changed_touches(e, ud, Event::TouchEnd)
+}
+
+/// Emscripten lists every touch on the screen, flagging the ones this event
+/// is actually about with isChanged
+unsafe fn push_changed_touches(e: *const EmscriptenTouchEvent, ud: *mut CVoid, to_event: fn(u32, Vec2i) -> Event) -> i32 {
+	let e: &EmscriptenTouchEvent = transmute(e);
+
+	let num_touches = (e.numTouches as usize).min(e.touches.len());
+
+	for touch in e.touches[..num_touches].iter().filter(|t| t.isChanged != 0) {
+		let pos = Vec2i::new(touch.clientX as _, touch.clientY as _);
+		push_event(ud, to_event(touch.identifier as u32, pos));
+	}
+
+	1
+}
@@ -0,0 +1,135 @@
+use std::ops::Generator;
+
+use bindings::emscripten::*;
+use events::EventQueue;
+use gamepad::{self, GamepadState};
+use platform::{Platform, Fetch};
+
+pub mod webgl;
+mod events;
+
+pub use self::webgl::WebGLContext;
+
+#[derive(Copy, Clone)]
+pub struct EmscriptenPlatform;
+
+impl EmscriptenPlatform {
+	pub fn new() -> Self { EmscriptenPlatform }
+}
+
+impl Platform for EmscriptenPlatform {
+	type Context = WebGLContext;
+
+	fn set_coro_as_main_loop<T>(&self, coro: T) -> ! where T: 'static + Generator<Yield=(), Return=()> {
+		unsafe {
+			let coro: Box<Generator<Yield=(), Return=()>> = box coro;
+			emscripten_set_main_loop_arg(Some(resume_main_coro), Box::into_raw(box coro) as _, 0, 1)
+		}
+	}
+
+	fn create_event_queue(&self) -> EventQueue {
+		events::create_event_queue()
+	}
+
+	fn create_context(&self) -> WebGLContext {
+		WebGLContext::new()
+	}
+
+	fn poll_gamepads(&self, pads: &mut Vec<GamepadState>) {
+		use std::mem::uninitialized;
+
+		pads.clear();
+
+		unsafe {
+			for i in 0..emscripten_get_num_gamepads() {
+				let mut status: EmscriptenGamepadEvent = uninitialized();
+				if emscripten_get_gamepad_status(i, &mut status) != EMSCRIPTEN_RESULT_SUCCESS { continue }
+				if status.connected == 0 { continue }
+
+				let mut pad = GamepadState::new(status.index as u32);
+
+				let num_axes = (status.numAxes as usize).min(gamepad::MAX_GAMEPAD_AXES);
+				for (axis, &value) in pad.axes.iter_mut().zip(status.axis[..num_axes].iter()) {
+					*axis = value as f32;
+				}
+
+				let num_buttons = (status.numButtons as usize).min(gamepad::MAX_GAMEPAD_BUTTONS);
+				for (button, &value) in pad.buttons.iter_mut().zip(status.analogButton[..num_buttons].iter()) {
+					*button = value as f32;
+				}
+
+				pads.push(pad);
+			}
+		}
+	}
+
+	fn request_fullscreen(&self) {
+		use std::mem::uninitialized;
+		use std::ptr::null;
+
+		unsafe {
+			let mut fs_state: EmscriptenFullscreenChangeEvent = uninitialized();
+			emscripten_get_fullscreen_status(&mut fs_state);
+
+			// Browsers only allow fullscreen from inside an input handler, and
+			// this is called from the main loop, so defer it until the next one
+			if fs_state.isFullscreen == 0 {
+				emscripten_request_fullscreen(null(), 1);
+			}
+		}
+	}
+
+	fn now(&self) -> f64 {
+		unsafe { emscripten_get_now() / 1000.0 }
+	}
+
+	fn fetch_text(&self, path: &str) -> Fetch {
+		use std::ffi::CString;
+
+		// Relative to the page, the same way index.html finds the game's js
+		let url = CString::new(format!("{}/{}", env!("CARGO_PKG_NAME"), path)).unwrap();
+		let fetch = Fetch::new();
+
+		// Exactly one of the callbacks runs, and takes this back
+		let arg = Box::into_raw(box fetch.clone());
+
+		unsafe {
+			emscripten_async_wget_data(url.as_ptr(), arg as _, Some(fetch_loaded), Some(fetch_failed));
+		}
+
+		fetch
+	}
+}
+
+unsafe extern "C" fn fetch_loaded(arg: *mut CVoid, data: *mut CVoid, size: i32) {
+	use std::slice;
+
+	let fetch: Box<Fetch> = Box::from_raw(arg as _);
+
+	// emscripten frees `data` once this returns
+	let bytes = slice::from_raw_parts(data as *const u8, size as usize);
+	fetch.complete(String::from_utf8(bytes.to_vec()).map_err(|e| e.to_string()));
+}
+
+unsafe extern "C" fn fetch_failed(arg: *mut CVoid) {
+	let fetch: Box<Fetch> = Box::from_raw(arg as _);
+	fetch.complete(Err("Request failed".to_string()));
+}
+
+extern "C" fn resume_main_coro(ctx: *mut CVoid) {
+	use std::mem::transmute;
+	use std::ops::GeneratorState::*;
+
+	let coro: &mut Box<Generator<Yield=(), Return=()>> = unsafe{ transmute(ctx) };
+
+	match coro.resume() {
+		Yielded(()) => {}
+		Complete(()) => unsafe {
+			println!("Main coro has returned");
+
+			Box::from_raw(ctx as _);
+
+			emscripten_cancel_main_loop();
+		}
+	}
+}
@@ -2,6 +2,7 @@ use bindings::emscripten::*;
 use bindings::gl;
 
 use common::color::*;
+use platform::RenderContext;
 
 pub struct WebGLContext {}
 
@@ -51,8 +52,10 @@ impl WebGLContext {
 
 		WebGLContext {}
 	}
+}
 
-	pub fn set_background<C>(&self, col: C) where C: Into<Color> {
+impl RenderContext for WebGLContext {
+	fn set_background<C>(&self, col: C) where C: Into<Color> {
 		unsafe {
 			let c = col.into();
 			gl::ClearColor(c.r, c.g, c.b, c.a);
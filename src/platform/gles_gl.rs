@@ -0,0 +1,224 @@
+// A GlBackend that hands everything straight to the system's GLES2 driver,
+// for the windowed native platform. GLES2 is what WebGL is built on, so the
+// rendering code sees exactly the API it gets in the browser.
+
+use std::ffi::CString;
+use std::os::raw::c_void;
+use std::ptr;
+
+use bindings::gl::*;
+use bindings::gl_dispatch::GlBackend;
+
+pub struct GlesGl;
+
+impl GlesGl {
+	pub fn new() -> Self { GlesGl }
+}
+
+fn info_log(len: i32, read: &mut FnMut(i32, *mut i32, *mut GLchar)) -> String {
+	if len <= 0 { return String::new() }
+
+	let mut buf = vec![0u8; len as usize];
+	let mut written = 0;
+	read(len, &mut written, buf.as_mut_ptr() as *mut GLchar);
+	buf.truncate(written.max(0) as usize);
+
+	String::from_utf8_lossy(&buf).into_owned()
+}
+
+impl GlBackend for GlesGl {
+	fn gen_buffer(&mut self) -> u32 { let mut name = 0; unsafe { glGenBuffers(1, &mut name) }; name }
+	fn gen_texture(&mut self) -> u32 { let mut name = 0; unsafe { glGenTextures(1, &mut name) }; name }
+	fn gen_framebuffer(&mut self) -> u32 { let mut name = 0; unsafe { glGenFramebuffers(1, &mut name) }; name }
+	fn create_shader(&mut self, kind: GLenum) -> u32 { unsafe { glCreateShader(kind) } }
+	fn create_program(&mut self) -> u32 { unsafe { glCreateProgram() } }
+
+	fn delete_buffer(&mut self, buffer: u32) { unsafe { glDeleteBuffers(1, &buffer) } }
+	fn delete_texture(&mut self, texture: u32) { unsafe { glDeleteTextures(1, &texture) } }
+	fn delete_framebuffer(&mut self, framebuffer: u32) { unsafe { glDeleteFramebuffers(1, &framebuffer) } }
+	fn delete_shader(&mut self, shader: u32) { unsafe { glDeleteShader(shader) } }
+	fn delete_program(&mut self, program: u32) { unsafe { glDeleteProgram(program) } }
+
+	fn enable(&mut self, cap: GLenum) { unsafe { glEnable(cap) } }
+	fn disable(&mut self, cap: GLenum) { unsafe { glDisable(cap) } }
+	fn blend_equation(&mut self, mode: GLenum) { unsafe { glBlendEquation(mode) } }
+	fn blend_func(&mut self, sfactor: GLenum, dfactor: GLenum) { unsafe { glBlendFunc(sfactor, dfactor) } }
+	fn depth_func(&mut self, func: GLenum) { unsafe { glDepthFunc(func) } }
+	fn depth_mask(&mut self, flag: bool) { unsafe { glDepthMask(if flag { TRUE } else { FALSE }) } }
+	fn front_face(&mut self, mode: GLenum) { unsafe { glFrontFace(mode) } }
+	fn line_width(&mut self, width: f32) { unsafe { glLineWidth(width) } }
+	fn viewport(&mut self, x: i32, y: i32, width: i32, height: i32) { unsafe { glViewport(x, y, width, height) } }
+	fn clear_color(&mut self, r: f32, g: f32, b: f32, a: f32) { unsafe { glClearColor(r, g, b, a) } }
+	fn clear(&mut self, mask: GLbitfield) { unsafe { glClear(mask) } }
+
+	fn bind_buffer(&mut self, target: GLenum, buffer: u32) { unsafe { glBindBuffer(target, buffer) } }
+	fn buffer_data(&mut self, target: GLenum, data: &[u8], usage: GLenum) {
+		unsafe { glBufferData(target, data.len() as GLsizeiptr, data.as_ptr() as *const c_void, usage) }
+	}
+
+	fn active_texture(&mut self, unit: GLenum) { unsafe { glActiveTexture(unit) } }
+	fn bind_texture(&mut self, target: GLenum, texture: u32) { unsafe { glBindTexture(target, texture) } }
+	fn tex_parameter(&mut self, target: GLenum, pname: GLenum, param: i32) { unsafe { glTexParameteri(target, pname, param) } }
+	fn tex_image_2d(&mut self, target: GLenum, level: i32, internal_format: i32, width: i32, height: i32,
+		format: GLenum, kind: GLenum, pixels: Option<&[u8]>) {
+
+		let pixels = pixels.map_or(ptr::null(), |p| p.as_ptr() as *const c_void);
+		unsafe { glTexImage2D(target, level, internal_format, width, height, 0, format, kind, pixels) }
+	}
+
+	fn bind_framebuffer(&mut self, target: GLenum, framebuffer: u32) { unsafe { glBindFramebuffer(target, framebuffer) } }
+	fn framebuffer_texture_2d(&mut self, target: GLenum, attachment: GLenum, textarget: GLenum, texture: u32, level: i32) {
+		unsafe { glFramebufferTexture2D(target, attachment, textarget, texture, level) }
+	}
+
+	fn shader_source(&mut self, shader: u32, source: &str) {
+		let ptr = source.as_ptr() as *const GLchar;
+		let len = source.len() as i32;
+		unsafe { glShaderSource(shader, 1, &ptr, &len) }
+	}
+
+	fn compile_shader(&mut self, shader: u32) { unsafe { glCompileShader(shader) } }
+	fn attach_shader(&mut self, program: u32, shader: u32) { unsafe { glAttachShader(program, shader) } }
+	fn link_program(&mut self, program: u32) { unsafe { glLinkProgram(program) } }
+	fn use_program(&mut self, program: u32) { unsafe { glUseProgram(program) } }
+
+	fn get_shader_parameter(&mut self, shader: u32, pname: GLenum) -> i32 {
+		let mut value = 0;
+		unsafe { glGetShaderiv(shader, pname, &mut value) };
+		value
+	}
+
+	fn get_program_parameter(&mut self, program: u32, pname: GLenum) -> i32 {
+		let mut value = 0;
+		unsafe { glGetProgramiv(program, pname, &mut value) };
+		value
+	}
+
+	fn get_shader_info_log(&mut self, shader: u32) -> String {
+		let len = self.get_shader_parameter(shader, INFO_LOG_LENGTH);
+		info_log(len, &mut |size, written, buf| unsafe { glGetShaderInfoLog(shader, size, written, buf) })
+	}
+
+	fn get_program_info_log(&mut self, program: u32) -> String {
+		let len = self.get_program_parameter(program, INFO_LOG_LENGTH);
+		info_log(len, &mut |size, written, buf| unsafe { glGetProgramInfoLog(program, size, written, buf) })
+	}
+
+	fn get_integer(&mut self, pname: GLenum) -> i32 {
+		let mut value = 0;
+		unsafe { glGetIntegerv(pname, &mut value) };
+		value
+	}
+
+	fn get_error(&mut self) -> GLenum { unsafe { glGetError() } }
+
+	fn get_uniform_location(&mut self, program: u32, name: &str) -> i32 {
+		match CString::new(name) {
+			Ok(name) => unsafe { glGetUniformLocation(program, name.as_ptr()) },
+			Err(_) => -1,
+		}
+	}
+
+	fn uniform_f32s(&mut self, location: i32, values: &[f32]) {
+		unsafe {
+			let v = values;
+			match v.len() {
+				1 => glUniform1f(location, v[0]),
+				2 => glUniform2f(location, v[0], v[1]),
+				3 => glUniform3f(location, v[0], v[1], v[2]),
+				4 => glUniform4f(location, v[0], v[1], v[2], v[3]),
+				n => panic!("Uniforms have 1 to 4 components, not {}", n),
+			}
+		}
+	}
+
+	fn uniform_i32(&mut self, location: i32, value: i32) { unsafe { glUniform1i(location, value) } }
+	fn uniform_matrix4(&mut self, location: i32, transpose: bool, values: &[f32]) {
+		let count = (values.len() / 16) as i32;
+		unsafe { glUniformMatrix4fv(location, count, if transpose { TRUE } else { FALSE }, values.as_ptr()) }
+	}
+
+	fn enable_vertex_attrib_array(&mut self, index: u32) { unsafe { glEnableVertexAttribArray(index) } }
+	fn disable_vertex_attrib_array(&mut self, index: u32) { unsafe { glDisableVertexAttribArray(index) } }
+	fn vertex_attrib_pointer(&mut self, index: u32, size: i32, kind: GLenum, normalized: bool, stride: i32, offset: usize) {
+		let normalized = if normalized { TRUE } else { FALSE };
+		unsafe { glVertexAttribPointer(index, size, kind, normalized, stride, offset as *const c_void) }
+	}
+
+	fn draw_arrays(&mut self, mode: GLenum, first: i32, count: i32) { unsafe { glDrawArrays(mode, first, count) } }
+	fn draw_elements(&mut self, mode: GLenum, count: i32, kind: GLenum, offset: usize) {
+		unsafe { glDrawElements(mode, count, kind, offset as *const c_void) }
+	}
+
+	fn read_pixels(&mut self, x: i32, y: i32, width: i32, height: i32, format: GLenum, kind: GLenum, pixels: &mut [u8]) {
+		unsafe { glReadPixels(x, y, width, height, format, kind, pixels.as_mut_ptr() as *mut c_void) }
+	}
+}
+
+#[link(name = "GLESv2")]
+extern "C" {
+	fn glGenBuffers(n: GLsizei, buffers: *mut GLuint);
+	fn glGenTextures(n: GLsizei, textures: *mut GLuint);
+	fn glGenFramebuffers(n: GLsizei, framebuffers: *mut GLuint);
+	fn glCreateShader(kind: GLenum) -> GLuint;
+	fn glCreateProgram() -> GLuint;
+
+	fn glDeleteBuffers(n: GLsizei, buffers: *const GLuint);
+	fn glDeleteTextures(n: GLsizei, textures: *const GLuint);
+	fn glDeleteFramebuffers(n: GLsizei, framebuffers: *const GLuint);
+	fn glDeleteShader(shader: GLuint);
+	fn glDeleteProgram(program: GLuint);
+
+	fn glEnable(cap: GLenum);
+	fn glDisable(cap: GLenum);
+	fn glBlendEquation(mode: GLenum);
+	fn glBlendFunc(sfactor: GLenum, dfactor: GLenum);
+	fn glDepthFunc(func: GLenum);
+	fn glDepthMask(flag: GLboolean);
+	fn glFrontFace(mode: GLenum);
+	fn glLineWidth(width: GLfloat);
+	fn glViewport(x: GLint, y: GLint, width: GLsizei, height: GLsizei);
+	fn glClearColor(r: GLfloat, g: GLfloat, b: GLfloat, a: GLfloat);
+	fn glClear(mask: GLbitfield);
+
+	fn glBindBuffer(target: GLenum, buffer: GLuint);
+	fn glBufferData(target: GLenum, size: GLsizeiptr, data: *const c_void, usage: GLenum);
+
+	fn glActiveTexture(unit: GLenum);
+	fn glBindTexture(target: GLenum, texture: GLuint);
+	fn glTexParameteri(target: GLenum, pname: GLenum, param: GLint);
+	fn glTexImage2D(target: GLenum, level: GLint, internal_format: GLint, width: GLsizei, height: GLsizei,
+		border: GLint, format: GLenum, kind: GLenum, pixels: *const c_void);
+
+	fn glBindFramebuffer(target: GLenum, framebuffer: GLuint);
+	fn glFramebufferTexture2D(target: GLenum, attachment: GLenum, textarget: GLenum, texture: GLuint, level: GLint);
+
+	fn glShaderSource(shader: GLuint, count: GLsizei, sources: *const *const GLchar, lengths: *const GLint);
+	fn glCompileShader(shader: GLuint);
+	fn glAttachShader(program: GLuint, shader: GLuint);
+	fn glLinkProgram(program: GLuint);
+	fn glUseProgram(program: GLuint);
+
+	fn glGetShaderiv(shader: GLuint, pname: GLenum, params: *mut GLint);
+	fn glGetProgramiv(program: GLuint, pname: GLenum, params: *mut GLint);
+	fn glGetShaderInfoLog(shader: GLuint, size: GLsizei, written: *mut GLsizei, log: *mut GLchar);
+	fn glGetProgramInfoLog(program: GLuint, size: GLsizei, written: *mut GLsizei, log: *mut GLchar);
+	fn glGetIntegerv(pname: GLenum, data: *mut GLint);
+	fn glGetError() -> GLenum;
+
+	fn glGetUniformLocation(program: GLuint, name: *const GLchar) -> GLint;
+	fn glUniform1f(location: GLint, x: GLfloat);
+	fn glUniform2f(location: GLint, x: GLfloat, y: GLfloat);
+	fn glUniform3f(location: GLint, x: GLfloat, y: GLfloat, z: GLfloat);
+	fn glUniform4f(location: GLint, x: GLfloat, y: GLfloat, z: GLfloat, w: GLfloat);
+	fn glUniform1i(location: GLint, x: GLint);
+	fn glUniformMatrix4fv(location: GLint, count: GLsizei, transpose: GLboolean, values: *const GLfloat);
+
+	fn glEnableVertexAttribArray(index: GLuint);
+	fn glDisableVertexAttribArray(index: GLuint);
+	fn glVertexAttribPointer(index: GLuint, size: GLint, kind: GLenum, normalized: GLboolean, stride: GLsizei, offset: *const c_void);
+
+	fn glDrawArrays(mode: GLenum, first: GLint, count: GLsizei);
+	fn glDrawElements(mode: GLenum, count: GLsizei, kind: GLenum, offset: *const c_void);
+	fn glReadPixels(x: GLint, y: GLint, width: GLsizei, height: GLsizei, format: GLenum, kind: GLenum, pixels: *mut c_void);
+}
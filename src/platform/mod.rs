@@ -0,0 +1,76 @@
+// Everything the game needs from the outside world - a main loop, input
+// events, a GL context and a clock - goes through Platform, so the game
+// itself doesn't care whether it's running in a browser or on a desktop.
+//
+// The emscripten backend is used when building for the web (`cargo asmjs` or
+// `cargo wasm`), the native one everywhere else. Native builds are headless
+// unless the `windowed` feature is turned on.
+
+use std::cell::RefCell;
+use std::ops::Generator;
+use std::rc::Rc;
+
+use common::color::Color;
+use events::EventQueue;
+use gamepad::GamepadState;
+
+#[cfg(target_os = "emscripten")] pub mod emscripten;
+#[cfg(not(target_os = "emscripten"))] pub mod native;
+#[cfg(all(feature = "windowed", not(target_os = "emscripten")))] pub mod x11;
+#[cfg(all(feature = "windowed", not(target_os = "emscripten")))] pub mod gles_gl;
+#[cfg(all(test, not(target_os = "emscripten")))] pub mod software_gl;
+#[cfg(all(test, not(target_os = "emscripten")))] pub mod recording_gl;
+
+#[cfg(target_os = "emscripten")] pub use self::emscripten::EmscriptenPlatform as DefaultPlatform;
+#[cfg(not(target_os = "emscripten"))] pub use self::native::NativePlatform as DefaultPlatform;
+
+pub trait Platform: Clone {
+	type Context: RenderContext;
+
+	/// Resumes `coro` once per frame until it completes
+	fn set_coro_as_main_loop<T>(&self, coro: T) -> ! where T: 'static + Generator<Yield=(), Return=()>;
+
+	/// Creates a queue and starts pushing input events into it. Input stops
+	/// when the queue is dropped. Only one queue can be live at a time
+	fn create_event_queue(&self) -> EventQueue;
+
+	fn create_context(&self) -> Self::Context;
+
+	/// Replaces the contents of `pads` with the current state of every
+	/// connected gamepad. Called once per frame
+	fn poll_gamepads(&self, pads: &mut Vec<GamepadState>);
+
+	/// Asks to go fullscreen, if the platform has such a thing and isn't already
+	fn request_fullscreen(&self);
+
+	/// Time in seconds since some arbitrary point
+	fn now(&self) -> f64;
+
+	/// Starts reading the text file at `path`, relative to the project root.
+	/// On the web this is a request to wherever the page was served from
+	fn fetch_text(&self, path: &str) -> Fetch;
+}
+
+/// The eventual contents of a file asked for with Platform::fetch_text
+#[derive(Clone)]
+pub struct Fetch(Rc<RefCell<Option<Result<String, String>>>>);
+
+impl Fetch {
+	pub fn new() -> Self {
+		Fetch(Rc::new(RefCell::new(None)))
+	}
+
+	pub fn complete(&self, result: Result<String, String>) {
+		*self.0.borrow_mut() = Some(result);
+	}
+
+	/// The file's contents or why it couldn't be read, or None while it's
+	/// still on its way
+	pub fn poll(&self) -> Option<Result<String, String>> {
+		self.0.borrow().clone()
+	}
+}
+
+pub trait RenderContext {
+	fn set_background<C>(&self, col: C) where C: Into<Color>;
+}
@@ -0,0 +1,382 @@
+// The native backend, for running the game on a desktop host. It has two modes:
+//
+// Headless has no window and no real GL - rendering goes to NullGl and the clock
+// advances exactly one frame_time per frame, so runs are fully deterministic and
+// everything can be exercised from cargo test.
+//
+// Windowed opens an X11 window with a GLES2 context, reads the mouse and
+// keyboard, and runs on the wall clock at the display's refresh rate. It needs
+// X11, EGL and GLESv2 to link against, so it's only built with the `windowed`
+// feature. Set BOAT_HEADLESS to run headless anyway.
+
+use std::cell::Cell;
+use std::env;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::ops::{Generator, GeneratorState};
+use std::process;
+use std::rc::Rc;
+use std::time::Instant;
+
+use common::*;
+use bindings::gl;
+use bindings::gl_dispatch::{self, GlBackend};
+use events::{Event, EventQueue};
+use gamepad::GamepadState;
+use platform::{Platform, RenderContext, Fetch};
+use rendering::leaks;
+
+#[cfg(feature = "windowed")] use platform::x11::Window;
+#[cfg(not(feature = "windowed"))] use self::no_window::Window;
+
+const DEFAULT_SCREEN_SIZE: (i32, i32) = (800, 600);
+const DEFAULT_HEADLESS_FRAMES: u32 = 600;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum NativeMode {
+	Headless,
+	Windowed,
+}
+
+/// Clones share their frame count and window, so the copy the main loop
+/// holds and the one the game holds agree on the time
+#[derive(Clone)]
+pub struct NativePlatform {
+	pub mode: NativeMode,
+	pub screen_size: Vec2i,
+	pub frame_time: f64,
+	pub frame_limit: Option<u32>,
+
+	frame_count: Rc<Cell<u32>>,
+	start: Instant,
+	window: Option<Rc<Window>>,
+}
+
+impl NativePlatform {
+	/// Windowed if built with the `windowed` feature and a window can be
+	/// opened, otherwise headless. Either way BOAT_FRAMES limits how many
+	/// frames run - headless runs default to 600
+	pub fn new() -> Self {
+		let size = Vec2i::new(DEFAULT_SCREEN_SIZE.0, DEFAULT_SCREEN_SIZE.1);
+		let frame_limit = env::var("BOAT_FRAMES").ok().and_then(|s| s.parse().ok());
+
+		if env::var("BOAT_HEADLESS").is_err() {
+			match NativePlatform::windowed(size, frame_limit) {
+				Ok(platform) => return platform,
+				Err(e) => println!("Running headless: {}", e),
+			}
+		}
+
+		NativePlatform::headless(size, Some(frame_limit.unwrap_or(DEFAULT_HEADLESS_FRAMES)))
+	}
+
+	pub fn headless(screen_size: Vec2i, frame_limit: Option<u32>) -> Self {
+		NativePlatform {
+			mode: NativeMode::Headless,
+			screen_size,
+			frame_time: 1.0 / 60.0,
+			frame_limit,
+
+			frame_count: Rc::new(Cell::new(0)),
+			start: Instant::now(),
+			window: None,
+		}
+	}
+
+	/// Opens a window of `screen_size`. `frame_time` is only a guess here - the
+	/// display's refresh rate decides how often frames actually run
+	#[cfg(feature = "windowed")]
+	pub fn windowed(screen_size: Vec2i, frame_limit: Option<u32>) -> Result<Self, String> {
+		let window = Window::open(screen_size, env!("CARGO_PKG_NAME"))?;
+
+		Ok(NativePlatform {
+			mode: NativeMode::Windowed,
+			window: Some(Rc::new(window)),
+			..NativePlatform::headless(screen_size, frame_limit)
+		})
+	}
+
+	#[cfg(not(feature = "windowed"))]
+	pub fn windowed(_: Vec2i, _: Option<u32>) -> Result<Self, String> {
+		Err("built without the windowed feature".to_string())
+	}
+
+	pub fn frame_count(&self) -> u32 {
+		self.frame_count.get()
+	}
+
+	/// Resumes `coro` until it completes, the window is closed or `frame_limit`
+	/// frames have passed, returning the number of frames run. Unlike
+	/// set_coro_as_main_loop this returns, so it can be used from tests and tools
+	pub fn run_frames<T>(&self, coro: &mut T, frame_limit: Option<u32>) -> u32 where T: Generator<Yield=(), Return=()> {
+		let mut frames = 0;
+
+		while frame_limit.map_or(true, |l| frames < l) {
+			if let Some(ref window) = self.window {
+				if !window.pump_events() { break }
+			}
+
+			let state = coro.resume();
+			frames += 1;
+			self.frame_count.set(self.frame_count.get() + 1);
+
+			if let Some(ref window) = self.window {
+				window.swap_buffers();
+			}
+
+			if let GeneratorState::Complete(()) = state {
+				break
+			}
+		}
+
+		frames
+	}
+}
+
+impl Platform for NativePlatform {
+	type Context = NativeContext;
+
+	fn set_coro_as_main_loop<T>(&self, mut coro: T) -> ! where T: 'static + Generator<Yield=(), Return=()> {
+		self.run_frames(&mut coro, self.frame_limit);
+
+		// Dropping the coroutine drops everything it owns, so anything GL that's
+		// still alive after this was leaked
+		drop(coro);
+		leaks::report();
+
+		process::exit(0)
+	}
+
+	fn create_event_queue(&self) -> EventQueue {
+		let mut queue = EventQueue::new();
+
+		match self.window {
+			Some(ref window) => window.attach_queue(&mut queue),
+			None => queue.push(Event::Resize(self.screen_size)),
+		}
+
+		queue
+	}
+
+	fn create_context(&self) -> NativeContext {
+		match self.window {
+			Some(ref window) => window.make_context_current(),
+			None => { gl_dispatch::set_backend(box NullGl::new()); }
+		}
+
+		NativeContext {}
+	}
+
+	fn poll_gamepads(&self, pads: &mut Vec<GamepadState>) {
+		pads.clear();
+	}
+
+	fn request_fullscreen(&self) {
+		if let Some(ref window) = self.window {
+			window.request_fullscreen();
+		}
+	}
+
+	fn now(&self) -> f64 {
+		match self.window {
+			Some(_) => {
+				let elapsed = self.start.elapsed();
+				elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 * 1e-9
+			}
+
+			None => self.frame_count() as f64 * self.frame_time,
+		}
+	}
+
+	fn fetch_text(&self, path: &str) -> Fetch {
+		let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(path);
+		let read = File::open(&path).and_then(|mut f| {
+			let mut text = String::new();
+			f.read_to_string(&mut text).map(|_| text)
+		});
+
+		let fetch = Fetch::new();
+		fetch.complete(read.map_err(|e| format!("Couldn't read '{}': {}", path.display(), e)));
+		fetch
+	}
+}
+
+/// The window's GLES2 context when windowed, NullGl when headless
+pub struct NativeContext {}
+
+impl RenderContext for NativeContext {
+	fn set_background<C>(&self, col: C) where C: Into<Color> {
+		unsafe {
+			let c = col.into();
+			gl::ClearColor(c.r, c.g, c.b, c.a);
+		}
+	}
+}
+
+/// Stands in for x11::Window when built without the `windowed` feature. It
+/// can't be created, so a platform without one is always headless
+#[cfg(not(feature = "windowed"))]
+mod no_window {
+	use events::EventQueue;
+
+	pub enum Window {}
+
+	impl Window {
+		pub fn attach_queue(&self, _: &mut EventQueue) { match *self {} }
+		pub fn make_context_current(&self) { match *self {} }
+		pub fn pump_events(&self) -> bool { match *self {} }
+		pub fn swap_buffers(&self) { match *self {} }
+		pub fn request_fullscreen(&self) { match *self {} }
+	}
+}
+
+/// Accepts and ignores everything, handing out unique object names so
+/// the rendering code can run without a GPU
+pub struct NullGl {
+	next_name: u32,
+}
+
+impl NullGl {
+	pub fn new() -> Self {
+		NullGl { next_name: 1 }
+	}
+
+	fn new_name(&mut self) -> u32 {
+		let name = self.next_name;
+		self.next_name += 1;
+		name
+	}
+}
+
+impl GlBackend for NullGl {
+	fn gen_buffer(&mut self) -> u32 { self.new_name() }
+	fn gen_texture(&mut self) -> u32 { self.new_name() }
+	fn gen_framebuffer(&mut self) -> u32 { self.new_name() }
+	fn create_shader(&mut self, _: u32) -> u32 { self.new_name() }
+	fn create_program(&mut self) -> u32 { self.new_name() }
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use resources as res;
+
+	/// Yields until it's been resumed `length` times, or forever if None
+	struct Frames {
+		resumed: u32,
+		length: Option<u32>,
+	}
+
+	impl Generator for Frames {
+		type Yield = ();
+		type Return = ();
+
+		fn resume(&mut self) -> GeneratorState<(), ()> {
+			self.resumed += 1;
+
+			if self.length.map_or(false, |l| self.resumed >= l) {
+				GeneratorState::Complete(())
+			} else {
+				GeneratorState::Yielded(())
+			}
+		}
+	}
+
+	fn platform() -> NativePlatform {
+		NativePlatform::headless(Vec2i::new(320, 240), None)
+	}
+
+	#[test]
+	fn run_frames_stops_at_the_limit() {
+		let platform = platform();
+		let mut coro = Frames { resumed: 0, length: None };
+
+		assert_eq!(platform.run_frames(&mut coro, Some(10)), 10);
+		assert_eq!(coro.resumed, 10);
+		assert_eq!(platform.frame_count(), 10);
+	}
+
+	#[test]
+	fn run_frames_stops_when_the_coroutine_completes() {
+		let platform = platform();
+		let mut coro = Frames { resumed: 0, length: Some(3) };
+
+		assert_eq!(platform.run_frames(&mut coro, None), 3);
+		assert_eq!(platform.run_frames(&mut Frames { resumed: 0, length: Some(3) }, Some(2)), 2);
+	}
+
+	#[test]
+	fn clock_advances_one_frame_time_per_frame() {
+		let platform = platform();
+		assert_eq!(platform.now(), 0.0);
+
+		platform.run_frames(&mut Frames { resumed: 0, length: None }, Some(30));
+		assert!((platform.now() - 30.0 * platform.frame_time).abs() < 1e-9);
+	}
+
+	#[test]
+	fn event_queue_starts_with_the_screen_size() {
+		let events = platform().create_event_queue().drain();
+
+		assert_eq!(events.len(), 1, "expected a single resize, got {:?}", events);
+
+		match events[0] {
+			Event::Resize(sz) => assert_eq!((sz.x, sz.y), (320, 240)),
+			other => panic!("expected a resize, got {:?}", other),
+		}
+	}
+
+	#[test]
+	fn fetch_text_reads_from_the_project_root() {
+		let platform = platform();
+
+		match platform.fetch_text("assets/input.cfg").poll() {
+			Some(Ok(text)) => assert_eq!(text, res::config::INPUT),
+			other => panic!("expected the input config, got {:?}", other),
+		}
+
+		match platform.fetch_text("assets/does_not_exist").poll() {
+			Some(Err(_)) => {}
+			other => panic!("expected a missing file to fail, got {:?}", other),
+		}
+	}
+
+	#[test]
+	fn each_platform_keeps_its_own_clock() {
+		let (a, b) = (platform(), platform());
+		let a_clone = a.clone();
+
+		a.run_frames(&mut Frames { resumed: 0, length: None }, Some(5));
+
+		assert_eq!(a_clone.frame_count(), 5);
+		assert_eq!(b.frame_count(), 0);
+		assert_eq!(b.now(), 0.0);
+	}
+
+	#[cfg(not(feature = "windowed"))]
+	#[test]
+	fn windowed_needs_the_feature() {
+		assert!(NativePlatform::windowed(Vec2i::new(320, 240), None).is_err());
+	}
+
+	#[test]
+	fn null_gl_hands_out_unique_names() {
+		let mut gl = NullGl::new();
+		let names = [
+			gl.gen_buffer(), gl.gen_texture(), gl.gen_framebuffer(),
+			gl.create_shader(0), gl.create_program(), gl.gen_buffer(),
+		];
+
+		for (i, a) in names.iter().enumerate() {
+			assert!(*a != 0, "0 is never a valid GL name");
+			assert!(names[i+1..].iter().all(|b| a != b), "{} handed out twice", a);
+		}
+	}
+
+	#[test]
+	fn headless_context_installs_null_gl() {
+		let _context = platform().create_context();
+		assert!(gl_dispatch::take_backend().is_some());
+	}
+}
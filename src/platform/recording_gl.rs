@@ -0,0 +1,858 @@
+// A GlBackend that draws nothing, but writes down every call made to it and
+// keeps track of which objects exist and what's bound, so the exact command
+// stream the rendering module produces can be checked. Anything real GL would
+// reject - binding a deleted texture, drawing with no program, uploading to
+// nothing - is noted as a problem and reported through GetError as well.
+//
+//   let recording = record_gl(|| { let mesh: Mesh = mb.into(); mesh.bind(); });
+//   assert!(recording.problems.is_empty());
+//   assert_eq!(recording.calls[0], GlCall::GenBuffer(1));
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use bindings::gl::*;
+use bindings::gl_dispatch::{self, GlBackend};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum GlObject {
+	Buffer,
+	Texture,
+	Framebuffer,
+	Shader,
+	Program,
+}
+
+/// One call into GL and its arguments. Calls that create objects record the
+/// name handed back
+#[derive(Clone, Debug, PartialEq)]
+pub enum GlCall {
+	GenBuffer(u32),
+	GenTexture(u32),
+	GenFramebuffer(u32),
+	CreateShader(GLenum, u32),
+	CreateProgram(u32),
+
+	DeleteBuffer(u32),
+	DeleteTexture(u32),
+	DeleteFramebuffer(u32),
+	DeleteShader(u32),
+	DeleteProgram(u32),
+
+	Enable(GLenum),
+	Disable(GLenum),
+	BlendEquation(GLenum),
+	BlendFunc(GLenum, GLenum),
+	DepthFunc(GLenum),
+	DepthMask(bool),
+	FrontFace(GLenum),
+	LineWidth(f32),
+	Viewport(i32, i32, i32, i32),
+	ClearColor(f32, f32, f32, f32),
+	Clear(GLbitfield),
+
+	BindBuffer(GLenum, u32),
+	BufferData(GLenum, Vec<u8>, GLenum),
+
+	ActiveTexture(GLenum),
+	BindTexture(GLenum, u32),
+	TexParameter(GLenum, GLenum, i32),
+	TexImage2D { target: GLenum, level: i32, internal_format: i32, width: i32, height: i32,
+		format: GLenum, kind: GLenum, pixels: Option<Vec<u8>> },
+
+	BindFramebuffer(GLenum, u32),
+	FramebufferTexture2D(GLenum, GLenum, GLenum, u32, i32),
+
+	ShaderSource(u32, String),
+	CompileShader(u32),
+	AttachShader(u32, u32),
+	LinkProgram(u32),
+	UseProgram(u32),
+
+	GetShaderParameter(u32, GLenum),
+	GetProgramParameter(u32, GLenum),
+	GetShaderInfoLog(u32),
+	GetProgramInfoLog(u32),
+	GetInteger(GLenum),
+	GetError,
+
+	GetUniformLocation(u32, String),
+	Uniform(i32, Vec<f32>),
+	UniformI32(i32, i32),
+	UniformMatrix4(i32, bool, Vec<f32>),
+
+	EnableVertexAttribArray(u32),
+	DisableVertexAttribArray(u32),
+	VertexAttribPointer { index: u32, size: i32, kind: GLenum, normalized: bool, stride: i32, offset: usize },
+
+	DrawArrays(GLenum, i32, i32),
+	DrawElements(GLenum, i32, GLenum, usize),
+
+	ReadPixels(i32, i32, i32, i32, GLenum, GLenum),
+}
+
+/// Everything a RecordingGl saw
+#[derive(Clone, Debug)]
+pub struct GlRecording {
+	pub calls: Vec<GlCall>,
+	/// Descriptions of every call real GL would have rejected, in order
+	pub problems: Vec<String>,
+
+	live: HashMap<u32, GlObject>,
+	deleted: HashMap<u32, GlObject>,
+}
+
+impl GlRecording {
+	fn new() -> Self {
+		GlRecording {
+			calls: Vec::new(),
+			problems: Vec::new(),
+			live: HashMap::new(),
+			deleted: HashMap::new(),
+		}
+	}
+
+	/// Objects that have been created and not deleted, in the order they were made
+	pub fn live_objects(&self) -> Vec<(u32, GlObject)> {
+		let mut objects: Vec<_> = self.live.iter().map(|(&name, &kind)| (name, kind)).collect();
+		objects.sort_by_key(|&(name, _)| name);
+		objects
+	}
+
+	pub fn is_live(&self, name: u32) -> bool { self.live.contains_key(&name) }
+}
+
+/// Records into a GlRecording shared with whoever created it, since once the
+/// backend is installed there's no getting it back out as a RecordingGl
+pub struct RecordingGl {
+	recording: Rc<RefCell<GlRecording>>,
+	next_name: u32,
+	error: GLenum,
+
+	array_buffer: u32,
+	element_buffer: u32,
+	active_texture: u32,
+	textures: HashMap<u32, u32>,
+	framebuffer: u32,
+	program: u32,
+	/// The current program, if it's been deleted. GL keeps it around until
+	/// another one is used
+	doomed_program: u32,
+
+	uniform_names: Vec<String>,
+}
+
+impl RecordingGl {
+	pub fn new() -> (RecordingGl, Rc<RefCell<GlRecording>>) {
+		let recording = Rc::new(RefCell::new(GlRecording::new()));
+
+		let gl = RecordingGl {
+			recording: recording.clone(),
+			next_name: 1,
+			error: NO_ERROR,
+
+			array_buffer: 0,
+			element_buffer: 0,
+			active_texture: 0,
+			textures: HashMap::new(),
+			framebuffer: 0,
+			program: 0,
+			doomed_program: 0,
+
+			uniform_names: Vec::new(),
+		};
+
+		(gl, recording)
+	}
+
+	fn record(&mut self, call: GlCall) {
+		self.recording.borrow_mut().calls.push(call);
+	}
+
+	fn problem(&mut self, error: GLenum, description: String) {
+		if self.error == NO_ERROR {
+			self.error = error;
+		}
+
+		self.recording.borrow_mut().problems.push(description);
+	}
+
+	fn create(&mut self, kind: GlObject) -> u32 {
+		let name = self.next_name;
+		self.next_name += 1;
+		self.recording.borrow_mut().live.insert(name, kind);
+		name
+	}
+
+	/// Checks `name` is a live object of the right kind. Zero is always fine,
+	/// since that's how things get unbound
+	fn check(&mut self, call: &str, name: u32, kind: GlObject) -> bool {
+		if name == 0 { return true }
+
+		let (live, deleted) = {
+			let recording = self.recording.borrow();
+			(recording.live.get(&name).cloned(), recording.deleted.get(&name).cloned())
+		};
+
+		let problem = match (live, deleted) {
+			(Some(k), _) if k == kind => return true,
+			(Some(k), _) => format!("{}: {} is a {:?}, not a {:?}", call, name, k, kind),
+			(None, Some(k)) => format!("{}: {:?} {} has been deleted", call, k, name),
+			(None, None) => format!("{}: {:?} {} was never created", call, kind, name),
+		};
+
+		self.problem(INVALID_OPERATION, problem);
+		false
+	}
+
+	/// Needs something bound for `call` to act on
+	fn check_bound(&mut self, call: &str, bound: u32, what: &str) {
+		if bound == 0 {
+			self.problem(INVALID_OPERATION, format!("{}: no {} bound", call, what));
+		}
+	}
+
+	fn delete(&mut self, call: &str, name: u32, kind: GlObject) {
+		if name == 0 || !self.check(call, name, kind) { return }
+
+		{
+			let mut recording = self.recording.borrow_mut();
+			recording.live.remove(&name);
+			recording.deleted.insert(name, kind);
+		}
+
+		// Deleting something that's bound unbinds it
+		let unbind = |bound: &mut u32| if *bound == name { *bound = 0 };
+		unbind(&mut self.array_buffer);
+		unbind(&mut self.element_buffer);
+		unbind(&mut self.framebuffer);
+
+		for texture in self.textures.values_mut() {
+			unbind(texture);
+		}
+	}
+
+	fn bound_texture(&self) -> u32 {
+		self.textures.get(&self.active_texture).cloned().unwrap_or(0)
+	}
+}
+
+impl GlBackend for RecordingGl {
+	fn gen_buffer(&mut self) -> u32 {
+		let name = self.create(GlObject::Buffer);
+		self.record(GlCall::GenBuffer(name));
+		name
+	}
+
+	fn gen_texture(&mut self) -> u32 {
+		let name = self.create(GlObject::Texture);
+		self.record(GlCall::GenTexture(name));
+		name
+	}
+
+	fn gen_framebuffer(&mut self) -> u32 {
+		let name = self.create(GlObject::Framebuffer);
+		self.record(GlCall::GenFramebuffer(name));
+		name
+	}
+
+	fn create_shader(&mut self, kind: GLenum) -> u32 {
+		let name = self.create(GlObject::Shader);
+		self.record(GlCall::CreateShader(kind, name));
+		name
+	}
+
+	fn create_program(&mut self) -> u32 {
+		let name = self.create(GlObject::Program);
+		self.record(GlCall::CreateProgram(name));
+		name
+	}
+
+	fn delete_buffer(&mut self, buffer: u32) {
+		self.record(GlCall::DeleteBuffer(buffer));
+		self.delete("DeleteBuffers", buffer, GlObject::Buffer);
+	}
+
+	fn delete_texture(&mut self, texture: u32) {
+		self.record(GlCall::DeleteTexture(texture));
+		self.delete("DeleteTextures", texture, GlObject::Texture);
+	}
+
+	fn delete_framebuffer(&mut self, framebuffer: u32) {
+		self.record(GlCall::DeleteFramebuffer(framebuffer));
+		self.delete("DeleteFramebuffers", framebuffer, GlObject::Framebuffer);
+	}
+
+	fn delete_shader(&mut self, shader: u32) {
+		self.record(GlCall::DeleteShader(shader));
+		self.delete("DeleteShader", shader, GlObject::Shader);
+	}
+
+	fn delete_program(&mut self, program: u32) {
+		self.record(GlCall::DeleteProgram(program));
+
+		// The current program is only flagged, and stays current until it's
+		// replaced
+		if program != 0 && program == self.program {
+			if self.check("DeleteProgram", program, GlObject::Program) {
+				self.doomed_program = program;
+			}
+
+			return
+		}
+
+		self.delete("DeleteProgram", program, GlObject::Program);
+	}
+
+	fn enable(&mut self, cap: GLenum) { self.record(GlCall::Enable(cap)) }
+	fn disable(&mut self, cap: GLenum) { self.record(GlCall::Disable(cap)) }
+	fn blend_equation(&mut self, mode: GLenum) { self.record(GlCall::BlendEquation(mode)) }
+	fn blend_func(&mut self, sfactor: GLenum, dfactor: GLenum) { self.record(GlCall::BlendFunc(sfactor, dfactor)) }
+	fn depth_func(&mut self, func: GLenum) { self.record(GlCall::DepthFunc(func)) }
+	fn depth_mask(&mut self, flag: bool) { self.record(GlCall::DepthMask(flag)) }
+	fn front_face(&mut self, mode: GLenum) { self.record(GlCall::FrontFace(mode)) }
+	fn line_width(&mut self, width: f32) { self.record(GlCall::LineWidth(width)) }
+	fn viewport(&mut self, x: i32, y: i32, width: i32, height: i32) { self.record(GlCall::Viewport(x, y, width, height)) }
+	fn clear_color(&mut self, r: f32, g: f32, b: f32, a: f32) { self.record(GlCall::ClearColor(r, g, b, a)) }
+	fn clear(&mut self, mask: GLbitfield) { self.record(GlCall::Clear(mask)) }
+
+	fn bind_buffer(&mut self, target: GLenum, buffer: u32) {
+		self.record(GlCall::BindBuffer(target, buffer));
+		if !self.check("BindBuffer", buffer, GlObject::Buffer) { return }
+
+		match target {
+			ARRAY_BUFFER => self.array_buffer = buffer,
+			ELEMENT_ARRAY_BUFFER => self.element_buffer = buffer,
+			_ => self.problem(INVALID_ENUM, format!("BindBuffer: unknown target {:#x}", target)),
+		}
+	}
+
+	fn buffer_data(&mut self, target: GLenum, data: &[u8], usage: GLenum) {
+		self.record(GlCall::BufferData(target, data.to_vec(), usage));
+
+		let bound = match target {
+			ARRAY_BUFFER => self.array_buffer,
+			ELEMENT_ARRAY_BUFFER => self.element_buffer,
+			_ => return self.problem(INVALID_ENUM, format!("BufferData: unknown target {:#x}", target)),
+		};
+
+		self.check_bound("BufferData", bound, "buffer");
+	}
+
+	fn active_texture(&mut self, unit: GLenum) {
+		self.record(GlCall::ActiveTexture(unit));
+		self.active_texture = unit.wrapping_sub(TEXTURE0);
+	}
+
+	fn bind_texture(&mut self, target: GLenum, texture: u32) {
+		self.record(GlCall::BindTexture(target, texture));
+		if !self.check("BindTexture", texture, GlObject::Texture) { return }
+
+		let unit = self.active_texture;
+		self.textures.insert(unit, texture);
+	}
+
+	fn tex_parameter(&mut self, target: GLenum, pname: GLenum, param: i32) {
+		self.record(GlCall::TexParameter(target, pname, param));
+
+		let bound = self.bound_texture();
+		self.check_bound("TexParameteri", bound, "texture");
+	}
+
+	fn tex_image_2d(&mut self, target: GLenum, level: i32, internal_format: i32, width: i32, height: i32,
+		format: GLenum, kind: GLenum, pixels: Option<&[u8]>) {
+
+		self.record(GlCall::TexImage2D {
+			target, level, internal_format, width, height, format, kind,
+			pixels: pixels.map(|p| p.to_vec()),
+		});
+
+		let bound = self.bound_texture();
+		self.check_bound("TexImage2D", bound, "texture");
+	}
+
+	fn bind_framebuffer(&mut self, target: GLenum, framebuffer: u32) {
+		self.record(GlCall::BindFramebuffer(target, framebuffer));
+
+		if self.check("BindFramebuffer", framebuffer, GlObject::Framebuffer) {
+			self.framebuffer = framebuffer;
+		}
+	}
+
+	fn framebuffer_texture_2d(&mut self, target: GLenum, attachment: GLenum, textarget: GLenum, texture: u32, level: i32) {
+		self.record(GlCall::FramebufferTexture2D(target, attachment, textarget, texture, level));
+
+		let bound = self.framebuffer;
+		self.check_bound("FramebufferTexture2D", bound, "framebuffer");
+		self.check("FramebufferTexture2D", texture, GlObject::Texture);
+	}
+
+	fn shader_source(&mut self, shader: u32, source: &str) {
+		self.record(GlCall::ShaderSource(shader, source.to_owned()));
+		self.check("ShaderSource", shader, GlObject::Shader);
+	}
+
+	fn compile_shader(&mut self, shader: u32) {
+		self.record(GlCall::CompileShader(shader));
+		self.check("CompileShader", shader, GlObject::Shader);
+	}
+
+	fn attach_shader(&mut self, program: u32, shader: u32) {
+		self.record(GlCall::AttachShader(program, shader));
+		self.check("AttachShader", program, GlObject::Program);
+		self.check("AttachShader", shader, GlObject::Shader);
+	}
+
+	fn link_program(&mut self, program: u32) {
+		self.record(GlCall::LinkProgram(program));
+		self.check("LinkProgram", program, GlObject::Program);
+	}
+
+	fn use_program(&mut self, program: u32) {
+		self.record(GlCall::UseProgram(program));
+
+		if !self.check("UseProgram", program, GlObject::Program) { return }
+
+		let doomed = self.doomed_program;
+		if doomed != 0 && doomed != program {
+			self.doomed_program = 0;
+			self.delete("UseProgram", doomed, GlObject::Program);
+		}
+
+		self.program = program;
+	}
+
+	fn get_shader_parameter(&mut self, shader: u32, pname: GLenum) -> i32 {
+		self.record(GlCall::GetShaderParameter(shader, pname));
+		self.check("GetShaderiv", shader, GlObject::Shader);
+		if pname == COMPILE_STATUS { 1 } else { 0 }
+	}
+
+	fn get_program_parameter(&mut self, program: u32, pname: GLenum) -> i32 {
+		self.record(GlCall::GetProgramParameter(program, pname));
+		self.check("GetProgramiv", program, GlObject::Program);
+		if pname == LINK_STATUS { 1 } else { 0 }
+	}
+
+	fn get_shader_info_log(&mut self, shader: u32) -> String {
+		self.record(GlCall::GetShaderInfoLog(shader));
+		String::new()
+	}
+
+	fn get_program_info_log(&mut self, program: u32) -> String {
+		self.record(GlCall::GetProgramInfoLog(program));
+		String::new()
+	}
+
+	fn get_integer(&mut self, pname: GLenum) -> i32 {
+		self.record(GlCall::GetInteger(pname));
+
+		match pname {
+			TEXTURE_BINDING_2D => self.bound_texture() as i32,
+			_ => 0,
+		}
+	}
+
+	fn get_error(&mut self) -> GLenum {
+		self.record(GlCall::GetError);
+
+		let error = self.error;
+		self.error = NO_ERROR;
+		error
+	}
+
+	fn get_uniform_location(&mut self, program: u32, name: &str) -> i32 {
+		self.record(GlCall::GetUniformLocation(program, name.to_owned()));
+		if !self.check("GetUniformLocation", program, GlObject::Program) { return -1 }
+
+		// Every name gets its own location, the same for every program
+		match self.uniform_names.iter().position(|n| n == name) {
+			Some(loc) => loc as i32,
+			None => {
+				self.uniform_names.push(name.to_owned());
+				self.uniform_names.len() as i32 - 1
+			}
+		}
+	}
+
+	fn uniform_f32s(&mut self, location: i32, values: &[f32]) {
+		self.record(GlCall::Uniform(location, values.to_vec()));
+
+		let program = self.program;
+		self.check_bound("Uniform", program, "program");
+	}
+
+	fn uniform_i32(&mut self, location: i32, value: i32) {
+		self.record(GlCall::UniformI32(location, value));
+
+		let program = self.program;
+		self.check_bound("Uniform1i", program, "program");
+	}
+
+	fn uniform_matrix4(&mut self, location: i32, transpose: bool, values: &[f32]) {
+		self.record(GlCall::UniformMatrix4(location, transpose, values.to_vec()));
+
+		let program = self.program;
+		self.check_bound("UniformMatrix4fv", program, "program");
+	}
+
+	fn enable_vertex_attrib_array(&mut self, index: u32) { self.record(GlCall::EnableVertexAttribArray(index)) }
+	fn disable_vertex_attrib_array(&mut self, index: u32) { self.record(GlCall::DisableVertexAttribArray(index)) }
+
+	fn vertex_attrib_pointer(&mut self, index: u32, size: i32, kind: GLenum, normalized: bool, stride: i32, offset: usize) {
+		self.record(GlCall::VertexAttribPointer { index, size, kind, normalized, stride, offset });
+
+		let bound = self.array_buffer;
+		self.check_bound("VertexAttribPointer", bound, "array buffer");
+	}
+
+	fn draw_arrays(&mut self, mode: GLenum, first: i32, count: i32) {
+		self.record(GlCall::DrawArrays(mode, first, count));
+
+		let program = self.program;
+		self.check_bound("DrawArrays", program, "program");
+	}
+
+	fn draw_elements(&mut self, mode: GLenum, count: i32, kind: GLenum, offset: usize) {
+		self.record(GlCall::DrawElements(mode, count, kind, offset));
+
+		let (program, elements) = (self.program, self.element_buffer);
+		self.check_bound("DrawElements", program, "program");
+		self.check_bound("DrawElements", elements, "element array buffer");
+	}
+
+	fn read_pixels(&mut self, x: i32, y: i32, width: i32, height: i32, format: GLenum, kind: GLenum, _pixels: &mut [u8]) {
+		self.record(GlCall::ReadPixels(x, y, width, height, format, kind));
+	}
+}
+
+/// Installs a RecordingGl on this thread while `f` runs, and returns what it
+/// recorded. Whatever backend was installed before is put back afterwards
+pub fn record_gl<F>(f: F) -> GlRecording where F: FnOnce() {
+	let (gl, recording) = RecordingGl::new();
+	let previous = gl_dispatch::set_backend(box gl);
+
+	f();
+
+	match previous {
+		Some(backend) => { gl_dispatch::set_backend(backend); }
+		None => { gl_dispatch::take_backend(); }
+	}
+
+	let recording = recording.borrow().clone();
+	recording
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use common::*;
+	use rendering::{Shader, Texture};
+	use rendering::framebuffer::FramebufferBuilder;
+	use rendering::mesh_builder::*;
+
+	const VS: &'static str = "attribute vec3 position;\nvoid main() { gl_Position = vec4(position, 1.0); }";
+	const FS: &'static str = "void main() { gl_FragColor = vec4(1.0); }";
+
+	fn f32_bytes(values: &[f32]) -> Vec<u8> {
+		values.iter()
+			.flat_map(|v| {
+				let bits = v.to_bits();
+				vec![bits as u8, (bits >> 8) as u8, (bits >> 16) as u8, (bits >> 24) as u8]
+			})
+			.collect()
+	}
+
+	#[test]
+	fn mesh_bind() {
+		let recording = record_gl(|| {
+			let mut mb = MeshBuilder::new();
+			mb.add_vert(ColorVertex(Vec3::new(1.0, 2.0, 3.0), Vec3::new(0.5, 0.5, 0.5)));
+			mb.add_vert(ColorVertex(Vec3::new(4.0, 5.0, 6.0), Vec3::new(0.0, 0.0, 1.0)));
+
+			let mesh: Mesh = mb.into();
+			mesh.bind();
+		});
+
+		assert_eq!(recording.calls, vec![
+			GlCall::GenBuffer(1),
+			GlCall::GenBuffer(2),
+			GlCall::BindBuffer(ARRAY_BUFFER, 1),
+			GlCall::BufferData(ARRAY_BUFFER, f32_bytes(&[1.0, 2.0, 3.0, 0.5, 0.5, 0.5, 4.0, 5.0, 6.0, 0.0, 0.0, 1.0]), STATIC_DRAW),
+			GlCall::BindBuffer(ELEMENT_ARRAY_BUFFER, 2),
+			GlCall::BufferData(ELEMENT_ARRAY_BUFFER, vec![0, 0, 1, 0], STATIC_DRAW),
+
+			GlCall::BindBuffer(ELEMENT_ARRAY_BUFFER, 2),
+			GlCall::BindBuffer(ARRAY_BUFFER, 1),
+			GlCall::EnableVertexAttribArray(0),
+			GlCall::VertexAttribPointer { index: 0, size: 3, kind: FLOAT, normalized: false, stride: 24, offset: 0 },
+			GlCall::EnableVertexAttribArray(1),
+			GlCall::VertexAttribPointer { index: 1, size: 3, kind: FLOAT, normalized: false, stride: 24, offset: 12 },
+
+			GlCall::DeleteBuffer(1),
+			GlCall::DeleteBuffer(2),
+		]);
+
+		assert_eq!(recording.problems, Vec::<String>::new());
+		assert!(recording.live_objects().is_empty());
+	}
+
+	#[test]
+	fn texture_upload_2d() {
+		let recording = record_gl(|| {
+			let mut texture = Texture::new();
+			let pixels = [Color::rgb8(255, 0, 0), Color::rgb8(0, 255, 0)];
+			texture.upload_2d(&pixels, Vec2i::new(2, 1));
+		});
+
+		assert_eq!(recording.calls, vec![
+			GlCall::GenTexture(1),
+			GlCall::GetInteger(TEXTURE_BINDING_2D),
+			GlCall::BindTexture(TEXTURE_2D, 1),
+			GlCall::TexParameter(TEXTURE_2D, TEXTURE_MIN_FILTER, NEAREST as i32),
+			GlCall::TexParameter(TEXTURE_2D, TEXTURE_MAG_FILTER, NEAREST as i32),
+			GlCall::TexParameter(TEXTURE_2D, TEXTURE_WRAP_S, CLAMP_TO_EDGE as i32),
+			GlCall::TexParameter(TEXTURE_2D, TEXTURE_WRAP_T, CLAMP_TO_EDGE as i32),
+			GlCall::BindTexture(TEXTURE_2D, 0),
+
+			GlCall::GetInteger(TEXTURE_BINDING_2D),
+			GlCall::BindTexture(TEXTURE_2D, 1),
+			GlCall::TexImage2D { target: TEXTURE_2D, level: 0, internal_format: RGBA as i32, width: 2, height: 1,
+				format: RGBA, kind: UNSIGNED_BYTE, pixels: Some(vec![255, 0, 0, 255, 0, 255, 0, 255]) },
+			GlCall::BindTexture(TEXTURE_2D, 0),
+
+			GlCall::DeleteTexture(1),
+		]);
+
+		assert_eq!(recording.problems, Vec::<String>::new());
+	}
+
+	#[test]
+	fn framebuffer_add_target() {
+		let recording = record_gl(|| {
+			FramebufferBuilder::new(Vec2i::new(64, 32))
+				.add_target()
+				.finalize();
+		});
+
+		assert_eq!(recording.calls, vec![
+			GlCall::GenFramebuffer(1),
+			GlCall::BindFramebuffer(FRAMEBUFFER, 1),
+
+			GlCall::GenTexture(2),
+			GlCall::GetInteger(TEXTURE_BINDING_2D),
+			GlCall::BindTexture(TEXTURE_2D, 2),
+			GlCall::TexParameter(TEXTURE_2D, TEXTURE_MIN_FILTER, LINEAR as i32),
+			GlCall::TexParameter(TEXTURE_2D, TEXTURE_MAG_FILTER, LINEAR as i32),
+			GlCall::TexParameter(TEXTURE_2D, TEXTURE_WRAP_S, CLAMP_TO_EDGE as i32),
+			GlCall::TexParameter(TEXTURE_2D, TEXTURE_WRAP_T, CLAMP_TO_EDGE as i32),
+			GlCall::TexImage2D { target: TEXTURE_2D, level: 0, internal_format: RGBA as i32, width: 64, height: 32,
+				format: RGBA, kind: UNSIGNED_BYTE, pixels: None },
+			GlCall::FramebufferTexture2D(FRAMEBUFFER, COLOR_ATTACHMENT0, TEXTURE_2D, 2, 0),
+			GlCall::BindTexture(TEXTURE_2D, 0),
+
+			GlCall::BindFramebuffer(FRAMEBUFFER, 0),
+
+			GlCall::DeleteFramebuffer(1),
+			GlCall::DeleteTexture(2),
+		]);
+
+		assert_eq!(recording.problems, Vec::<String>::new());
+		assert!(recording.live_objects().is_empty());
+	}
+
+	#[test]
+	fn shared_target_outlives_its_framebuffer() {
+		let recording = record_gl(|| {
+			let fb = FramebufferBuilder::new(Vec2i::new(64, 32))
+				.add_target()
+				.finalize();
+
+			let target = fb.get_target(0).unwrap();
+			drop(fb);
+
+			// Binding a deleted texture would show up in problems
+			let _guard = target.bind_guard();
+		});
+
+		let tail = &recording.calls[recording.calls.len() - 5..];
+		assert_eq!(tail, &[
+			GlCall::DeleteFramebuffer(1),
+			GlCall::GetInteger(TEXTURE_BINDING_2D),
+			GlCall::BindTexture(TEXTURE_2D, 2),
+			GlCall::BindTexture(TEXTURE_2D, 0),
+			GlCall::DeleteTexture(2),
+		][..]);
+
+		assert_eq!(recording.problems, Vec::<String>::new());
+		assert!(recording.live_objects().is_empty());
+	}
+
+	#[test]
+	fn shader_new() {
+		let recording = record_gl(|| {
+			Shader::new(VS, FS).unwrap();
+		});
+
+		assert_eq!(&recording.calls[..16], &[
+			GlCall::CreateProgram(1),
+
+			GlCall::CreateShader(VERTEX_SHADER, 2),
+			GlCall::ShaderSource(2, VS.to_owned()),
+			GlCall::CompileShader(2),
+			GlCall::GetShaderParameter(2, COMPILE_STATUS),
+			GlCall::AttachShader(1, 2),
+
+			GlCall::CreateShader(FRAGMENT_SHADER, 3),
+			GlCall::ShaderSource(3, FS.to_owned()),
+			GlCall::CompileShader(3),
+			GlCall::GetShaderParameter(3, COMPILE_STATUS),
+			GlCall::AttachShader(1, 3),
+
+			GlCall::LinkProgram(1),
+			GlCall::GetProgramParameter(1, LINK_STATUS),
+			GlCall::DeleteShader(2),
+			GlCall::DeleteShader(3),
+			GlCall::UseProgram(1),
+		][..]);
+
+		assert_eq!(recording.problems, Vec::<String>::new());
+	}
+
+	#[test]
+	fn deleting_the_current_program_waits_until_its_replaced() {
+		let recording = record_gl(|| {
+			let shader = Shader::new(VS, FS).unwrap();
+			shader.use_program();
+			drop(shader);
+
+			// Still current, so still usable
+			unsafe { gl_dispatch::UniformMatrix4fv(0, 1, FALSE, [0.0f32; 16].as_ptr()); }
+		});
+
+		assert_eq!(recording.problems, Vec::<String>::new());
+		assert!(recording.is_live(1));
+
+		let recording = record_gl(|| {
+			let shader = Shader::new(VS, FS).unwrap();
+			drop(shader);
+			unsafe { gl_dispatch::UseProgram(0); }
+		});
+
+		assert_eq!(recording.problems, Vec::<String>::new());
+		assert!(!recording.is_live(1));
+	}
+
+	#[test]
+	fn deleting_another_program_is_immediate() {
+		let recording = record_gl(|| {
+			let current = Shader::new(VS, FS).unwrap();
+			let other = Shader::new(VS, FS).unwrap();
+			current.use_program();
+			drop(other);
+		});
+
+		assert_eq!(recording.problems, Vec::<String>::new());
+		assert!(recording.is_live(1));
+		assert!(!recording.is_live(4));
+	}
+
+	#[test]
+	fn binding_a_deleted_texture() {
+		let mut error = NO_ERROR;
+
+		let recording = record_gl(|| {
+			let name = Texture::new().gl_handle();
+
+			unsafe {
+				gl_dispatch::BindTexture(TEXTURE_2D, name);
+				gl_dispatch::TexParameteri(TEXTURE_2D, TEXTURE_MIN_FILTER, LINEAR as i32);
+				error = gl_dispatch::GetError();
+			}
+		});
+
+		assert_eq!(recording.problems, vec![
+			"BindTexture: Texture 1 has been deleted".to_owned(),
+			"TexParameteri: no texture bound".to_owned(),
+		]);
+
+		// GetError reports the first problem, like real GL
+		assert_eq!(error, INVALID_OPERATION);
+	}
+
+	#[test]
+	fn uploading_to_a_deleted_buffer() {
+		let recording = record_gl(|| unsafe {
+			let mut buffer = 0;
+			gl_dispatch::GenBuffers(1, &mut buffer);
+			gl_dispatch::BindBuffer(ARRAY_BUFFER, buffer);
+			gl_dispatch::DeleteBuffers(1, &buffer);
+
+			// Deleting it unbound it, and it can't be bound again
+			let data = [0u8; 4];
+			gl_dispatch::BufferData(ARRAY_BUFFER, 4, data.as_ptr() as *const GLvoid, STATIC_DRAW);
+			gl_dispatch::BindBuffer(ARRAY_BUFFER, buffer);
+		});
+
+		assert_eq!(recording.problems, vec![
+			"BufferData: no buffer bound".to_owned(),
+			"BindBuffer: Buffer 1 has been deleted".to_owned(),
+		]);
+	}
+
+	#[test]
+	fn drawing_with_a_deleted_program() {
+		let recording = record_gl(|| {
+			let shader = Shader::new(VS, FS).unwrap();
+			let name = shader.gl_handle();
+
+			unsafe { gl_dispatch::UseProgram(0); }
+			drop(shader);
+
+			unsafe {
+				gl_dispatch::UseProgram(name);
+				gl_dispatch::DrawArrays(TRIANGLES, 0, 3);
+			}
+		});
+
+		assert_eq!(recording.problems, vec![
+			"UseProgram: Program 1 has been deleted".to_owned(),
+			"DrawArrays: no program bound".to_owned(),
+		]);
+	}
+
+	#[test]
+	fn drawing_elements_after_the_buffer_is_deleted() {
+		let recording = record_gl(|| {
+			let shader = Shader::new(VS, FS).unwrap();
+			shader.use_program();
+
+			unsafe {
+				let mut elements = 0;
+				gl_dispatch::GenBuffers(1, &mut elements);
+				gl_dispatch::BindBuffer(ELEMENT_ARRAY_BUFFER, elements);
+				gl_dispatch::DeleteBuffers(1, &elements);
+				gl_dispatch::DrawElements(TRIANGLES, 3, UNSIGNED_SHORT, 0 as *const GLvoid);
+			}
+		});
+
+		assert_eq!(recording.problems, vec!["DrawElements: no element array buffer bound".to_owned()]);
+	}
+
+	#[test]
+	fn names_of_the_wrong_kind() {
+		let recording = record_gl(|| unsafe {
+			let mut buffer = 0;
+			gl_dispatch::GenBuffers(1, &mut buffer);
+			gl_dispatch::BindTexture(TEXTURE_2D, buffer);
+			gl_dispatch::BindTexture(TEXTURE_2D, 7);
+			gl_dispatch::DeleteBuffers(1, &buffer);
+		});
+
+		assert_eq!(recording.problems, vec![
+			"BindTexture: 1 is a Buffer, not a Texture".to_owned(),
+			"BindTexture: Texture 7 was never created".to_owned(),
+		]);
+	}
+}
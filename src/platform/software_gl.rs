@@ -0,0 +1,880 @@
+// A GlBackend that draws on the CPU, so what the rendering module produces can
+// be checked without a browser or a GPU. It doesn't run GLSL - every program
+// behaves like assets/basic.vs and basic.fs, taking position from attribute 0
+// and colour from attribute 1 and transforming by the `proj` and `view`
+// uniforms. That covers everything the game draws.
+//
+// Supported: buffers, float vertex attributes, DrawElements and DrawArrays
+// with TRIANGLES, depth testing, blending, ClearColor/Clear, Viewport and
+// ReadPixels. Lines are accepted but not drawn, and draws into framebuffers
+// other than the default one are dropped.
+
+use std::collections::HashMap;
+use std::env;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::PathBuf;
+
+use common::*;
+use bindings::gl::*;
+use bindings::gl_dispatch::{self, GlBackend};
+use rendering::image::Image;
+
+/// How far a channel can stray from a golden image before it counts as different.
+/// Leaves room for float rounding differences between machines
+const GOLDEN_TOLERANCE: u8 = 2;
+
+#[derive(Copy, Clone)]
+struct Attribute {
+	enabled: bool,
+	buffer: u32,
+	size: i32,
+	kind: GLenum,
+	stride: i32,
+	offset: usize,
+}
+
+impl Attribute {
+	fn new() -> Self {
+		Attribute { enabled: false, buffer: 0, size: 4, kind: FLOAT, stride: 0, offset: 0 }
+	}
+}
+
+struct Program {
+	uniform_names: Vec<String>,
+	uniforms: HashMap<i32, Vec<f32>>,
+}
+
+/// A vertex after the vertex stage, in clip space
+#[derive(Copy, Clone)]
+struct ClipVertex {
+	pos: [f32; 4],
+	color: [f32; 4],
+}
+
+/// A vertex ready to rasterise, in window space
+#[derive(Copy, Clone)]
+struct ScreenVertex {
+	x: f32,
+	y: f32,
+	depth: f32,
+	inv_w: f32,
+	color: [f32; 4],
+}
+
+pub struct SoftwareGl {
+	size: Vec2i,
+	/// Bottom row first, like GL
+	color: Vec<[f32; 4]>,
+	depth: Vec<f32>,
+
+	next_name: u32,
+	error: GLenum,
+
+	buffers: HashMap<u32, Vec<u8>>,
+	programs: HashMap<u32, Program>,
+	attributes: Vec<Attribute>,
+
+	array_buffer: u32,
+	element_buffer: u32,
+	framebuffer: u32,
+	program: u32,
+
+	viewport: (i32, i32, i32, i32),
+	clear_color: [f32; 4],
+
+	depth_test: bool,
+	depth_func: GLenum,
+	depth_mask: bool,
+
+	blend: bool,
+	blend_equation: GLenum,
+	blend_func: (GLenum, GLenum),
+}
+
+impl SoftwareGl {
+	/// A backend drawing into a `size` sized default framebuffer, with GL's
+	/// default state
+	pub fn new(size: Vec2i) -> Self {
+		let pixels = (size.x * size.y) as usize;
+
+		SoftwareGl {
+			size,
+			color: vec![[0.0; 4]; pixels],
+			depth: vec![1.0; pixels],
+
+			next_name: 1,
+			error: NO_ERROR,
+
+			buffers: HashMap::new(),
+			programs: HashMap::new(),
+			attributes: vec![Attribute::new(); 8],
+
+			array_buffer: 0,
+			element_buffer: 0,
+			framebuffer: 0,
+			program: 0,
+
+			viewport: (0, 0, size.x, size.y),
+			clear_color: [0.0; 4],
+
+			depth_test: false,
+			depth_func: LESS,
+			depth_mask: true,
+
+			blend: false,
+			blend_equation: FUNC_ADD,
+			blend_func: (ONE, ZERO),
+		}
+	}
+
+	fn new_name(&mut self) -> u32 {
+		let name = self.next_name;
+		self.next_name += 1;
+		name
+	}
+
+	fn set_error(&mut self, error: GLenum) {
+		// Like GL, only the first error is kept until it's read
+		if self.error == NO_ERROR {
+			self.error = error;
+		}
+	}
+
+	/// The current program's value for the uniform called `name`, if it's been set
+	fn uniform(&self, name: &str) -> Option<&[f32]> {
+		let program = match self.programs.get(&self.program) {
+			Some(program) => program,
+			None => return None,
+		};
+
+		program.uniform_names.iter().position(|n| n == name)
+			.and_then(|loc| program.uniforms.get(&(loc as i32)))
+			.map(|values| &values[..])
+	}
+
+	fn fetch_attribute(&self, index: usize, vertex: usize) -> [f32; 4] {
+		let mut value = [0.0, 0.0, 0.0, 1.0];
+
+		let attrib = match self.attributes.get(index) {
+			Some(attrib) if attrib.enabled && attrib.kind == FLOAT => *attrib,
+			_ => return value,
+		};
+
+		let data = match self.buffers.get(&attrib.buffer) {
+			Some(data) => data,
+			None => return value,
+		};
+
+		let stride = if attrib.stride > 0 { attrib.stride as usize } else { attrib.size as usize * 4 };
+		let start = attrib.offset + vertex * stride;
+
+		for (i, v) in value.iter_mut().enumerate().take(attrib.size as usize) {
+			if let Some(x) = read_f32(data, start + i * 4) {
+				*v = x;
+			}
+		}
+
+		value
+	}
+
+	/// What basic.vs does
+	fn shade_vertex(&self, vertex: usize) -> ClipVertex {
+		let position = self.fetch_attribute(0, vertex);
+		let color = self.fetch_attribute(1, vertex);
+
+		let world = match self.uniform("view") {
+			Some(view) => transform(view, position),
+			None => position,
+		};
+
+		let pos = match self.uniform("proj") {
+			Some(proj) => transform(proj, world),
+			None => world,
+		};
+
+		ClipVertex { pos, color }
+	}
+
+	fn draw(&mut self, mode: GLenum, indices: &[usize]) {
+		if self.framebuffer != 0 { return }
+
+		match mode {
+			TRIANGLES | TRIANGLE_STRIP | TRIANGLE_FAN => {}
+			LINES | LINE_STRIP | LINE_LOOP | POINTS => return,
+			_ => return self.set_error(INVALID_ENUM),
+		}
+
+		let vertices: Vec<ClipVertex> = indices.iter().map(|&i| self.shade_vertex(i)).collect();
+		let count = vertices.len();
+
+		for i in 0..count.saturating_sub(2) {
+			let tri = match mode {
+				TRIANGLES if i % 3 == 0 => [i, i+1, i+2],
+				TRIANGLE_STRIP if i % 2 == 0 => [i, i+1, i+2],
+				TRIANGLE_STRIP => [i+1, i, i+2],
+				TRIANGLE_FAN => [0, i+1, i+2],
+				_ => continue,
+			};
+
+			self.draw_triangle(vertices[tri[0]], vertices[tri[1]], vertices[tri[2]]);
+		}
+	}
+
+	/// Clips against the near plane, then rasterises whatever is left as a fan
+	fn draw_triangle(&mut self, a: ClipVertex, b: ClipVertex, c: ClipVertex) {
+		let near_distance = |v: &ClipVertex| v.pos[2] + v.pos[3];
+
+		let mut poly = Vec::with_capacity(4);
+		let corners = [a, b, c];
+
+		for i in 0..3 {
+			let (from, to) = (corners[i], corners[(i + 1) % 3]);
+			let (df, dt) = (near_distance(&from), near_distance(&to));
+
+			if df >= 0.0 {
+				poly.push(from);
+			}
+
+			if (df >= 0.0) != (dt >= 0.0) {
+				poly.push(lerp_vertex(from, to, df / (df - dt)));
+			}
+		}
+
+		let screen: Vec<ScreenVertex> = poly.iter()
+			.filter(|v| v.pos[3] > 0.0)
+			.map(|v| self.to_screen(v))
+			.collect();
+
+		for i in 1..screen.len().saturating_sub(1) {
+			self.rasterise(screen[0], screen[i], screen[i + 1]);
+		}
+	}
+
+	fn to_screen(&self, v: &ClipVertex) -> ScreenVertex {
+		let (vx, vy, vw, vh) = self.viewport;
+		let inv_w = 1.0 / v.pos[3];
+
+		ScreenVertex {
+			x: vx as f32 + (v.pos[0] * inv_w + 1.0) * 0.5 * vw as f32,
+			y: vy as f32 + (v.pos[1] * inv_w + 1.0) * 0.5 * vh as f32,
+			depth: (v.pos[2] * inv_w + 1.0) * 0.5,
+			inv_w,
+			color: v.color,
+		}
+	}
+
+	fn rasterise(&mut self, v0: ScreenVertex, v1: ScreenVertex, v2: ScreenVertex) {
+		// Keep everything anticlockwise so the edge functions are positive inside
+		let area = edge(&v0, &v1, v2.x, v2.y);
+		if area == 0.0 { return }
+
+		let (v1, v2) = if area > 0.0 { (v1, v2) } else { (v2, v1) };
+		let area = area.abs();
+
+		let (vx, vy, vw, vh) = self.viewport;
+		let min_x = vx.max(0);
+		let min_y = vy.max(0);
+		let max_x = (vx + vw).min(self.size.x);
+		let max_y = (vy + vh).min(self.size.y);
+
+		let lo_x = (v0.x.min(v1.x).min(v2.x).floor() as i32).max(min_x);
+		let lo_y = (v0.y.min(v1.y).min(v2.y).floor() as i32).max(min_y);
+		let hi_x = (v0.x.max(v1.x).max(v2.x).ceil() as i32).min(max_x);
+		let hi_y = (v0.y.max(v1.y).max(v2.y).ceil() as i32).min(max_y);
+
+		for y in lo_y..hi_y {
+			for x in lo_x..hi_x {
+				let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+
+				let w0 = edge(&v1, &v2, px, py);
+				let w1 = edge(&v2, &v0, px, py);
+				let w2 = edge(&v0, &v1, px, py);
+
+				if !covers(w0, &v1, &v2) || !covers(w1, &v2, &v0) || !covers(w2, &v0, &v1) {
+					continue
+				}
+
+				let (b0, b1, b2) = (w0 / area, w1 / area, w2 / area);
+
+				let depth = b0 * v0.depth + b1 * v1.depth + b2 * v2.depth;
+				if depth < 0.0 || depth > 1.0 { continue }
+
+				// Colours are interpolated in clip space, so they stay put under perspective
+				let (p0, p1, p2) = (b0 * v0.inv_w, b1 * v1.inv_w, b2 * v2.inv_w);
+				let inv_w = p0 + p1 + p2;
+
+				let mut color = [0.0; 4];
+				for i in 0..4 {
+					color[i] = (p0 * v0.color[i] + p1 * v1.color[i] + p2 * v2.color[i]) / inv_w;
+				}
+
+				self.write_fragment(x, y, depth, color);
+			}
+		}
+	}
+
+	fn write_fragment(&mut self, x: i32, y: i32, depth: f32, color: [f32; 4]) {
+		let idx = (y * self.size.x + x) as usize;
+
+		if self.depth_test {
+			if !depth_passes(self.depth_func, depth, self.depth[idx]) { return }
+
+			if self.depth_mask {
+				self.depth[idx] = depth;
+			}
+		}
+
+		let mut color = color;
+		if self.blend {
+			let dst = self.color[idx];
+			let (sfactor, dfactor) = self.blend_func;
+
+			for i in 0..4 {
+				let s = color[i] * blend_factor(sfactor, i, &color, &dst);
+				let d = dst[i] * blend_factor(dfactor, i, &color, &dst);
+
+				color[i] = match self.blend_equation {
+					FUNC_SUBTRACT => s - d,
+					FUNC_REVERSE_SUBTRACT => d - s,
+					_ => s + d,
+				};
+			}
+		}
+
+		for c in color.iter_mut() {
+			*c = c.max(0.0).min(1.0);
+		}
+
+		self.color[idx] = color;
+	}
+}
+
+impl GlBackend for SoftwareGl {
+	fn gen_buffer(&mut self) -> u32 {
+		let name = self.new_name();
+		self.buffers.insert(name, Vec::new());
+		name
+	}
+
+	fn gen_texture(&mut self) -> u32 { self.new_name() }
+	fn gen_framebuffer(&mut self) -> u32 { self.new_name() }
+	fn create_shader(&mut self, _: GLenum) -> u32 { self.new_name() }
+
+	fn create_program(&mut self) -> u32 {
+		let name = self.new_name();
+		self.programs.insert(name, Program { uniform_names: Vec::new(), uniforms: HashMap::new() });
+		name
+	}
+
+	fn delete_buffer(&mut self, buffer: u32) { self.buffers.remove(&buffer); }
+	fn delete_program(&mut self, program: u32) { self.programs.remove(&program); }
+
+	fn enable(&mut self, cap: GLenum) {
+		match cap {
+			DEPTH_TEST => self.depth_test = true,
+			BLEND => self.blend = true,
+			_ => {}
+		}
+	}
+
+	fn disable(&mut self, cap: GLenum) {
+		match cap {
+			DEPTH_TEST => self.depth_test = false,
+			BLEND => self.blend = false,
+			_ => {}
+		}
+	}
+
+	fn blend_equation(&mut self, mode: GLenum) { self.blend_equation = mode; }
+	fn blend_func(&mut self, sfactor: GLenum, dfactor: GLenum) { self.blend_func = (sfactor, dfactor); }
+	fn depth_func(&mut self, func: GLenum) { self.depth_func = func; }
+	fn depth_mask(&mut self, flag: bool) { self.depth_mask = flag; }
+
+	fn viewport(&mut self, x: i32, y: i32, width: i32, height: i32) {
+		self.viewport = (x, y, width, height);
+	}
+
+	fn clear_color(&mut self, r: f32, g: f32, b: f32, a: f32) {
+		self.clear_color = [r, g, b, a];
+	}
+
+	fn clear(&mut self, mask: GLbitfield) {
+		if self.framebuffer != 0 { return }
+
+		if mask & COLOR_BUFFER_BIT != 0 {
+			for c in self.color.iter_mut() { *c = self.clear_color; }
+		}
+
+		if mask & DEPTH_BUFFER_BIT != 0 {
+			for d in self.depth.iter_mut() { *d = 1.0; }
+		}
+	}
+
+	fn bind_buffer(&mut self, target: GLenum, buffer: u32) {
+		match target {
+			ARRAY_BUFFER => self.array_buffer = buffer,
+			ELEMENT_ARRAY_BUFFER => self.element_buffer = buffer,
+			_ => self.set_error(INVALID_ENUM),
+		}
+	}
+
+	fn buffer_data(&mut self, target: GLenum, data: &[u8], _usage: GLenum) {
+		let buffer = match target {
+			ARRAY_BUFFER => self.array_buffer,
+			ELEMENT_ARRAY_BUFFER => self.element_buffer,
+			_ => return self.set_error(INVALID_ENUM),
+		};
+
+		let found = match self.buffers.get_mut(&buffer) {
+			Some(contents) => { *contents = data.to_vec(); true }
+			None => false,
+		};
+
+		if !found { self.set_error(INVALID_OPERATION) }
+	}
+
+	fn bind_framebuffer(&mut self, _target: GLenum, framebuffer: u32) {
+		self.framebuffer = framebuffer;
+	}
+
+	fn use_program(&mut self, program: u32) { self.program = program; }
+
+	fn get_error(&mut self) -> GLenum {
+		let error = self.error;
+		self.error = NO_ERROR;
+		error
+	}
+
+	fn get_uniform_location(&mut self, program: u32, name: &str) -> i32 {
+		let program = match self.programs.get_mut(&program) {
+			Some(program) => program,
+			None => return -1,
+		};
+
+		if let Some(loc) = program.uniform_names.iter().position(|n| n == name) {
+			return loc as i32
+		}
+
+		program.uniform_names.push(name.to_owned());
+		program.uniform_names.len() as i32 - 1
+	}
+
+	fn uniform_f32s(&mut self, location: i32, values: &[f32]) {
+		if location < 0 { return }
+
+		if let Some(program) = self.programs.get_mut(&self.program) {
+			program.uniforms.insert(location, values.to_vec());
+		}
+	}
+
+	fn uniform_matrix4(&mut self, location: i32, transpose: bool, values: &[f32]) {
+		if !transpose {
+			return self.uniform_f32s(location, &values[..16])
+		}
+
+		let mut columns = [0.0; 16];
+		for r in 0..4 {
+			for c in 0..4 {
+				columns[c*4 + r] = values[r*4 + c];
+			}
+		}
+
+		self.uniform_f32s(location, &columns)
+	}
+
+	fn enable_vertex_attrib_array(&mut self, index: u32) {
+		let found = match self.attributes.get_mut(index as usize) {
+			Some(attrib) => { attrib.enabled = true; true }
+			None => false,
+		};
+
+		if !found { self.set_error(INVALID_VALUE) }
+	}
+
+	fn disable_vertex_attrib_array(&mut self, index: u32) {
+		if let Some(attrib) = self.attributes.get_mut(index as usize) {
+			attrib.enabled = false;
+		}
+	}
+
+	fn vertex_attrib_pointer(&mut self, index: u32, size: i32, kind: GLenum, _normalized: bool, stride: i32, offset: usize) {
+		let buffer = self.array_buffer;
+
+		let found = match self.attributes.get_mut(index as usize) {
+			Some(attrib) => {
+				let enabled = attrib.enabled;
+				*attrib = Attribute { enabled, buffer, size, kind, stride, offset };
+				true
+			}
+
+			None => false,
+		};
+
+		if !found { self.set_error(INVALID_VALUE) }
+	}
+
+	fn draw_arrays(&mut self, mode: GLenum, first: i32, count: i32) {
+		let indices: Vec<usize> = (first as usize .. (first + count) as usize).collect();
+		self.draw(mode, &indices);
+	}
+
+	fn draw_elements(&mut self, mode: GLenum, count: i32, kind: GLenum, offset: usize) {
+		let index_size = match kind {
+			UNSIGNED_BYTE => 1,
+			UNSIGNED_SHORT => 2,
+			UNSIGNED_INT => 4,
+			_ => return self.set_error(INVALID_ENUM),
+		};
+
+		let indices: Vec<usize> = match self.buffers.get(&self.element_buffer) {
+			Some(data) => (0..count as usize)
+				.map(|i| read_uint(data, offset + i * index_size, index_size))
+				.collect(),
+
+			None => return self.set_error(INVALID_OPERATION),
+		};
+
+		self.draw(mode, &indices);
+	}
+
+	fn read_pixels(&mut self, x: i32, y: i32, width: i32, height: i32, format: GLenum, kind: GLenum, pixels: &mut [u8]) {
+		if format != RGBA || kind != UNSIGNED_BYTE {
+			return self.set_error(INVALID_ENUM)
+		}
+
+		for row in 0..height {
+			for col in 0..width {
+				let (sx, sy) = (x + col, y + row);
+				if sx < 0 || sy < 0 || sx >= self.size.x || sy >= self.size.y { continue }
+
+				let color = self.color[(sy * self.size.x + sx) as usize];
+				let out = ((row * width + col) * 4) as usize;
+
+				for i in 0..4 {
+					pixels[out + i] = (color[i] * 255.0).round() as u8;
+				}
+			}
+		}
+	}
+}
+
+fn read_f32(data: &[u8], at: usize) -> Option<f32> {
+	if at + 4 > data.len() { return None }
+	Some(f32::from_bits(read_uint(data, at, 4) as u32))
+}
+
+/// Reads a little endian unsigned integer `size` bytes long
+fn read_uint(data: &[u8], at: usize, size: usize) -> usize {
+	let mut value = 0;
+	for i in 0..size {
+		value |= (*data.get(at + i).unwrap_or(&0) as usize) << (i * 8);
+	}
+	value
+}
+
+/// Multiplies a column major matrix, as handed to UniformMatrix4fv, by `v`
+fn transform(m: &[f32], v: [f32; 4]) -> [f32; 4] {
+	if m.len() < 16 { return v }
+
+	let mut out = [0.0; 4];
+	for r in 0..4 {
+		out[r] = m[r] * v[0] + m[4 + r] * v[1] + m[8 + r] * v[2] + m[12 + r] * v[3];
+	}
+	out
+}
+
+fn lerp_vertex(a: ClipVertex, b: ClipVertex, t: f32) -> ClipVertex {
+	let mut v = a;
+	for i in 0..4 {
+		v.pos[i] = a.pos[i] + (b.pos[i] - a.pos[i]) * t;
+		v.color[i] = a.color[i] + (b.color[i] - a.color[i]) * t;
+	}
+	v
+}
+
+/// Twice the signed area of the triangle (a, b, p). Positive when p is to the
+/// left of a -> b
+fn edge(a: &ScreenVertex, b: &ScreenVertex, px: f32, py: f32) -> f32 {
+	(b.x - a.x) * (py - a.y) - (b.y - a.y) * (px - a.x)
+}
+
+/// Whether a pixel centre with edge function `w` is inside the edge a -> b.
+/// Centres exactly on an edge only belong to it if it's a top or left edge,
+/// so triangles sharing an edge never both draw the same pixel
+fn covers(w: f32, a: &ScreenVertex, b: &ScreenVertex) -> bool {
+	if w != 0.0 { return w > 0.0 }
+
+	let top = a.y == b.y && b.x < a.x;
+	let left = b.y < a.y;
+	top || left
+}
+
+fn depth_passes(func: GLenum, incoming: f32, stored: f32) -> bool {
+	match func {
+		NEVER => false,
+		LESS => incoming < stored,
+		EQUAL => incoming == stored,
+		LEQUAL => incoming <= stored,
+		GREATER => incoming > stored,
+		NOTEQUAL => incoming != stored,
+		GEQUAL => incoming >= stored,
+		_ => true,
+	}
+}
+
+fn blend_factor(factor: GLenum, channel: usize, src: &[f32; 4], dst: &[f32; 4]) -> f32 {
+	match factor {
+		ZERO => 0.0,
+		SRC_COLOR => src[channel],
+		ONE_MINUS_SRC_COLOR => 1.0 - src[channel],
+		DST_COLOR => dst[channel],
+		ONE_MINUS_DST_COLOR => 1.0 - dst[channel],
+		SRC_ALPHA => src[3],
+		ONE_MINUS_SRC_ALPHA => 1.0 - src[3],
+		DST_ALPHA => dst[3],
+		ONE_MINUS_DST_ALPHA => 1.0 - dst[3],
+		_ => 1.0,
+	}
+}
+
+/// Installs a SoftwareGl on this thread while `f` runs, and returns what it drew.
+/// Whatever backend was installed before is put back afterwards
+pub fn render_to_image<F>(size: Vec2i, f: F) -> Image where F: FnOnce() {
+	let previous = gl_dispatch::set_backend(box SoftwareGl::new(size));
+
+	unsafe { Viewport(0, 0, size.x, size.y); }
+	f();
+
+	let image = Image::read_from_gl(size);
+
+	match previous {
+		Some(backend) => { gl_dispatch::set_backend(backend); }
+		None => { gl_dispatch::take_backend(); }
+	}
+
+	image
+}
+
+/// Compares `image` against assets/golden/<name>.png. If BOAT_UPDATE_GOLDEN is
+/// set, `image` becomes the golden image instead. When the golden image is
+/// missing or doesn't match, the actual image is written next to it as
+/// <name>.actual.png
+pub fn check_golden(name: &str, image: &Image) -> Result<(), String> {
+	let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("assets").join("golden");
+	let golden_path = dir.join(format!("{}.png", name));
+	let actual_path = dir.join(format!("{}.actual.png", name));
+
+	let save = |path: &PathBuf| -> Result<(), String> {
+		fs::create_dir_all(&dir)
+			.and_then(|_| File::create(path))
+			.and_then(|mut f| f.write_all(&image.encode_png()))
+			.map_err(|e| format!("Couldn't write '{}': {}", path.display(), e))
+	};
+
+	if env::var("BOAT_UPDATE_GOLDEN").is_ok() {
+		return save(&golden_path)
+	}
+
+	if !golden_path.exists() {
+		save(&actual_path)?;
+
+		return Err(format!("'{}' has no golden image. Actual image written to '{}', \
+			run with BOAT_UPDATE_GOLDEN=1 to accept it", name, actual_path.display()))
+	}
+
+	let golden = Image::load_png(&golden_path)?;
+
+	let mismatch = match image.count_differences(&golden, GOLDEN_TOLERANCE) {
+		Some(0) => return Ok(()),
+		Some(pixels) => format!("{} pixels differ", pixels),
+		None => format!("size is {:?}, golden is {:?}", image.size, golden.size),
+	};
+
+	save(&actual_path)?;
+
+	Err(format!("'{}' doesn't match its golden image: {}. Actual image written to '{}'",
+		name, mismatch, actual_path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use boat::{build_boat_mesh, sail_mat};
+	use camera::Camera;
+	use ocean::{build_sea_mesh, WaveField};
+	use terrain::{TerrainChunk, CHUNK_RESOLUTION};
+	use rendering::Shader;
+	use rendering::mesh_builder::*;
+	use res::shaders::*;
+
+	/// Small, so the golden images stay small
+	fn golden_size() -> Vec2i { Vec2i::new(160, 120) }
+
+	/// Rolling waves that don't depend on the noise crate, so the golden image
+	/// only changes when the meshing or rendering does
+	struct TestWaves;
+
+	impl WaveField for TestWaves {
+		fn height_at(&self, pos: Vec2, phase: f32) -> f32 {
+			0.3 * (pos.x * 0.7 + phase).sin() * (pos.y * 0.4).cos()
+		}
+	}
+
+	/// Renders whatever `draw` draws the way the game does, looking at `focus`
+	/// from `distance` away, and checks it against the golden image `name`
+	fn check_scene<F>(name: &str, focus: Vec2, distance: f32, draw: F) where F: FnOnce(&Shader) {
+		let size = golden_size();
+
+		let image = render_to_image(size, || {
+			unsafe {
+				Enable(DEPTH_TEST);
+				Enable(BLEND);
+				BlendEquation(FUNC_ADD);
+				BlendFunc(SRC_ALPHA, ONE_MINUS_SRC_ALPHA);
+
+				ClearColor(0.1, 0.1, 0.1, 1.0);
+				Clear(COLOR_BUFFER_BIT | DEPTH_BUFFER_BIT);
+			}
+
+			let mut camera = Camera::new(PI/4.0, distance);
+			camera.set_viewport_size(size);
+			camera.update(focus, 0.0, 0.0);
+			camera.snap();
+
+			let shader = Shader::new(BASIC_VS, BASIC_FS).unwrap();
+			shader.set_proj(&(camera.proj_mat() * camera.view_mat()));
+			shader.set_view(&Mat4::ident());
+
+			draw(&shader);
+		});
+
+		if let Err(e) = check_golden(name, &image) {
+			panic!("{}", e);
+		}
+	}
+
+	fn draw(mb: MeshBuilder<ColorVertex>) {
+		let mesh: Mesh = mb.into();
+		mesh.bind();
+		mesh.draw(TRIANGLES);
+	}
+
+	#[test]
+	fn sea_golden() {
+		check_scene("sea", Vec2::zero(), 14.0, |_| {
+			let mut mb = MeshBuilder::new();
+			build_sea_mesh(&TestWaves, &mut mb, Vec2::zero(), 0.5);
+			draw(mb);
+		});
+	}
+
+	#[test]
+	fn boat_golden() {
+		check_scene("boat", Vec2::zero(), 4.0, |shader| {
+			let (mut hull, mut sail) = (MeshBuilder::new(), MeshBuilder::new());
+			build_boat_mesh(&mut hull, &mut sail);
+
+			let model = Mat4::yrot(0.6);
+
+			shader.set_view(&model);
+			draw(hull);
+
+			shader.set_view(&(model * sail_mat(0.5)));
+			draw(sail);
+		});
+	}
+
+	#[test]
+	fn terrain_golden() {
+		// A made up island rather than a generated one, so this doesn't depend
+		// on the noise crate either. It's tall enough to reach every band of colour
+		let stride = CHUNK_RESOLUTION + 1;
+		let centre = CHUNK_RESOLUTION as f32 / 2.0;
+		let mut heights = Vec::with_capacity(stride * stride);
+
+		for z in 0..stride {
+			for x in 0..stride {
+				let dist = Vec2::new(x as f32 - centre, z as f32 - centre).length() / centre;
+				heights.push(-0.6 + (1.0 - dist * dist).max(0.0) * 3.0);
+			}
+		}
+
+		let chunk = TerrainChunk { coord: (0, 0), heights };
+
+		check_scene("terrain", Vec2::splat(8.0), 22.0, |_| {
+			let mut mb = MeshBuilder::new();
+			chunk.build_mesh(&mut mb);
+			draw(mb);
+		});
+	}
+
+	/// basic.fs always writes an alpha of 1, so nothing the game draws is
+	/// translucent yet. A four wide colour attribute gets its alpha through
+	#[repr(C)]
+	#[derive(Copy, Clone)]
+	struct TranslucentVertex(Vec3, [f32; 4]);
+
+	impl Vertex for TranslucentVertex {
+		fn get_layout() -> VertexLayout {
+			VertexLayout::new::<Self>()
+				.add_binding(0, 3, 0)
+				.add_binding(1, 4, 12)
+		}
+	}
+
+	/// A full height strip of the screen between `left` and `right`, in clip space
+	fn strip(left: f32, right: f32, color: [f32; 4]) -> MeshBuilder<TranslucentVertex> {
+		let mut mb = MeshBuilder::new();
+
+		mb.add_quad(&[
+			TranslucentVertex(Vec3::new(left, -1.0, 0.0), color),
+			TranslucentVertex(Vec3::new(right, -1.0, 0.0), color),
+			TranslucentVertex(Vec3::new(right, 1.0, 0.0), color),
+			TranslucentVertex(Vec3::new(left, 1.0, 0.0), color),
+		]);
+
+		mb
+	}
+
+	#[test]
+	fn translucent_over_opaque() {
+		let image = render_to_image(Vec2i::new(8, 2), || {
+			unsafe {
+				Enable(BLEND);
+				BlendEquation(FUNC_ADD);
+				BlendFunc(SRC_ALPHA, ONE_MINUS_SRC_ALPHA);
+
+				ClearColor(0.0, 0.0, 1.0, 1.0);
+				Clear(COLOR_BUFFER_BIT);
+			}
+
+			// No proj or view set, so positions go straight through
+			let _shader = Shader::new(BASIC_VS, BASIC_FS).unwrap();
+
+			for mb in vec![strip(-1.0, 0.0, [1.0, 0.0, 0.0, 0.5]), strip(-0.5, 1.0, [0.0, 1.0, 0.0, 0.25])] {
+				let mesh: Mesh = mb.into();
+				mesh.bind();
+				mesh.draw(TRIANGLES);
+			}
+		});
+
+		// Half red over the blue, then a quarter green over that or the blue
+		let expected = [
+			(0, [128, 0, 128]),
+			(1, [128, 0, 128]),
+			(2, [96, 64, 96]),
+			(3, [96, 64, 96]),
+			(4, [0, 64, 191]),
+			(7, [0, 64, 191]),
+		];
+
+		for y in 0..2 {
+			for &(x, rgb) in expected.iter() {
+				let pixel = image.pixel(x, y);
+				let close = (0..3).all(|i| (pixel[i] as i32 - rgb[i]).abs() <= 1);
+				assert!(close, "pixel ({}, {}) is {:?}, expected {:?}", x, y, pixel, rgb);
+			}
+		}
+	}
+}
@@ -0,0 +1,346 @@
+#![allow(non_upper_case_globals)]
+
+// The windowed half of the native platform: an X11 window with a GLES2 context
+// from EGL, and its input turned into the same events the browser gives us.
+
+use std::cell::{Cell, RefCell};
+use std::ffi::CString;
+use std::ptr;
+
+use common::*;
+use bindings::xlib::{self, *};
+use bindings::egl::*;
+use bindings::gl;
+use bindings::gl_dispatch;
+use events::{Event, EventQueue, Key};
+use platform::gles_gl::GlesGl;
+
+thread_local! {
+	// Where window events go. Set by attach_queue and cleared when the queue
+	// is dropped, so nothing is pushed into a queue that no longer exists
+	static QUEUE: Cell<*const RefCell<Vec<Event>>> = Cell::new(ptr::null());
+}
+
+pub struct Window {
+	display: *mut Display,
+	window: xlib::Window,
+	delete_message: Atom,
+
+	egl_display: EGLDisplay,
+	config: EGLConfig,
+	surface: EGLSurface,
+	context: Cell<EGLContext>,
+
+	size: Cell<Vec2i>,
+	fullscreen: Cell<bool>,
+}
+
+impl Window {
+	pub fn open(size: Vec2i, title: &str) -> Result<Window, String> {
+		unsafe {
+			let display = XOpenDisplay(ptr::null());
+			if display.is_null() {
+				return Err("couldn't connect to an X server".to_string())
+			}
+
+			let root = XDefaultRootWindow(display);
+			let window = XCreateSimpleWindow(display, root, 0, 0, size.x as u32, size.y as u32, 0, 0, 0);
+
+			let title = CString::new(title).unwrap_or_default();
+			XStoreName(display, window, title.as_ptr());
+			XSelectInput(display, window, KeyPressMask | KeyReleaseMask | ButtonPressMask | ButtonReleaseMask
+				| PointerMotionMask | StructureNotifyMask);
+
+			// Otherwise the window manager just kills the connection when the window's closed
+			let mut delete_message = XInternAtom(display, b"WM_DELETE_WINDOW\0".as_ptr() as _, False);
+			XSetWMProtocols(display, window, &mut delete_message, 1);
+
+			// Held keys repeat presses like they do in the browser, without X's
+			// extra release before each one
+			XkbSetDetectableAutoRepeat(display, True, ptr::null_mut());
+			XMapWindow(display, window);
+
+			let mut win = Window {
+				display,
+				window,
+				delete_message,
+
+				egl_display: EGL_NO_DISPLAY,
+				config: ptr::null_mut(),
+				surface: EGL_NO_SURFACE,
+				context: Cell::new(EGL_NO_CONTEXT),
+
+				size: Cell::new(size),
+				fullscreen: Cell::new(false),
+			};
+
+			// Dropping win on failure closes the window and the display again
+			win.init_egl()?;
+			Ok(win)
+		}
+	}
+
+	unsafe fn init_egl(&mut self) -> Result<(), String> {
+		self.egl_display = eglGetDisplay(self.display);
+		if self.egl_display == EGL_NO_DISPLAY || eglInitialize(self.egl_display, ptr::null_mut(), ptr::null_mut()) == EGL_FALSE {
+			return Err(format!("couldn't initialise EGL (0x{:x})", eglGetError()))
+		}
+
+		eglBindAPI(EGL_OPENGL_ES_API);
+
+		// Close to what WebGLContext asks the browser for
+		let attribs = [
+			EGL_SURFACE_TYPE, EGL_WINDOW_BIT,
+			EGL_RENDERABLE_TYPE, EGL_OPENGL_ES2_BIT,
+			EGL_RED_SIZE, 8,
+			EGL_GREEN_SIZE, 8,
+			EGL_BLUE_SIZE, 8,
+			EGL_ALPHA_SIZE, 8,
+			EGL_DEPTH_SIZE, 16,
+			EGL_NONE,
+		];
+
+		let mut count = 0;
+		if eglChooseConfig(self.egl_display, attribs.as_ptr(), &mut self.config, 1, &mut count) == EGL_FALSE || count < 1 {
+			return Err("no EGL config supports GLES2 on a window".to_string())
+		}
+
+		self.surface = eglCreateWindowSurface(self.egl_display, self.config, self.window, ptr::null());
+		if self.surface == EGL_NO_SURFACE {
+			return Err(format!("couldn't create a window surface (0x{:x})", eglGetError()))
+		}
+
+		Ok(())
+	}
+
+	pub fn size(&self) -> Vec2i { self.size.get() }
+
+	/// Creates the GLES2 context the first time it's called, makes it current
+	/// and routes gl:: calls to it
+	pub fn make_context_current(&self) {
+		unsafe {
+			if self.context.get() == EGL_NO_CONTEXT {
+				let attribs = [EGL_CONTEXT_CLIENT_VERSION, 2, EGL_NONE];
+				let context = eglCreateContext(self.egl_display, self.config, EGL_NO_CONTEXT, attribs.as_ptr());
+				if context == EGL_NO_CONTEXT {
+					panic!("GLES2 context creation failed (0x{:x})", eglGetError());
+				}
+
+				self.context.set(context);
+			}
+
+			if eglMakeCurrent(self.egl_display, self.surface, self.surface, self.context.get()) == EGL_FALSE {
+				panic!("Failed to make GLES2 context current (0x{:x})", eglGetError());
+			}
+
+			// Wait for vsync, like requestAnimationFrame does
+			eglSwapInterval(self.egl_display, 1);
+
+			gl_dispatch::set_backend(box GlesGl::new());
+			gl::FrontFace(gl::CCW);
+		}
+	}
+
+	pub fn swap_buffers(&self) {
+		if self.context.get() != EGL_NO_CONTEXT {
+			unsafe { eglSwapBuffers(self.egl_display, self.surface); }
+		}
+	}
+
+	/// Sends events from this window to `queue` until it's dropped. Only one
+	/// queue can be attached at a time
+	pub fn attach_queue(&self, queue: &mut EventQueue) {
+		QUEUE.with(|q| {
+			assert!(q.get().is_null(), "Only one event queue can be live at a time");
+			q.set(queue.storage_ptr());
+		});
+
+		queue.push(Event::Resize(self.size()));
+		queue.set_teardown(detach_queue);
+	}
+
+	/// Handles everything that's happened since the last call. Returns false
+	/// once the window has been closed
+	pub fn pump_events(&self) -> bool {
+		let mut open = true;
+
+		unsafe {
+			while XPending(self.display) > 0 {
+				let mut event = XEvent { pad: [0; 24] };
+				XNextEvent(self.display, &mut event);
+
+				match self.translate(&mut event) {
+					Some(e) => push_event(e),
+					None => if event.kind() == ClientMessage {
+						let message: &XClientMessageEvent = event.cast();
+						open &= message.data[0] as Atom != self.delete_message;
+					},
+				}
+			}
+		}
+
+		open
+	}
+
+	unsafe fn translate(&self, event: &mut XEvent) -> Option<Event> {
+		match event.kind() {
+			ConfigureNotify => {
+				let e: &XConfigureEvent = event.cast();
+				let (size, old) = (Vec2i::new(e.width, e.height), self.size.get());
+				if (size.x, size.y) == (old.x, old.y) { return None }
+
+				self.size.set(size);
+				Some(Event::Resize(size))
+			}
+
+			ButtonPress | ButtonRelease => {
+				let e: &XButtonEvent = event.cast();
+				let pos = Vec2i::new(e.x, e.y);
+
+				match (e.kind == ButtonPress, e.button) {
+					(true, Button1) => Some(Event::Down(pos)),
+					(false, Button1) => Some(Event::Up(pos)),
+					(true, Button4) => Some(Event::Wheel(-1.0)),
+					(true, Button5) => Some(Event::Wheel(1.0)),
+					_ => None,
+				}
+			}
+
+			MotionNotify => {
+				let e: &XMotionEvent = event.cast();
+				Some(Event::Move(Vec2i::new(e.x, e.y)))
+			}
+
+			KeyPress | KeyRelease => {
+				let kind = event.kind();
+				let e = &mut *(event as *mut XEvent as *mut XKeyEvent);
+
+				// Leave shortcuts to the desktop, as the browser does
+				if kind == KeyPress && e.state & (ControlMask | Mod1Mask | Mod4Mask) != 0 {
+					return None
+				}
+
+				let key = key_for_keysym(XLookupKeysym(e, 0));
+				if kind == KeyPress { key.map(Event::KeyDown) } else { key.map(Event::KeyUp) }
+			}
+
+			_ => None,
+		}
+	}
+
+	/// Asks the window manager to make the window fullscreen, if it isn't already
+	pub fn request_fullscreen(&self) {
+		if self.fullscreen.get() { return }
+
+		unsafe {
+			let state = XInternAtom(self.display, b"_NET_WM_STATE\0".as_ptr() as _, False);
+			let fullscreen = XInternAtom(self.display, b"_NET_WM_STATE_FULLSCREEN\0".as_ptr() as _, False);
+
+			let mut event = XEvent { pad: [0; 24] };
+			{
+				let message = &mut *(&mut event as *mut XEvent as *mut XClientMessageEvent);
+				message.kind = ClientMessage;
+				message.window = self.window;
+				message.message_type = state;
+				message.format = 32;
+				message.data = [1, fullscreen as _, 0, 1, 0];
+			}
+
+			XSendEvent(self.display, XDefaultRootWindow(self.display), False,
+				SubstructureRedirectMask | SubstructureNotifyMask, &mut event);
+			XFlush(self.display);
+		}
+
+		self.fullscreen.set(true);
+	}
+}
+
+impl Drop for Window {
+	fn drop(&mut self) {
+		unsafe {
+			if self.egl_display != EGL_NO_DISPLAY {
+				eglMakeCurrent(self.egl_display, EGL_NO_SURFACE, EGL_NO_SURFACE, EGL_NO_CONTEXT);
+
+				if self.context.get() != EGL_NO_CONTEXT {
+					eglDestroyContext(self.egl_display, self.context.get());
+				}
+
+				if self.surface != EGL_NO_SURFACE {
+					eglDestroySurface(self.egl_display, self.surface);
+				}
+
+				eglTerminate(self.egl_display);
+			}
+
+			XDestroyWindow(self.display, self.window);
+			XCloseDisplay(self.display);
+		}
+	}
+}
+
+fn push_event(event: Event) {
+	QUEUE.with(|q| {
+		let queue = q.get();
+		if !queue.is_null() {
+			unsafe { (*queue).borrow_mut().push(event) }
+		}
+	})
+}
+
+fn detach_queue() {
+	QUEUE.with(|q| q.set(ptr::null()));
+}
+
+/// The DOM code for a key, so the same input config works on both platforms.
+/// `sym` is the unshifted keysym, so letters are always lower case
+pub fn key_for_keysym(sym: KeySym) -> Option<Key> {
+	let code = match sym {
+		XK_a ..= XK_z => format!("Key{}", (sym - XK_a + b'A' as KeySym) as u8 as char),
+		XK_0 ..= XK_9 => format!("Digit{}", sym - XK_0),
+
+		XK_Left => "ArrowLeft".to_string(),
+		XK_Right => "ArrowRight".to_string(),
+		XK_Up => "ArrowUp".to_string(),
+		XK_Down => "ArrowDown".to_string(),
+		XK_space => "Space".to_string(),
+		XK_Return => "Enter".to_string(),
+		XK_Escape => "Escape".to_string(),
+		XK_Tab => "Tab".to_string(),
+		XK_BackSpace => "Backspace".to_string(),
+		XK_Shift_L => "ShiftLeft".to_string(),
+		XK_Shift_R => "ShiftRight".to_string(),
+		XK_Control_L => "ControlLeft".to_string(),
+		XK_Control_R => "ControlRight".to_string(),
+		XK_Alt_L => "AltLeft".to_string(),
+		XK_Alt_R => "AltRight".to_string(),
+
+		_ => return None,
+	};
+
+	Key::from_code(&code)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn code(sym: KeySym) -> Option<String> {
+		key_for_keysym(sym).map(|k| k.code().to_string())
+	}
+
+	#[test]
+	fn keysyms_map_to_dom_codes() {
+		assert_eq!(code(XK_a), Some("KeyA".to_string()));
+		assert_eq!(code(XK_z), Some("KeyZ".to_string()));
+		assert_eq!(code(XK_0 + 7), Some("Digit7".to_string()));
+		assert_eq!(code(XK_Left), Some("ArrowLeft".to_string()));
+		assert_eq!(code(XK_space), Some("Space".to_string()));
+	}
+
+	#[test]
+	fn unknown_keysyms_are_ignored() {
+		// F1, and an upper case A that XLookupKeysym(_, 0) never gives
+		assert_eq!(code(0xffbe), None);
+		assert_eq!(code(0x41), None);
+	}
+}
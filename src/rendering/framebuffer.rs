@@ -2,12 +2,13 @@
 
 use math::*;
 use rendering::gl;
+use rendering::leaks::{self, GlResource};
 
 use rendering::texture::*;
 
 pub struct Framebuffer {
 	gl_handle: u32,
-	targets: Vec<Texture>,
+	targets: Vec<SharedTexture>,
 	depth_target: Option<Texture>,
 	size: Vec2i,
 }
@@ -25,10 +26,10 @@ impl Framebuffer {
 		}
 	}
 
-	pub fn get_target(&mut self, id: usize) -> Option<&mut Texture> {
-		if id >= self.targets.len() { return None }
-
-		Some(&mut self.targets[id])
+	/// A handle to a colour target, which keeps the texture alive for as
+	/// long as it's needed even if the framebuffer goes first
+	pub fn get_target(&self, id: usize) -> Option<SharedTexture> {
+		self.targets.get(id).cloned()
 	}
 
 	pub fn get_depth(&mut self) -> Option<&mut Texture> {
@@ -39,7 +40,7 @@ impl Framebuffer {
 		if self.size == nsize { return }
 
 		unsafe {
-			for tex in self.targets.iter_mut() {
+			for tex in self.targets.iter() {
 				let _guard = tex.bind_guard();
 
 				gl::TexImage2D(gl::TEXTURE_2D, 0, gl::RGBA as i32, nsize.x, nsize.y, 0, 
@@ -58,6 +59,18 @@ impl Framebuffer {
 	}
 }
 
+impl Drop for Framebuffer {
+	fn drop(&mut self) {
+		unsafe {
+			gl::DeleteFramebuffers(1, &self.gl_handle);
+		}
+
+		leaks::destroyed(GlResource::Framebuffer, self.gl_handle);
+
+		// The targets delete themselves after this, unless they're shared
+	}
+}
+
 pub struct FramebufferBuilder {
 	fb: Framebuffer,
 }
@@ -74,6 +87,7 @@ impl FramebufferBuilder {
 			fb.bind();
 		}
 
+		leaks::created(GlResource::Framebuffer, fb.gl_handle);
 		FramebufferBuilder { fb }
 	}
 
@@ -89,6 +103,7 @@ impl FramebufferBuilder {
 			fb.bind();
 		}
 
+		leaks::created(GlResource::Framebuffer, fb.gl_handle);
 		FramebufferBuilder { fb }
 	}
 
@@ -119,7 +134,7 @@ impl FramebufferBuilder {
 				gl::TEXTURE_2D, gl_handle, 0);
 		}
 
-		self.fb.depth_target = Some(Texture{gl_handle, size: self.fb.size});
+		self.fb.depth_target = Some(Texture::from_handle(gl_handle, self.fb.size));
 
 		self
 	}
@@ -144,7 +159,7 @@ impl FramebufferBuilder {
 			gl::FramebufferTexture2D(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0 + next_target, gl::TEXTURE_2D, gl_handle, 0);
 		}
 
-		self.fb.targets.push(Texture{gl_handle, size: self.fb.size});
+		self.fb.targets.push(SharedTexture::new(Texture::from_handle(gl_handle, self.fb.size)));
 
 		self
 	}
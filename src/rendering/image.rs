@@ -0,0 +1,73 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use common::*;
+use rendering::gl;
+use rendering::png;
+
+/// An RGBA8 image, top row first
+#[derive(Clone, Debug)]
+pub struct Image {
+	pub size: Vec2i,
+	pub pixels: Vec<u8>,
+}
+
+impl Image {
+	pub fn new(size: Vec2i) -> Self {
+		Image { size, pixels: vec![0; (size.x * size.y * 4) as usize] }
+	}
+
+	/// Reads back the bottom left `size` pixels of the bound framebuffer
+	pub fn read_from_gl(size: Vec2i) -> Self {
+		let mut image = Image::new(size);
+
+		unsafe {
+			gl::ReadPixels(0, 0, size.x, size.y, gl::RGBA, gl::UNSIGNED_BYTE, image.pixels.as_mut_ptr() as _);
+		}
+
+		// GL hands back the bottom row first
+		let row = (size.x * 4) as usize;
+		let flipped = image.pixels.chunks(row).rev()
+			.flat_map(|r| r.iter().cloned())
+			.collect();
+
+		image.pixels = flipped;
+		image
+	}
+
+	pub fn pixel(&self, x: i32, y: i32) -> [u8; 4] {
+		let i = ((y * self.size.x + x) * 4) as usize;
+		[self.pixels[i], self.pixels[i+1], self.pixels[i+2], self.pixels[i+3]]
+	}
+
+	pub fn encode_png(&self) -> Vec<u8> {
+		png::encode(self.size, &self.pixels)
+	}
+
+	pub fn decode_png(data: &[u8]) -> Result<Image, String> {
+		png::decode(data).map(|(size, pixels)| Image { size, pixels })
+	}
+
+	pub fn load_png<P>(path: P) -> Result<Image, String> where P: AsRef<Path> {
+		let path = path.as_ref();
+		let mut data = Vec::new();
+
+		File::open(path)
+			.and_then(|mut f| f.read_to_end(&mut data))
+			.map_err(|e| format!("Couldn't read '{}': {}", path.display(), e))?;
+
+		Image::decode_png(&data).map_err(|e| format!("Couldn't decode '{}': {}", path.display(), e))
+	}
+
+	/// How many pixels have a channel more than `tolerance` away from the same
+	/// pixel in `other`, or None if they aren't the same size
+	pub fn count_differences(&self, other: &Image, tolerance: u8) -> Option<usize> {
+		if self.size != other.size { return None }
+
+		let differs = |(a, b): (&[u8], &[u8])| a.iter().zip(b.iter())
+			.any(|(&a, &b)| (a as i32 - b as i32).abs() > tolerance as i32);
+
+		Some(self.pixels.chunks(4).zip(other.pixels.chunks(4)).filter(|&p| differs(p)).count())
+	}
+}
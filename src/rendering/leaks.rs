@@ -0,0 +1,61 @@
+// Debug builds keep a tally of every GL object the rendering types own, so
+// anything that's never dropped can be reported when the game shuts down. In
+// release builds this all compiles away to nothing.
+
+pub use self::tally::*;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum GlResource {
+	Buffer,
+	Texture,
+	Framebuffer,
+	Program,
+}
+
+#[cfg(debug)]
+mod tally {
+	use std::cell::RefCell;
+	use std::collections::HashSet;
+	use super::GlResource;
+
+	thread_local! {
+		static LIVE: RefCell<HashSet<(GlResource, u32)>> = RefCell::new(HashSet::new());
+	}
+
+	pub fn created(kind: GlResource, gl_handle: u32) {
+		LIVE.with(|live| { live.borrow_mut().insert((kind, gl_handle)); });
+	}
+
+	pub fn destroyed(kind: GlResource, gl_handle: u32) {
+		LIVE.with(|live| { live.borrow_mut().remove(&(kind, gl_handle)); });
+	}
+
+	/// How many objects of `kind` are currently alive
+	pub fn live_count(kind: GlResource) -> usize {
+		LIVE.with(|live| live.borrow().iter().filter(|&&(k, _)| k == kind).count())
+	}
+
+	/// Prints every GL object that's still alive, and returns how many there were
+	pub fn report() -> usize {
+		LIVE.with(|live| {
+			let mut leaked: Vec<_> = live.borrow().iter().cloned().collect();
+			leaked.sort_by_key(|&(kind, handle)| (kind as u32, handle));
+
+			for &(kind, handle) in leaked.iter() {
+				println!("Leaked {:?} {}", kind, handle);
+			}
+
+			leaked.len()
+		})
+	}
+}
+
+#[cfg(not(debug))]
+mod tally {
+	use super::GlResource;
+
+	pub fn created(_: GlResource, _: u32) {}
+	pub fn destroyed(_: GlResource, _: u32) {}
+	pub fn live_count(_: GlResource) -> usize { 0 }
+	pub fn report() -> usize { 0 }
+}
@@ -3,6 +3,7 @@
 use std::mem::size_of;
 use std::ptr::null;
 use rendering::gl;
+use rendering::leaks::{self, GlResource};
 use rendering::types::*;
 
 pub struct VertexAttributeBinding {
@@ -58,25 +59,45 @@ impl Vertex for DefaultVertex {
 	}
 }
 
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct ColorVertex(pub Vec3, pub Vec3);
 
+impl Vertex for ColorVertex {
+	fn get_layout() -> VertexLayout {
+		VertexLayout::new::<Self>()
+			.add_binding(0, 3, 0)
+			.add_binding(1, 3, 12)
+	}
+}
 
+
+
+/// Owns its vertex and index buffers, and deletes them when dropped
 pub struct Mesh {
-	pub vbo: u32,
-	pub ebo: u32,
+	vbo: u32,
+	ebo: u32,
 	pub count: u32,
 	pub layout: VertexLayout,
 }
 
 impl Mesh {
 	pub fn new() -> Self {
+		let (vbo, ebo) = (gl::pls_make_buffer(), gl::pls_make_buffer());
+		leaks::created(GlResource::Buffer, vbo);
+		leaks::created(GlResource::Buffer, ebo);
+
 		Mesh {
-			vbo: gl::pls_make_buffer(),
-			ebo: gl::pls_make_buffer(),
+			vbo,
+			ebo,
 			count: 0,
 			layout: VertexLayout::null(),
 		}
 	}
 
+	pub fn vbo(&self) -> u32 { self.vbo }
+	pub fn ebo(&self) -> u32 { self.ebo }
+
 	pub fn bind(&self) {
 		unsafe {
 			gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.ebo);
@@ -94,6 +115,19 @@ impl Mesh {
 			gl::DrawElements(mode, self.count as _, gl::UNSIGNED_SHORT, null());
 		}
 	}
+
+}
+
+impl Drop for Mesh {
+	fn drop(&mut self) {
+		unsafe {
+			gl::DeleteBuffers(1, &self.vbo);
+			gl::DeleteBuffers(1, &self.ebo);
+		}
+
+		leaks::destroyed(GlResource::Buffer, self.vbo);
+		leaks::destroyed(GlResource::Buffer, self.ebo);
+	}
 }
 
 
@@ -178,6 +212,23 @@ impl<V> MeshBuilder<V> where V: Vertex {
 	}
 }
 
+impl MeshBuilder<ColorVertex> {
+	/// Adds a triangle with its own vertices, tinted by how much it faces
+	/// the light. Gives everything the same flat shaded look
+	pub fn add_shaded_tri(&mut self, a: Vec3, b: Vec3, c: Vec3, base: Vec3) {
+		let light = Vec3::new(0.3, 1.0, 0.5).normalize();
+
+		let normal = (b - a).cross(c - a).normalize();
+		let color = base * (0.85 + 0.15 * normal.dot(light));
+
+		self.add_direct(&[
+			ColorVertex(a, color),
+			ColorVertex(b, color),
+			ColorVertex(c, color),
+		], &[0, 1, 2]);
+	}
+}
+
 impl<V> From<MeshBuilder<V>> for Mesh where V: Vertex {
 	fn from(mb: MeshBuilder<V>) -> Self {
 		let mut mesh = Mesh::new();
@@ -17,6 +17,9 @@ pub mod types;
 pub mod shader;
 pub mod texture;
 pub mod framebuffer;
+#[cfg(test)] pub mod image;
+pub mod leaks;
+#[cfg(test)] pub mod png;
 
 pub mod mesh_builder;
 
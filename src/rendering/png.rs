@@ -0,0 +1,432 @@
+// Just enough PNG to save and load 8 bit RGBA images. Encoding doesn't bother
+// compressing, decoding handles anything a normal image editor writes except
+// palettes, 16 bit channels and interlacing.
+
+use common::*;
+
+const SIGNATURE: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
+
+/// The largest a stored deflate block can be
+const MAX_STORED_BLOCK: usize = 65535;
+
+pub fn encode(size: Vec2i, rgba: &[u8]) -> Vec<u8> {
+	let row = (size.x * 4) as usize;
+
+	// Every row starts with its filter type, which is always 0 (none) here
+	let mut raw = Vec::with_capacity((row + 1) * size.y as usize);
+	for r in rgba.chunks(row) {
+		raw.push(0);
+		raw.extend_from_slice(r);
+	}
+
+	let mut zlib = vec![0x78, 0x01];
+	let block_count = (raw.len() + MAX_STORED_BLOCK - 1) / MAX_STORED_BLOCK;
+
+	for (i, block) in raw.chunks(MAX_STORED_BLOCK).enumerate() {
+		let len = block.len() as u16;
+		zlib.push(if i + 1 == block_count { 1 } else { 0 });
+		zlib.extend_from_slice(&[len as u8, (len >> 8) as u8, !len as u8, (!len >> 8) as u8]);
+		zlib.extend_from_slice(block);
+	}
+
+	if raw.is_empty() {
+		zlib.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+	}
+
+	push_u32(&mut zlib, adler32(&raw));
+
+	let mut header = Vec::new();
+	push_u32(&mut header, size.x as u32);
+	push_u32(&mut header, size.y as u32);
+	header.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+	let mut png = SIGNATURE.to_vec();
+	push_chunk(&mut png, b"IHDR", &header);
+	push_chunk(&mut png, b"IDAT", &zlib);
+	push_chunk(&mut png, b"IEND", &[]);
+	png
+}
+
+/// Decodes a PNG into its size and RGBA pixels, top row first
+pub fn decode(data: &[u8]) -> Result<(Vec2i, Vec<u8>), String> {
+	if data.len() < 8 || data[..8] != SIGNATURE {
+		return Err("not a PNG".to_owned())
+	}
+
+	let mut header = None;
+	let mut zlib = Vec::new();
+	let mut pos = 8;
+
+	while pos + 8 <= data.len() {
+		let len = read_u32(data, pos) as usize;
+		let kind = &data[pos+4 .. pos+8];
+
+		if pos + 12 + len > data.len() {
+			return Err("truncated chunk".to_owned())
+		}
+
+		let body = &data[pos+8 .. pos+8+len];
+		pos += 12 + len;
+
+		match kind {
+			b"IHDR" => header = Some(body.to_vec()),
+			b"IDAT" => zlib.extend_from_slice(body),
+			b"IEND" => break,
+			_ => {}
+		}
+	}
+
+	let header = match header {
+		Some(ref h) if h.len() == 13 => h,
+		_ => return Err("missing header".to_owned()),
+	};
+
+	let size = Vec2i::new(read_u32(header, 0) as i32, read_u32(header, 4) as i32);
+	let (depth, color_type, interlace) = (header[8], header[9], header[12]);
+
+	let channels = match color_type {
+		0 => 1, // Grey
+		2 => 3, // RGB
+		4 => 2, // Grey + alpha
+		6 => 4, // RGBA
+		_ => return Err(format!("unsupported colour type {}", color_type)),
+	};
+
+	if depth != 8 { return Err(format!("unsupported bit depth {}", depth)) }
+	if interlace != 0 { return Err("interlaced images aren't supported".to_owned()) }
+	if zlib.len() < 2 { return Err("missing image data".to_owned()) }
+
+	let raw = inflate(&zlib[2..])?;
+	let pixels = unfilter(&raw, size, channels)?;
+
+	let rgba = pixels.chunks(channels).flat_map(|p| {
+		let rgba = match channels {
+			1 => [p[0], p[0], p[0], 255],
+			2 => [p[0], p[0], p[0], p[1]],
+			3 => [p[0], p[1], p[2], 255],
+			_ => [p[0], p[1], p[2], p[3]],
+		};
+		rgba.to_vec()
+	}).collect();
+
+	Ok((size, rgba))
+}
+
+fn unfilter(raw: &[u8], size: Vec2i, channels: usize) -> Result<Vec<u8>, String> {
+	let row = size.x as usize * channels;
+	let rows = size.y as usize;
+
+	if raw.len() < (row + 1) * rows {
+		return Err("not enough image data".to_owned())
+	}
+
+	let mut out = vec![0u8; row * rows];
+
+	for y in 0..rows {
+		let filter = raw[y * (row + 1)];
+		let line = &raw[y * (row + 1) + 1 .. (y + 1) * (row + 1)];
+
+		for x in 0..row {
+			let a = if x >= channels { out[y*row + x - channels] } else { 0 };
+			let b = if y > 0 { out[(y-1)*row + x] } else { 0 };
+			let c = if x >= channels && y > 0 { out[(y-1)*row + x - channels] } else { 0 };
+
+			let predicted = match filter {
+				0 => 0,
+				1 => a,
+				2 => b,
+				3 => ((a as u16 + b as u16) / 2) as u8,
+				4 => paeth(a, b, c),
+				_ => return Err(format!("unknown filter type {}", filter)),
+			};
+
+			out[y*row + x] = line[x].wrapping_add(predicted);
+		}
+	}
+
+	Ok(out)
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+	let p = a as i16 + b as i16 - c as i16;
+	let (pa, pb, pc) = ((p - a as i16).abs(), (p - b as i16).abs(), (p - c as i16).abs());
+
+	if pa <= pb && pa <= pc { a }
+	else if pb <= pc { b }
+	else { c }
+}
+
+struct BitReader<'a> {
+	data: &'a [u8],
+	pos: usize,
+	bit: u32,
+}
+
+impl<'a> BitReader<'a> {
+	fn bits(&mut self, count: u32) -> Result<u32, String> {
+		let mut value = 0;
+
+		for i in 0..count {
+			let byte = match self.data.get(self.pos) {
+				Some(&byte) => byte,
+				None => return Err("image data ends early".to_owned()),
+			};
+
+			value |= ((byte as u32 >> self.bit) & 1) << i;
+
+			self.bit += 1;
+			if self.bit == 8 {
+				self.bit = 0;
+				self.pos += 1;
+			}
+		}
+
+		Ok(value)
+	}
+
+	fn align_to_byte(&mut self) {
+		if self.bit != 0 {
+			self.bit = 0;
+			self.pos += 1;
+		}
+	}
+}
+
+/// A canonical Huffman code, stored as how many codes there are of each
+/// length and the symbols in code order
+struct Huffman {
+	counts: [u16; 16],
+	symbols: Vec<u16>,
+}
+
+impl Huffman {
+	fn new(lengths: &[u8]) -> Self {
+		let mut counts = [0u16; 16];
+		for &len in lengths.iter() {
+			counts[len as usize] += 1;
+		}
+		counts[0] = 0;
+
+		let mut offsets = [0u16; 16];
+		for len in 1..15 {
+			offsets[len + 1] = offsets[len] + counts[len];
+		}
+
+		let mut symbols = vec![0; lengths.len()];
+		for (symbol, &len) in lengths.iter().enumerate() {
+			if len != 0 {
+				symbols[offsets[len as usize] as usize] = symbol as u16;
+				offsets[len as usize] += 1;
+			}
+		}
+
+		Huffman { counts, symbols }
+	}
+
+	fn decode(&self, bits: &mut BitReader) -> Result<u16, String> {
+		let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+
+		for len in 1..16 {
+			code |= bits.bits(1)? as i32;
+			let count = self.counts[len] as i32;
+
+			if code - first < count {
+				return Ok(self.symbols[(index + code - first) as usize])
+			}
+
+			index += count;
+			first = (first + count) << 1;
+			code <<= 1;
+		}
+
+		Err("bad Huffman code".to_owned())
+	}
+}
+
+const LENGTH_BASE: [u16; 29] = [3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31,
+	35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258];
+const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2,
+	3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+const DIST_BASE: [u16; 30] = [1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193,
+	257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577];
+const DIST_EXTRA: [u8; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6,
+	7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+
+/// The order code length code lengths come in, in a dynamic block header
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+/// Decompresses a raw deflate stream
+fn inflate(data: &[u8]) -> Result<Vec<u8>, String> {
+	let mut bits = BitReader { data, pos: 0, bit: 0 };
+	let mut out = Vec::new();
+
+	loop {
+		let last = bits.bits(1)? == 1;
+
+		match bits.bits(2)? {
+			0 => {
+				bits.align_to_byte();
+				let len = bits.bits(16)? as usize;
+				let _nlen = bits.bits(16)?;
+
+				if bits.pos + len > data.len() {
+					return Err("stored block runs past the end".to_owned())
+				}
+
+				out.extend_from_slice(&data[bits.pos .. bits.pos + len]);
+				bits.pos += len;
+			}
+
+			1 => {
+				let mut lengths = [0u8; 288];
+				for (i, l) in lengths.iter_mut().enumerate() {
+					*l = match i {
+						0...143 => 8,
+						144...255 => 9,
+						256...279 => 7,
+						_ => 8,
+					};
+				}
+
+				let literals = Huffman::new(&lengths);
+				let distances = Huffman::new(&[5; 30]);
+				inflate_block(&mut bits, &literals, &distances, &mut out)?;
+			}
+
+			2 => {
+				let (literals, distances) = read_dynamic_codes(&mut bits)?;
+				inflate_block(&mut bits, &literals, &distances, &mut out)?;
+			}
+
+			_ => return Err("bad block type".to_owned()),
+		}
+
+		if last { break }
+	}
+
+	Ok(out)
+}
+
+fn read_dynamic_codes(bits: &mut BitReader) -> Result<(Huffman, Huffman), String> {
+	let literal_count = bits.bits(5)? as usize + 257;
+	let distance_count = bits.bits(5)? as usize + 1;
+	let code_length_count = bits.bits(4)? as usize + 4;
+
+	let mut code_lengths = [0u8; 19];
+	for &i in CODE_LENGTH_ORDER.iter().take(code_length_count) {
+		code_lengths[i] = bits.bits(3)? as u8;
+	}
+
+	let code_lengths = Huffman::new(&code_lengths);
+
+	let total = literal_count + distance_count;
+	let mut lengths = Vec::with_capacity(total);
+
+	while lengths.len() < total {
+		let (value, repeat) = match code_lengths.decode(bits)? {
+			symbol @ 0...15 => (symbol as u8, 1),
+
+			16 => match lengths.last() {
+				Some(&prev) => (prev, 3 + bits.bits(2)?),
+				None => return Err("repeated length with nothing before it".to_owned()),
+			},
+
+			17 => (0, 3 + bits.bits(3)?),
+			_ => (0, 11 + bits.bits(7)?),
+		};
+
+		for _ in 0..repeat {
+			lengths.push(value);
+		}
+	}
+
+	if lengths.len() > total {
+		return Err("code lengths overrun".to_owned())
+	}
+
+	Ok((Huffman::new(&lengths[..literal_count]), Huffman::new(&lengths[literal_count..])))
+}
+
+fn inflate_block(bits: &mut BitReader, literals: &Huffman, distances: &Huffman, out: &mut Vec<u8>) -> Result<(), String> {
+	loop {
+		let symbol = literals.decode(bits)? as usize;
+
+		if symbol < 256 {
+			out.push(symbol as u8);
+			continue
+		}
+
+		if symbol == 256 { return Ok(()) }
+
+		let symbol = symbol - 257;
+		if symbol >= LENGTH_BASE.len() {
+			return Err("bad length code".to_owned())
+		}
+
+		let len = LENGTH_BASE[symbol] as usize + bits.bits(LENGTH_EXTRA[symbol] as u32)? as usize;
+
+		let symbol = distances.decode(bits)? as usize;
+		if symbol >= DIST_BASE.len() {
+			return Err("bad distance code".to_owned())
+		}
+
+		let dist = DIST_BASE[symbol] as usize + bits.bits(DIST_EXTRA[symbol] as u32)? as usize;
+		if dist > out.len() {
+			return Err("distance reaches back too far".to_owned())
+		}
+
+		// Copies can overlap what they're copying, so go a byte at a time
+		let start = out.len() - dist;
+		for i in 0..len {
+			let byte = out[start + i];
+			out.push(byte);
+		}
+	}
+}
+
+fn read_u32(data: &[u8], at: usize) -> u32 {
+	(data[at] as u32) << 24 | (data[at+1] as u32) << 16 | (data[at+2] as u32) << 8 | data[at+3] as u32
+}
+
+fn push_u32(out: &mut Vec<u8>, v: u32) {
+	out.extend_from_slice(&[(v >> 24) as u8, (v >> 16) as u8, (v >> 8) as u8, v as u8]);
+}
+
+fn push_chunk(out: &mut Vec<u8>, kind: &[u8], body: &[u8]) {
+	push_u32(out, body.len() as u32);
+
+	let start = out.len();
+	out.extend_from_slice(kind);
+	out.extend_from_slice(body);
+
+	let crc = crc32(&out[start..]);
+	push_u32(out, crc);
+}
+
+fn crc32(data: &[u8]) -> u32 {
+	let mut table = [0u32; 256];
+	for (n, entry) in table.iter_mut().enumerate() {
+		let mut c = n as u32;
+		for _ in 0..8 {
+			c = if c & 1 != 0 { 0xedb8_8320 ^ (c >> 1) } else { c >> 1 };
+		}
+		*entry = c;
+	}
+
+	let mut crc = 0xffff_ffffu32;
+	for &byte in data.iter() {
+		crc = table[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8);
+	}
+
+	crc ^ 0xffff_ffff
+}
+
+fn adler32(data: &[u8]) -> u32 {
+	let (mut a, mut b) = (1u32, 0u32);
+	for &byte in data.iter() {
+		a = (a + byte as u32) % 65521;
+		b = (b + a) % 65521;
+	}
+
+	b << 16 | a
+}
@@ -1,64 +1,138 @@
 #![allow(dead_code)]
 
 use std;
+use std::error::Error;
+use std::fmt;
 use math::*;
 use rendering::gl;
+use rendering::leaks::{self, GlResource};
 
-#[derive(Copy, Clone)]
+/// Owns a linked GL program, and deletes it when dropped
 pub struct Shader {
-	pub gl_handle: u32,
+	gl_handle: u32,
 
 	pub proj_loc: i32,
 	pub view_loc: i32,
 }
 
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ShaderStage {
+	Vertex,
+	Fragment,
+	Link,
+}
+
+/// Why a shader failed to build. `context` quotes the source around every line
+/// the log complains about
+#[derive(Clone, Debug)]
+pub struct ShaderError {
+	pub stage: ShaderStage,
+	pub log: String,
+	pub context: String,
+}
+
+impl fmt::Display for ShaderError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let what = match self.stage {
+			ShaderStage::Vertex => "Vertex shader failed to compile",
+			ShaderStage::Fragment => "Fragment shader failed to compile",
+			ShaderStage::Link => "Shader program failed to link",
+		};
+
+		write!(f, "{}:\n{}", what, self.log.trim_right())?;
+
+		if !self.context.is_empty() {
+			write!(f, "\n\n{}", self.context)?;
+		}
+
+		Ok(())
+	}
+}
+
+impl Error for ShaderError {
+	fn description(&self) -> &str { "shader build failed" }
+}
+
 impl Shader {
-	pub fn new(vertex_shader_src: &str, fragment_shader_src: &str) -> Shader {
-		use std::ffi::{CStr, CString};
+	pub fn new(vertex_shader_src: &str, fragment_shader_src: &str) -> Result<Shader, ShaderError> {
+		use std::ffi::CString;
+
 		unsafe {
-			let (vs,fs) = (gl::CreateShader(gl::VERTEX_SHADER), gl::CreateShader(gl::FRAGMENT_SHADER));
 			let program = gl::CreateProgram();
+			let mut shaders = Vec::new();
+
+			let stages = [
+				(gl::VERTEX_SHADER, ShaderStage::Vertex, vertex_shader_src),
+				(gl::FRAGMENT_SHADER, ShaderStage::Fragment, fragment_shader_src),
+			];
+
+			let mut result = Ok(());
+
+			for &(kind, stage, src) in stages.iter() {
+				let sh = gl::CreateShader(kind);
+				shaders.push(sh);
 
-			for &(sh, src) in [(vs, vertex_shader_src), (fs, fragment_shader_src)].iter() {
-				let src = CString::new(src).unwrap();
-				gl::ShaderSource(sh, 1, &src.as_ptr(), std::ptr::null());
+				// GL would stop reading at the NUL, and quietly compile whatever came before it
+				let c_src = match CString::new(src) {
+					Ok(c_src) => c_src,
+					Err(e) => {
+						let line_no = src[..e.nul_position()].matches('\n').count() + 1;
+						let log = format!("0:{}: error: source contains a NUL byte", line_no);
+						let context = annotate_source(src, &log);
+						result = Err(ShaderError { stage, log, context });
+						break
+					}
+				};
+
+				gl::ShaderSource(sh, 1, &c_src.as_ptr(), std::ptr::null());
 				gl::CompileShader(sh);
 
 				let mut status = 0i32;
 				gl::GetShaderiv(sh, gl::COMPILE_STATUS, &mut status);
 				if status == 0 {
-					let mut buf = [0u8; 1024];
-					let mut len = 0;
-					gl::GetShaderInfoLog(sh, buf.len() as _, &mut len, buf.as_mut_ptr() as _);
-
-					println!("{}", CStr::from_bytes_with_nul_unchecked(&buf[..len as usize]).to_str().unwrap());
+					let log = shader_info_log(sh);
+					let context = annotate_source(src, &log);
+					result = Err(ShaderError { stage, log, context });
+					break
 				}
-				
+
 				gl::AttachShader(program, sh);
 			}
 
-			gl::LinkProgram(program);
-			gl::UseProgram(program);
+			if result.is_ok() {
+				gl::LinkProgram(program);
 
-			gl::DeleteShader(vs);
-			gl::DeleteShader(fs);
+				let mut status = 0i32;
+				gl::GetProgramiv(program, gl::LINK_STATUS, &mut status);
+				if status == 0 {
+					let log = program_info_log(program);
+					result = Err(ShaderError { stage: ShaderStage::Link, log, context: String::new() });
+				}
+			}
 
-			Shader {
+			// The program keeps what it needs of these
+			for &sh in shaders.iter() {
+				gl::DeleteShader(sh);
+			}
+
+			if let Err(e) = result {
+				gl::DeleteProgram(program);
+				return Err(e)
+			}
+
+			gl::UseProgram(program);
+			leaks::created(GlResource::Program, program);
+
+			Ok(Shader {
 				gl_handle: program,
 
 				proj_loc: gl::GetUniformLocation(program, b"proj\0".as_ptr() as _),
 				view_loc: gl::GetUniformLocation(program, b"view\0".as_ptr() as _),
-			}
+			})
 		}
 	}
 
-	pub const fn invalid() -> Shader {
-		Shader {
-			gl_handle: 0,
-			proj_loc: 0,
-			view_loc: 0,
-		}
-	}
+	pub fn gl_handle(&self) -> u32 { self.gl_handle }
 
 	pub fn use_program(&self) {
 		unsafe {
@@ -129,4 +203,165 @@ impl Shader {
 	pub fn set_view(&self, mat: &Mat4) {
 		self.set_uniform_mat_raw(self.view_loc, &mat);
 	}
-}
\ No newline at end of file
+}
+
+impl Drop for Shader {
+	fn drop(&mut self) {
+		// invalid() shaders don't own anything
+		if self.gl_handle == 0 { return }
+
+		unsafe {
+			gl::DeleteProgram(self.gl_handle);
+		}
+
+		leaks::destroyed(GlResource::Program, self.gl_handle);
+	}
+}
+
+unsafe fn shader_info_log(shader: u32) -> String {
+	let mut capacity = 0i32;
+	gl::GetShaderiv(shader, gl::INFO_LOG_LENGTH, &mut capacity);
+	read_info_log(capacity, |size, len, buf| gl::GetShaderInfoLog(shader, size, len, buf))
+}
+
+unsafe fn program_info_log(program: u32) -> String {
+	let mut capacity = 0i32;
+	gl::GetProgramiv(program, gl::INFO_LOG_LENGTH, &mut capacity);
+	read_info_log(capacity, |size, len, buf| gl::GetProgramInfoLog(program, size, len, buf))
+}
+
+/// Reads a whole info log, however long it is
+unsafe fn read_info_log<F>(capacity: i32, get_log: F) -> String
+	where F: FnOnce(i32, *mut i32, *mut gl::GLchar) {
+
+	// Some drivers don't report a length, so leave room for something anyway
+	let mut buf = vec![0u8; capacity.max(1024) as usize];
+	let mut len = 0;
+	get_log(buf.len() as _, &mut len, buf.as_mut_ptr() as _);
+
+	String::from_utf8_lossy(&buf[..len as usize]).into_owned()
+}
+
+/// How many lines either side of an error to quote
+const CONTEXT_LINES: usize = 1;
+
+/// Quotes the lines of `src` mentioned in `log`, with the offending line marked.
+/// Understands both "ERROR: 0:12: ..." and "0:12(5): error: ..." styles
+fn annotate_source(src: &str, log: &str) -> String {
+	let lines: Vec<&str> = src.lines().collect();
+	let mut out = String::new();
+
+	for log_line in log.lines() {
+		let line_no = match error_line_number(log_line) {
+			Some(n) if n >= 1 && n <= lines.len() => n,
+			_ => continue,
+		};
+
+		let first = line_no.saturating_sub(CONTEXT_LINES).max(1);
+		let last = (line_no + CONTEXT_LINES).min(lines.len());
+
+		out.push_str(log_line.trim());
+		out.push('\n');
+
+		for n in first..last+1 {
+			let marker = if n == line_no { ">" } else { " " };
+			out.push_str(&format!("{} {:4} | {}\n", marker, n, lines[n-1]));
+		}
+
+		out.push('\n');
+	}
+
+	out.trim_right().to_owned()
+}
+
+/// Finds the "<source>:<line>" part of a log line
+fn error_line_number(log_line: &str) -> Option<usize> {
+	let parts: Vec<&str> = log_line.split(':').collect();
+
+	for pair in parts.windows(2) {
+		let source = pair[0].trim();
+		let line: String = pair[1].chars().take_while(|c| c.is_digit(10)).collect();
+
+		if !source.is_empty() && source.chars().all(|c| c.is_digit(10)) && !line.is_empty() {
+			return line.parse().ok()
+		}
+	}
+
+	None
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use platform::recording_gl::record_gl;
+
+	const SRC: &'static str = "void main() {\n\tfloat a = 1.0;\n\tfloat b = a +;\n\tgl_FragColor = vec4(b);\n}";
+
+	#[test]
+	fn line_numbers_in_either_log_style() {
+		assert_eq!(error_line_number("ERROR: 0:12: 'b' : undeclared identifier"), Some(12));
+		assert_eq!(error_line_number("0:12(5): error: syntax error, unexpected ';'"), Some(12));
+		assert_eq!(error_line_number("WARNING: 0:3: extension not supported"), Some(3));
+	}
+
+	#[test]
+	fn no_line_number_without_a_source() {
+		assert_eq!(error_line_number("ERROR: unexpected end of file"), None);
+		assert_eq!(error_line_number("Link failed: main not found"), None);
+		assert_eq!(error_line_number(""), None);
+	}
+
+	#[test]
+	fn annotates_the_offending_line() {
+		let context = annotate_source(SRC, "ERROR: 0:3: syntax error");
+
+		assert_eq!(context, "ERROR: 0:3: syntax error\n\
+			\x20    2 | \tfloat a = 1.0;\n\
+			>    3 | \tfloat b = a +;\n\
+			\x20    4 | \tgl_FragColor = vec4(b);");
+	}
+
+	#[test]
+	fn annotates_the_other_log_style() {
+		let context = annotate_source(SRC, "0:3(17): error: syntax error");
+		assert!(context.starts_with("0:3(17): error: syntax error\n"));
+		assert!(context.contains(">    3 | \tfloat b = a +;"));
+	}
+
+	#[test]
+	fn context_is_clamped_to_the_source() {
+		let first = annotate_source(SRC, "ERROR: 0:1: oops");
+		assert!(first.contains(">    1 | void main() {"));
+		assert!(!first.contains("   0 |"));
+
+		let last = annotate_source(SRC, "ERROR: 0:5: oops");
+		assert!(last.ends_with(">    5 | }"));
+	}
+
+	#[test]
+	fn out_of_range_lines_are_skipped() {
+		assert_eq!(annotate_source(SRC, "ERROR: 0:0: oops"), "");
+		assert_eq!(annotate_source(SRC, "ERROR: 0:6: oops"), "");
+		assert_eq!(annotate_source(SRC, "ERROR: 0:400: oops\nERROR: 0:2: real"),
+			"ERROR: 0:2: real\n\
+			\x20    1 | void main() {\n\
+			>    2 | \tfloat a = 1.0;\n\
+			\x20    3 | \tfloat b = a +;");
+	}
+
+	#[test]
+	fn nul_in_source_is_an_error() {
+		let mut result = None;
+		let recording = record_gl(|| {
+			result = Some(Shader::new("void main() {\n\tgl_Position = vec4(0.0);\0\n}", "void main() {}"));
+		});
+
+		let e = result.unwrap().err().expect("a source with a NUL in it built");
+		assert_eq!(e.stage, ShaderStage::Vertex);
+		assert!(e.log.contains("NUL"));
+		assert!(e.context.contains(">    2 |"));
+
+		// Everything made on the way is cleaned up again
+		assert!(recording.live_objects().is_empty());
+	}
+}
@@ -1,16 +1,32 @@
 #![allow(dead_code)]
 
 use rendering::gl;
+use rendering::leaks::{self, GlResource};
 use rendering::types::*;
 
 use std::ops::Drop;
+use std::rc::Rc;
 
+/// Owns a GL texture, and deletes it when dropped
 pub struct Texture {
-	pub gl_handle: u32,
+	gl_handle: u32,
 	pub size: Vec2i,
 }
 
+/// For a texture used from more than one place, like a framebuffer target that
+/// something else samples. The GL texture goes with the last handle.
+/// Meshes and shaders each have a single owner, so they don't need one
+pub type SharedTexture = Rc<Texture>;
+
 impl Texture {
+	/// Takes ownership of a texture made directly through gl::GenTextures, by
+	/// a framebuffer. Anywhere else, a handle that isn't really a texture would
+	/// be deleted on drop
+	pub(super) fn from_handle(gl_handle: u32, size: Vec2i) -> Self {
+		leaks::created(GlResource::Texture, gl_handle);
+		Texture { gl_handle, size }
+	}
+
 	pub fn new() -> Self {
 		let mut gl_handle = 0;
 
@@ -24,9 +40,11 @@ impl Texture {
 			gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
 		}
 
-		Texture { gl_handle, size: Vec2i::zero() }
+		Texture::from_handle(gl_handle, Vec2i::zero())
 	}
 
+	pub fn gl_handle(&self) -> u32 { self.gl_handle }
+
 	pub fn bind_guard(&self) -> TextureBindGuard {
 		TextureBindGuard::new_raw(self.gl_handle)
 	}
@@ -148,6 +166,16 @@ impl Texture {
 	}
 }
 
+impl Drop for Texture {
+	fn drop(&mut self) {
+		unsafe {
+			gl::DeleteTextures(1, &self.gl_handle);
+		}
+
+		leaks::destroyed(GlResource::Texture, self.gl_handle);
+	}
+}
+
 pub struct TextureBindGuard {
 	prev_binding: Option<u32>,
 }
@@ -0,0 +1,452 @@
+// Records everything that goes into a frame - the time, the raw events and the
+// polled gamepads - as a plain text log, and plays it back in place of live
+// input. Since the simulation only depends on those, a replay reproduces the
+// recorded boat trajectory exactly, even on a headless host.
+//
+// The log is one line per frame followed by a line per event:
+//
+//   f <frame> <time>
+//   down <x> <y>
+//   wheel <notches>
+//   touch_move <id> <x> <y>
+//   key_down <code>
+//   pad <index> <axes...> <buttons...>
+//
+// Set BOAT_RECORD to a path to record to it, or BOAT_REPLAY to play one back.
+// The game stops when a replay runs out, though on a headless host BOAT_FRAMES
+// has to be large enough to get there. In the browser there's nowhere to write
+// a file, so adding ?record to the page's URL keeps the log in memory instead,
+// and the dump_replay action writes it to standard output, which emscripten
+// shows in the browser's developer console. Natively, setting BOAT_RECORD to
+// an empty string records into memory too, and dump_replay saves the log to
+// REPLAY_DUMP_PATH. Nothing is kept unless recording was asked for.
+//
+// Apart from that dump, nothing here prints. Anything that goes wrong comes
+// back as a message for the game's console.
+
+use std::env;
+use std::fmt::Write as FmtWrite;
+use std::fs::File;
+use std::io::Write;
+
+use common::*;
+use events::{Event, Key};
+use gamepad::{GamepadState, MAX_GAMEPAD_AXES, MAX_GAMEPAD_BUTTONS};
+use boat::BoatSim;
+
+/// Where dump_replay saves an in-memory recording, relative to the working
+/// directory
+#[cfg(not(target_os = "emscripten"))]
+pub const REPLAY_DUMP_PATH: &'static str = "boat_dump.replay";
+
+/// Everything a frame of gameplay reads from the outside world
+#[derive(Clone, Debug)]
+pub struct FrameInput {
+	pub frame: u32,
+	pub time: f64,
+	pub events: Vec<Event>,
+	pub gamepads: Vec<GamepadState>,
+}
+
+impl FrameInput {
+	fn write_to(&self, out: &mut String) {
+		writeln!(out, "f {} {}", self.frame, self.time).unwrap();
+
+		for event in self.events.iter() {
+			write_event(out, event);
+		}
+
+		for pad in self.gamepads.iter() {
+			write!(out, "pad {}", pad.index).unwrap();
+			for v in pad.axes.iter().chain(pad.buttons.iter()) {
+				write!(out, " {}", v).unwrap();
+			}
+			writeln!(out, "").unwrap();
+		}
+	}
+}
+
+fn write_event(out: &mut String, event: &Event) {
+	let result = match *event {
+		Event::Resize(sz) => writeln!(out, "resize {} {}", sz.x, sz.y),
+		Event::Down(p) => writeln!(out, "down {} {}", p.x, p.y),
+		Event::Up(p) => writeln!(out, "up {} {}", p.x, p.y),
+		Event::Move(p) => writeln!(out, "move {} {}", p.x, p.y),
+		Event::Wheel(notches) => writeln!(out, "wheel {}", notches),
+		Event::TouchStart(id, p) => writeln!(out, "touch_start {} {} {}", id, p.x, p.y),
+		Event::TouchMove(id, p) => writeln!(out, "touch_move {} {} {}", id, p.x, p.y),
+		Event::TouchEnd(id, p) => writeln!(out, "touch_end {} {} {}", id, p.x, p.y),
+		Event::KeyDown(key) => writeln!(out, "key_down {}", key.code()),
+		Event::KeyUp(key) => writeln!(out, "key_up {}", key.code()),
+		Event::GamepadConnected(index) => writeln!(out, "pad_connected {}", index),
+		Event::GamepadDisconnected(index) => writeln!(out, "pad_disconnected {}", index),
+	};
+
+	result.unwrap();
+}
+
+/// Parses one non-frame line of a log into `frame`. Returns false if it
+/// doesn't make sense
+fn parse_line(words: &[&str], frame: &mut FrameInput) -> bool {
+	let int = |i: usize| words.get(i).and_then(|w| w.parse::<i32>().ok());
+	let id = |i: usize| words.get(i).and_then(|w| w.parse::<u32>().ok());
+	let key = |i: usize| words.get(i).and_then(|w| Key::from_code(w));
+	let point = |i: usize| match (int(i), int(i + 1)) {
+		(Some(x), Some(y)) => Some(Vec2i::new(x, y)),
+		_ => None,
+	};
+
+	let touch = |to_event: fn(u32, Vec2i) -> Event| match (id(1), point(2)) {
+		(Some(id), Some(pos)) => Some(to_event(id, pos)),
+		_ => None,
+	};
+
+	let event = match words[0] {
+		"resize" => point(1).map(Event::Resize),
+		"down" => point(1).map(Event::Down),
+		"up" => point(1).map(Event::Up),
+		"move" => point(1).map(Event::Move),
+		"wheel" => words.get(1).and_then(|w| w.parse().ok()).map(Event::Wheel),
+		"touch_start" => touch(Event::TouchStart),
+		"touch_move" => touch(Event::TouchMove),
+		"touch_end" => touch(Event::TouchEnd),
+		"key_down" => key(1).map(Event::KeyDown),
+		"key_up" => key(1).map(Event::KeyUp),
+		"pad_connected" => id(1).map(Event::GamepadConnected),
+		"pad_disconnected" => id(1).map(Event::GamepadDisconnected),
+
+		"pad" => return parse_pad(words).map(|pad| frame.gamepads.push(pad)).is_some(),
+
+		_ => None,
+	};
+
+	event.map(|e| frame.events.push(e)).is_some()
+}
+
+fn parse_pad(words: &[&str]) -> Option<GamepadState> {
+	if words.len() != 2 + MAX_GAMEPAD_AXES + MAX_GAMEPAD_BUTTONS { return None }
+
+	let mut pad = match words[1].parse() {
+		Ok(index) => GamepadState::new(index),
+		Err(_) => return None,
+	};
+
+	{
+		let values = pad.axes.iter_mut().chain(pad.buttons.iter_mut());
+
+		for (v, w) in values.zip(words[2..].iter()) {
+			match w.parse() {
+				Ok(x) => *v = x,
+				Err(_) => return None,
+			}
+		}
+	}
+
+	Some(pad)
+}
+
+/// Streams frames to a file, or keeps them in memory until they're dumped.
+/// A replay has to start from the first frame to reproduce anything, so
+/// recording is all or nothing rather than a window of recent frames
+pub struct Recorder {
+	log: Option<String>,
+	sink: Option<File>,
+	line: String,
+}
+
+impl Recorder {
+	/// Doesn't record anything
+	pub fn new() -> Self {
+		Recorder { log: None, sink: None, line: String::new() }
+	}
+
+	/// Keeps every frame in memory, to be fetched with log()
+	pub fn buffered() -> Self {
+		Recorder { log: Some(String::new()), ..Recorder::new() }
+	}
+
+	/// Records to the file named by BOAT_RECORD if it's set, or into memory
+	/// if it's set but empty
+	pub fn from_env() -> Result<Self, String> {
+		let path = match env::var("BOAT_RECORD") {
+			Ok(path) => path,
+			Err(_) => return Ok(Recorder::new()),
+		};
+
+		if path.is_empty() {
+			return Ok(Recorder::buffered())
+		}
+
+		let file = File::create(&path)
+			.map_err(|e| format!("Couldn't create recording '{}': {}", path, e))?;
+
+		Ok(Recorder { sink: Some(file), ..Recorder::new() })
+	}
+
+	pub fn is_recording(&self) -> bool {
+		self.log.is_some() || self.sink.is_some()
+	}
+
+	/// Adds `frame` to the recording. If writing it out fails, the file is
+	/// given up on rather than left with a gap in it
+	pub fn record(&mut self, frame: &FrameInput) -> Result<(), String> {
+		if !self.is_recording() { return Ok(()) }
+
+		self.line.clear();
+		frame.write_to(&mut self.line);
+
+		if let Some(ref mut log) = self.log {
+			log.push_str(&self.line);
+		}
+
+		let written = match self.sink {
+			Some(ref mut sink) => sink.write_all(self.line.as_bytes()),
+			None => Ok(()),
+		};
+
+		written.map_err(|e| {
+			self.sink = None;
+			format!("Failed writing recording, so stopped: {}", e)
+		})
+	}
+
+	/// Everything recorded so far, if it's being kept in memory
+	pub fn log(&self) -> Option<&str> {
+		self.log.as_ref().map(|log| log.as_str())
+	}
+
+	/// Saves the in-memory recording somewhere it can be picked up, and says
+	/// where that was
+	pub fn dump(&self) -> Result<String, String> {
+		match self.log() {
+			Some(log) => save_dump(log),
+			None => Err(String::from("not recording to memory - add ?record to the URL, or set BOAT_RECORD to nothing")),
+		}
+	}
+}
+
+#[cfg(not(target_os = "emscripten"))]
+fn save_dump(log: &str) -> Result<String, String> {
+	File::create(REPLAY_DUMP_PATH)
+		.and_then(|mut file| file.write_all(log.as_bytes()))
+		.map(|_| format!("saved to {}", REPLAY_DUMP_PATH))
+		.map_err(|e| format!("Couldn't save '{}': {}", REPLAY_DUMP_PATH, e))
+}
+
+/// A page can't write files, so the log goes to standard output instead
+#[cfg(target_os = "emscripten")]
+fn save_dump(log: &str) -> Result<String, String> {
+	::std::io::stdout().write_all(log.as_bytes())
+		.map(|_| String::from("written to the developer console"))
+		.map_err(|e| format!("Couldn't write to the developer console: {}", e))
+}
+
+/// A parsed log, handed back one frame at a time
+pub struct Replay {
+	frames: Vec<FrameInput>,
+	next: usize,
+}
+
+impl Replay {
+	pub fn parse(src: &str) -> Result<Replay, String> {
+		let mut frames: Vec<FrameInput> = Vec::new();
+
+		for (line_no, line) in src.lines().enumerate() {
+			let words: Vec<&str> = line.split_whitespace().collect();
+			if words.is_empty() { continue }
+
+			let parsed = if words[0] == "f" {
+				match (words.get(1).and_then(|w| w.parse().ok()), words.get(2).and_then(|w| w.parse().ok())) {
+					(Some(frame), Some(time)) => {
+						frames.push(FrameInput { frame, time, events: Vec::new(), gamepads: Vec::new() });
+						true
+					}
+					_ => false,
+				}
+			} else {
+				frames.last_mut().map_or(false, |frame| parse_line(&words, frame))
+			};
+
+			if !parsed {
+				return Err(format!("line {}: couldn't understand '{}'", line_no + 1, line))
+			}
+		}
+
+		Ok(Replay { frames, next: 0 })
+	}
+
+	/// Loads the log named by BOAT_REPLAY, if set
+	pub fn from_env() -> Result<Option<Replay>, String> {
+		use std::io::Read;
+
+		let path = match env::var("BOAT_REPLAY") {
+			Ok(path) => path,
+			Err(_) => return Ok(None),
+		};
+
+		let mut src = String::new();
+		File::open(&path).and_then(|mut f| f.read_to_string(&mut src))
+			.map_err(|e| format!("Couldn't read replay '{}': {}", path, e))?;
+
+		Replay::parse(&src)
+			.map(Some)
+			.map_err(|e| format!("Couldn't parse replay '{}': {}", path, e))
+	}
+
+	/// The next recorded frame, or None once the replay has run out
+	pub fn next_frame(&mut self) -> Option<&FrameInput> {
+		let frame = self.frames.get(self.next);
+		self.next += 1;
+		frame
+	}
+}
+
+/// Writes the boat's state after every simulation step to the file named by
+/// BOAT_TRAJECTORY, so two runs of the same replay can be diffed
+pub struct TrajectoryLog {
+	sink: Option<File>,
+	step: u32,
+}
+
+impl TrajectoryLog {
+	/// Doesn't log anything
+	pub fn new() -> Self {
+		TrajectoryLog { sink: None, step: 0 }
+	}
+
+	pub fn from_env() -> Result<Self, String> {
+		let path = match env::var("BOAT_TRAJECTORY") {
+			Ok(path) => path,
+			Err(_) => return Ok(TrajectoryLog::new()),
+		};
+
+		let file = File::create(&path)
+			.map_err(|e| format!("Couldn't create trajectory log '{}': {}", path, e))?;
+
+		Ok(TrajectoryLog { sink: Some(file), step: 0 })
+	}
+
+	pub fn record(&mut self, boat: &BoatSim) {
+		if let Some(ref mut sink) = self.sink {
+			let _ = sink.write_all(trajectory_line(self.step, boat).as_bytes());
+		}
+
+		self.step += 1;
+	}
+}
+
+/// One line of a trajectory log - the step, position, heading and speed
+fn trajectory_line(step: u32, boat: &BoatSim) -> String {
+	format!("{} {} {} {} {}\n", step, boat.position.x, boat.position.y, boat.heading, boat.speed)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::fs;
+	use std::path::PathBuf;
+
+	use boat::BoatSim;
+	use camera::Camera;
+	use helm::Helm;
+	use input::{Input, InputBindings};
+	use ocean::CalmSea;
+	use resources as res;
+	use timestep::FixedTimestep;
+	use wind::Wind;
+
+	const TOLERANCE: f32 = 1e-3;
+
+	fn replay_path(name: &str) -> PathBuf {
+		PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("assets/replays").join(name)
+	}
+
+	/// Plays `replay` through the same input handling and helm as the game, on
+	/// open water with a fixed wind, and returns the trajectory it produces
+	fn sail_replay(mut replay: Replay) -> String {
+		let mut input = Input::new(InputBindings::parse(res::config::INPUT).0);
+		let mut camera = Camera::new(PI/4.0, 12.0);
+		let mut helm = Helm::new(-3.0 * PI / 8.0);
+		let mut boat = BoatSim::new(helm.orders.target_heading);
+		let mut timestep = FixedTimestep::new(1.0 / 60.0);
+
+		let mut wind = Wind::new(0, 0.0);
+		wind.direction = PI / 2.0;
+		wind.strength = 0.6;
+
+		let mut trajectory = String::new();
+		let mut step = 0;
+		let mut last_time = None;
+
+		while let Some(frame) = replay.next_frame() {
+			let frame_dt = last_time.map_or(0.0, |last| (frame.time - last) as f32);
+			last_time = Some(frame.time);
+
+			for event in frame.events.iter() {
+				if let Event::Resize(sz) = *event {
+					camera.set_viewport_size(sz);
+				}
+			}
+
+			input.update(&frame.events, &frame.gamepads, frame.time, frame_dt);
+			helm.update(&input, &camera, &(), &boat, frame_dt);
+
+			for _ in 0..timestep.advance_to(frame.time) {
+				helm.step(&boat);
+				boat.step(&helm.orders, &CalmSea, &wind, &(), timestep.step());
+
+				trajectory.push_str(&trajectory_line(step, &boat));
+				step += 1;
+			}
+
+			camera.update(boat.position, boat.heading, frame_dt);
+		}
+
+		trajectory
+	}
+
+	fn parse_trajectory(src: &str) -> Vec<Vec<f32>> {
+		src.lines()
+			.map(|line| line.split_whitespace().map(|w| w.parse().unwrap()).collect())
+			.collect()
+	}
+
+	#[test]
+	fn steering_replay_matches_its_trajectory() {
+		let log = fs::read_to_string(replay_path("steering.log")).unwrap();
+		let trajectory = sail_replay(Replay::parse(&log).unwrap());
+
+		let expected_path = replay_path("steering.trajectory");
+		if env::var("BOAT_UPDATE_GOLDEN").is_ok() {
+			fs::write(&expected_path, &trajectory).unwrap();
+			return
+		}
+
+		let expected = fs::read_to_string(&expected_path)
+			.expect("missing steering.trajectory - run with BOAT_UPDATE_GOLDEN=1 to write it");
+
+		let actual = parse_trajectory(&trajectory);
+		let expected = parse_trajectory(&expected);
+		assert_eq!(actual.len(), expected.len(), "replay ran a different number of steps");
+
+		for (a, e) in actual.iter().zip(expected.iter()) {
+			let close = a.iter().zip(e.iter()).all(|(a, e)| (a - e).abs() <= TOLERANCE);
+			assert!(close, "trajectory diverged at step {}: got {:?}, expected {:?}", e[0], a, e);
+		}
+	}
+
+	#[test]
+	fn recorder_keeps_nothing_unless_asked() {
+		let frame = FrameInput { frame: 0, time: 0.0, events: vec![Event::Wheel(1.0)], gamepads: Vec::new() };
+
+		let mut recorder = Recorder::new();
+		recorder.record(&frame).unwrap();
+		assert!(!recorder.is_recording());
+		assert_eq!(recorder.log(), None);
+		assert!(recorder.dump().is_err());
+
+		let mut recorder = Recorder::buffered();
+		recorder.record(&frame).unwrap();
+		assert_eq!(recorder.log(), Some("f 0 0\nwheel 1\n"));
+	}
+}
@@ -1,4 +1,11 @@
 pub mod shaders {
+	// Where debug builds fetch them from at runtime
+	pub static BASIC_VS_PATH: &'static str = "assets/basic.vs";
+	pub static BASIC_FS_PATH: &'static str = "assets/basic.fs";
+
 	pub static BASIC_VS: &'static str = include_str!("../assets/basic.vs");
 	pub static BASIC_FS: &'static str = include_str!("../assets/basic.fs");
-}
\ No newline at end of file
+}
+pub mod config {
+	pub static INPUT: &'static str = include_str!("../assets/input.cfg");
+}
@@ -0,0 +1,197 @@
+use common::*;
+use wind::Wind;
+
+/// Sailing any closer to the wind than this and the sail just flaps
+pub const NO_GO_ANGLE: f32 = PI / 4.0;
+
+/// How far outside the no-go zone to sit when beating upwind
+const CLOSE_HAULED_MARGIN: f32 = PI / 24.0;
+
+/// Seconds spent on each tack when beating upwind
+const TACK_INTERVAL: f32 = 6.0;
+
+/// Fraction of full thrust left when running straight downwind
+const RUNNING_THRUST: f32 = 0.6;
+
+pub fn wrap_angle(a: f32) -> f32 {
+	let mut a = a % (2.0 * PI);
+	if a > PI { a -= 2.0 * PI }
+	if a < -PI { a += 2.0 * PI }
+	a
+}
+
+/// Signed angle from `heading` to the direction the wind is coming from.
+/// Positive means the wind is coming over the port side
+pub fn relative_wind_angle(heading: f32, wind: &Wind) -> f32 {
+	wrap_angle(wind.direction + PI - heading)
+}
+
+/// 0 when pointing straight into the wind, PI when running with it
+pub fn angle_off_wind(heading: f32, wind: &Wind) -> f32 {
+	relative_wind_angle(heading, wind).abs()
+}
+
+/// Fraction of full drive the sail produces at `heading`. Nothing in the
+/// no-go zone, peaking on a beam reach and dropping off a bit downwind
+pub fn thrust(heading: f32, wind: &Wind) -> f32 {
+	let angle = angle_off_wind(heading, wind);
+
+	let efficiency = if angle < NO_GO_ANGLE {
+		0.0
+	} else if angle < PI / 2.0 {
+		(angle - NO_GO_ANGLE) / (PI / 2.0 - NO_GO_ANGLE)
+	} else {
+		1.0 - (1.0 - RUNNING_THRUST) * (angle - PI / 2.0) / (PI / 2.0)
+	};
+
+	efficiency * wind.strength
+}
+
+/// How far the boom swings out from the centre line. The sail always sits on
+/// the side away from the wind
+pub fn boom_angle(heading: f32, wind: &Wind) -> f32 {
+	relative_wind_angle(heading, wind) / 2.0
+}
+
+/// Remembers which tack the boat is on while it works its way upwind
+#[derive(Copy, Clone, Debug)]
+pub struct TackState {
+	side: f32,
+	timer: f32,
+}
+
+impl TackState {
+	pub fn new() -> Self {
+		TackState { side: 0.0, timer: 0.0 }
+	}
+
+	pub fn is_tacking(&self) -> bool { self.side != 0.0 }
+
+	/// Turns a heading the player wants into one the boat can actually sail.
+	/// Headings in the no-go zone get replaced with alternating close hauled
+	/// headings either side of it, zigzagging towards the target
+	pub fn resolve_heading(&mut self, target_heading: f32, current_heading: f32, wind: &Wind, dt: f32) -> f32 {
+		let upwind = wind.direction + PI;
+
+		if angle_off_wind(target_heading, wind) >= NO_GO_ANGLE {
+			self.side = 0.0;
+			return target_heading
+		}
+
+		if self.side == 0.0 {
+			// Start on whichever tack needs the least turning
+			self.side = wrap_angle(current_heading - upwind).signum();
+			if self.side == 0.0 { self.side = 1.0 }
+			self.timer = TACK_INTERVAL;
+		}
+
+		self.timer -= dt;
+		if self.timer <= 0.0 {
+			self.side = -self.side;
+			self.timer = TACK_INTERVAL;
+		}
+
+		upwind + self.side * (NO_GO_ANGLE + CLOSE_HAULED_MARGIN)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use boat::{BoatSim, BoatInput};
+	use ocean::CalmSea;
+
+	const DT: f32 = 1.0 / 60.0;
+
+	/// Blowing towards +x at full strength, so it comes from -x
+	fn easterly() -> Wind {
+		let mut wind = Wind::new(0, 0.0);
+		wind.direction = 0.0;
+		wind.strength = 1.0;
+		wind
+	}
+
+	#[test]
+	fn no_thrust_in_the_no_go_zone() {
+		let wind = easterly();
+
+		for &off in [0.0, 0.3, -0.3, NO_GO_ANGLE - 0.01, -NO_GO_ANGLE + 0.01].iter() {
+			assert_eq!(thrust(PI + off, &wind), 0.0, "{} off the wind", off);
+		}
+
+		assert!(thrust(PI + NO_GO_ANGLE + 0.1, &wind) > 0.0);
+	}
+
+	#[test]
+	fn thrust_peaks_on_a_beam_reach() {
+		let mut wind = easterly();
+		let beam = thrust(PI / 2.0, &wind);
+
+		assert!((beam - 1.0).abs() < 1e-5);
+		assert!((thrust(-PI / 2.0, &wind) - beam).abs() < 1e-5);
+		assert!((thrust(0.0, &wind) - RUNNING_THRUST).abs() < 1e-5);
+
+		for i in 0..64 {
+			let heading = i as f32 / 64.0 * 2.0 * PI - PI;
+			assert!(thrust(heading, &wind) <= beam + 1e-5, "more thrust at {} than on the beam", heading);
+		}
+
+		wind.strength = 0.5;
+		assert!((thrust(PI / 2.0, &wind) - 0.5).abs() < 1e-5);
+	}
+
+	#[test]
+	fn tacks_alternate_either_side_of_the_wind() {
+		let wind = easterly();
+		let mut tack = TackState::new();
+
+		// Straight upwind, currently a little to port of it
+		let first = tack.resolve_heading(PI, PI - 0.2, &wind, DT);
+		assert!(tack.is_tacking());
+		assert!(angle_off_wind(first, &wind) >= NO_GO_ANGLE);
+		assert!(relative_wind_angle(first, &wind) > 0.0);
+
+		let mut second = first;
+		let mut elapsed = DT;
+		while wrap_angle(second - first).abs() < 1e-4 {
+			assert!(elapsed < TACK_INTERVAL + 1.0, "never tacked");
+			second = tack.resolve_heading(PI, first, &wind, DT);
+			elapsed += DT;
+		}
+
+		assert!((elapsed - TACK_INTERVAL).abs() < 2.0 * DT, "tacked after {}s", elapsed);
+		assert!(angle_off_wind(second, &wind) >= NO_GO_ANGLE);
+		assert!(relative_wind_angle(second, &wind) < 0.0);
+
+		// Anything sailable ends the tacking
+		assert_eq!(tack.resolve_heading(PI / 2.0, second, &wind, DT), PI / 2.0);
+		assert!(!tack.is_tacking());
+	}
+
+	#[test]
+	fn boat_tacks_across_the_wind() {
+		let wind = easterly();
+		let input = BoatInput { target_heading: PI, target_speed: 1.0 };
+		let mut boat = BoatSim::new(PI - 0.2);
+
+		let start_side = relative_wind_angle(boat.heading, &wind).signum();
+		let mut crossed_at = None;
+
+		// Just short of the second tack
+		for step in 0..(TACK_INTERVAL * 1.9 / DT) as usize {
+			boat.step(&input, &CalmSea, &wind, &(), DT);
+
+			if crossed_at.is_none() && relative_wind_angle(boat.heading, &wind).signum() != start_side {
+				crossed_at = Some(step as f32 * DT);
+			}
+		}
+
+		let crossed_at = crossed_at.expect("boat never came about");
+		assert!(crossed_at > TACK_INTERVAL - 1.0, "came about early, after {}s", crossed_at);
+
+		// Settled on the new tack, sailing again, and made some ground upwind
+		assert!(angle_off_wind(boat.heading, &wind) >= NO_GO_ANGLE - 0.05, "heading {}", boat.heading);
+		assert!(boat.speed > 0.1);
+		assert!(boat.position.x < 0.0);
+	}
+}
@@ -0,0 +1,154 @@
+// Debug builds fetch shader sources at runtime instead of relying on the copies
+// baked in with include_str!, so tweaking something under assets/ only needs a
+// reload rather than a rebuild. Release builds only ever use the baked copies.
+
+use std::ops::Deref;
+
+use platform::{Platform, Fetch};
+use rendering::{Shader, ShaderError};
+
+/// What came of the last reload
+pub enum ReloadResult {
+	Reloaded,
+	/// The sources are the same as the ones already in use, so nothing was rebuilt
+	Unchanged,
+	/// The program didn't build, so the previous one is still in use
+	Failed(ShaderError),
+	/// One of the sources couldn't be fetched
+	Missing(String),
+}
+
+pub struct ReloadableShader {
+	vs_path: &'static str,
+	fs_path: &'static str,
+	shader: Shader,
+	/// What `shader` was built from
+	sources: (String, String),
+	pending: Option<(Fetch, Fetch)>,
+}
+
+impl ReloadableShader {
+	/// Builds from the baked in sources, which are always there, and then in
+	/// debug builds asks for the ones on disk in case they've changed since
+	pub fn new<P: Platform>(platform: &P, vs_path: &'static str, fs_path: &'static str, vs_src: &str, fs_src: &str) -> Result<Self, ShaderError> {
+		let mut shader = ReloadableShader {
+			vs_path, fs_path,
+			shader: Shader::new(vs_src, fs_src)?,
+			sources: (vs_src.to_owned(), fs_src.to_owned()),
+			pending: None,
+		};
+
+		shader.reload(platform);
+		Ok(shader)
+	}
+
+	pub fn shader(&self) -> &Shader { &self.shader }
+
+	/// Starts fetching both sources again. Does nothing in release builds
+	#[allow(unused_variables)]
+	pub fn reload<P: Platform>(&mut self, platform: &P) {
+		#[cfg(debug)] {
+			self.pending = Some((platform.fetch_text(self.vs_path), platform.fetch_text(self.fs_path)));
+		}
+	}
+
+	/// Once both sources of a reload have arrived, rebuilds the program and
+	/// swaps it in, unless they're what's already in use. If that fails the
+	/// old program stays, so a typo in a shader doesn't take everything else
+	/// down with it
+	pub fn update(&mut self) -> Option<ReloadResult> {
+		let (vs, fs) = match self.pending {
+			Some((ref vs, ref fs)) => match (vs.poll(), fs.poll()) {
+				(Some(vs), Some(fs)) => (vs, fs),
+				_ => return None,
+			},
+			None => return None,
+		};
+
+		self.pending = None;
+
+		let vs = match vs {
+			Ok(vs) => vs,
+			Err(e) => return Some(ReloadResult::Missing(format!("{}: {}", self.vs_path, e))),
+		};
+
+		let fs = match fs {
+			Ok(fs) => fs,
+			Err(e) => return Some(ReloadResult::Missing(format!("{}: {}", self.fs_path, e))),
+		};
+
+		if (&vs, &fs) == (&self.sources.0, &self.sources.1) {
+			return Some(ReloadResult::Unchanged)
+		}
+
+		match Shader::new(&vs, &fs) {
+			Ok(shader) => {
+				self.shader = shader;
+				self.sources = (vs, fs);
+				Some(ReloadResult::Reloaded)
+			}
+
+			Err(e) => Some(ReloadResult::Failed(e)),
+		}
+	}
+}
+
+impl Deref for ReloadableShader {
+	type Target = Shader;
+	fn deref(&self) -> &Shader { &self.shader }
+}
+
+// Release builds never fetch anything, so there's nothing to test there
+#[cfg(all(test, debug))]
+mod tests {
+	use super::*;
+	use platform::native::NativePlatform;
+	use platform::recording_gl::{record_gl, GlCall};
+	use res::shaders::*;
+	use common::Vec2i;
+
+	fn programs_created(calls: &[GlCall]) -> usize {
+		calls.iter().filter(|c| match **c { GlCall::CreateProgram(_) => true, _ => false }).count()
+	}
+
+	#[test]
+	fn unchanged_sources_arent_rebuilt() {
+		let platform = NativePlatform::headless(Vec2i::new(64, 64), None);
+		let mut result = None;
+
+		let recording = record_gl(|| {
+			let mut shader = ReloadableShader::new(&platform, BASIC_VS_PATH, BASIC_FS_PATH, BASIC_VS, BASIC_FS).unwrap();
+			result = shader.update();
+		});
+
+		match result {
+			Some(ReloadResult::Unchanged) => {}
+			_ => panic!("expected the sources on disk to match the baked ones"),
+		}
+
+		assert_eq!(programs_created(&recording.calls), 1);
+	}
+
+	#[test]
+	fn changed_sources_are_swapped_in() {
+		let platform = NativePlatform::headless(Vec2i::new(64, 64), None);
+		let stale_vs = format!("// stale\n{}", BASIC_VS);
+		let mut results = Vec::new();
+
+		let recording = record_gl(|| {
+			let mut shader = ReloadableShader::new(&platform, BASIC_VS_PATH, BASIC_FS_PATH, &stale_vs, BASIC_FS).unwrap();
+			results.push(shader.update());
+
+			// Now it's built from what's on disk, so a second reload is a no-op
+			shader.reload(&platform);
+			results.push(shader.update());
+		});
+
+		match (&results[0], &results[1]) {
+			(&Some(ReloadResult::Reloaded), &Some(ReloadResult::Unchanged)) => {}
+			_ => panic!("expected a reload followed by nothing to do"),
+		}
+
+		assert_eq!(programs_created(&recording.calls), 2);
+	}
+}
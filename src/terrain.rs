@@ -0,0 +1,330 @@
+use common::*;
+use noise::{NoiseModule, Perlin, Seedable};
+
+use rendering::mesh_builder::*;
+use collision::{CollisionWorld, Obstacle};
+
+pub const CHUNK_SIZE: f32 = 16.0;
+pub const CHUNK_RESOLUTION: usize = 16;
+
+/// The world is split into cells this big, each of which may hold one island
+const ISLAND_CELL_SIZE: f32 = 48.0;
+const ISLAND_CHANCE: f32 = 0.6;
+const ISLAND_MIN_RADIUS: f32 = 5.0;
+const ISLAND_MAX_RADIUS: f32 = 14.0;
+const ISLAND_MAX_HEIGHT: f32 = 3.0;
+
+/// Terrain this far below sea level is never seen, so isn't meshed
+const SEABED_HEIGHT: f32 = -0.6;
+
+const SAND_HEIGHT: f32 = 0.2;
+const GRASS_HEIGHT: f32 = 1.6;
+
+const ROCK_ATTEMPTS_PER_CHUNK: u32 = 8;
+/// Rocks only turn up in the shallows, between these two heights
+const ROCK_MIN_HEIGHT: f32 = -0.5;
+const ROCK_MAX_HEIGHT: f32 = -0.1;
+const ROCK_MIN_RADIUS: f32 = 0.25;
+const ROCK_MAX_RADIUS: f32 = 0.6;
+
+/// Chance of an island getting a pier
+const PIER_CHANCE: f32 = 0.7;
+/// How far a pier reaches out past its island's shore
+const PIER_REACH: f32 = 3.0;
+const PIER_HALF_WIDTH: f32 = 0.3;
+
+pub type ChunkCoord = (i32, i32);
+
+struct Island {
+	centre: Vec2,
+	radius: f32,
+	height: f32,
+}
+
+/// Deterministically places and shapes islands from a world seed. The same
+/// seed and position always give the same height
+pub struct IslandGenerator {
+	seed: u32,
+	noise: Perlin,
+}
+
+impl IslandGenerator {
+	pub fn new(seed: usize) -> Self {
+		IslandGenerator {
+			seed: seed as u32,
+			noise: Perlin::new().set_seed(seed),
+		}
+	}
+
+	fn island_in_cell(&self, cx: i32, cz: i32) -> Option<Island> {
+		// Keep the area the boat starts in clear
+		if cx == 0 && cz == 0 { return None }
+
+		if hash_to_unit(self.seed, cx, cz, 0) > ISLAND_CHANCE {
+			return None
+		}
+
+		let radius = ISLAND_MIN_RADIUS + (ISLAND_MAX_RADIUS - ISLAND_MIN_RADIUS) * hash_to_unit(self.seed, cx, cz, 1);
+
+		// Keep islands entirely inside their cell, so height_at only ever has to
+		// look at one of them
+		let slack = (ISLAND_CELL_SIZE - 2.0 * radius).max(0.0);
+		let offset = Vec2::new(hash_to_unit(self.seed, cx, cz, 2), hash_to_unit(self.seed, cx, cz, 3)) * slack;
+
+		let cell_origin = Vec2::new(cx as f32, cz as f32) * ISLAND_CELL_SIZE;
+
+		Some(Island {
+			centre: cell_origin + Vec2::splat(radius) + offset,
+			radius,
+			height: ISLAND_MAX_HEIGHT * (0.4 + 0.6 * hash_to_unit(self.seed, cx, cz, 4)),
+		})
+	}
+
+	/// Height of the terrain at `pos`, relative to sea level
+	pub fn height_at(&self, pos: Vec2) -> f32 {
+		let cx = (pos.x / ISLAND_CELL_SIZE).floor() as i32;
+		let cz = (pos.y / ISLAND_CELL_SIZE).floor() as i32;
+
+		match self.island_in_cell(cx, cz) {
+			Some(island) => self.island_height(&island, pos),
+			None => SEABED_HEIGHT,
+		}
+	}
+
+	fn island_height(&self, island: &Island, pos: Vec2) -> f32 {
+		let dist = (pos - island.centre).length() / island.radius;
+		if dist >= 1.0 { return SEABED_HEIGHT }
+
+		let falloff = 1.0 - dist * dist;
+
+		let mut detail = 0.0;
+		let mut amplitude = 0.5;
+		let mut frequency = 0.15;
+
+		for _ in 0..3 {
+			let n: f32 = self.noise.get([pos.x * frequency, pos.y * frequency]);
+			detail += amplitude * n;
+			amplitude *= 0.5;
+			frequency *= 2.0;
+		}
+
+		SEABED_HEIGHT + falloff * island.height * (1.0 + detail)
+	}
+
+	pub fn generate_chunk(&self, coord: ChunkCoord) -> TerrainChunk {
+		let stride = CHUNK_RESOLUTION + 1;
+		let origin = chunk_origin(coord);
+		let cell_size = CHUNK_SIZE / CHUNK_RESOLUTION as f32;
+
+		let mut heights = Vec::with_capacity(stride * stride);
+
+		for z in 0..stride {
+			for x in 0..stride {
+				let pos = origin + Vec2::new(x as f32, z as f32) * cell_size;
+				heights.push(self.height_at(pos));
+			}
+		}
+
+		TerrainChunk { coord, heights }
+	}
+
+	/// Rocks and piers that belong to the chunk at `coord`. Like the terrain
+	/// itself these only depend on the seed, so they can be regenerated at will
+	pub fn obstacles_in_chunk(&self, coord: ChunkCoord) -> Vec<Obstacle> {
+		let origin = chunk_origin(coord);
+		let mut obstacles = Vec::new();
+
+		for i in 0..ROCK_ATTEMPTS_PER_CHUNK {
+			let salt = 16 + i * 3;
+			let offset = Vec2::new(hash_to_unit(self.seed, coord.0, coord.1, salt), hash_to_unit(self.seed, coord.0, coord.1, salt + 1));
+			let centre = origin + offset * CHUNK_SIZE;
+
+			let height = self.height_at(centre);
+			if height < ROCK_MIN_HEIGHT || height > ROCK_MAX_HEIGHT { continue }
+
+			let radius = ROCK_MIN_RADIUS + (ROCK_MAX_RADIUS - ROCK_MIN_RADIUS) * hash_to_unit(self.seed, coord.0, coord.1, salt + 2);
+			obstacles.push(Obstacle::Circle { centre, radius });
+		}
+
+		// Chunks tile cells exactly, so the island a chunk could hold a pier
+		// for is the one in the cell containing the chunk
+		let cx = (origin.x / ISLAND_CELL_SIZE).floor() as i32;
+		let cz = (origin.y / ISLAND_CELL_SIZE).floor() as i32;
+
+		if let Some((a, b)) = self.pier_in_cell(cx, cz) {
+			if chunk_coord_at(a) == coord {
+				obstacles.push(Obstacle::Capsule { a, b, radius: PIER_HALF_WIDTH });
+			}
+		}
+
+		obstacles
+	}
+
+	/// The two ends of the pier on the island in a cell, if it has one
+	fn pier_in_cell(&self, cx: i32, cz: i32) -> Option<(Vec2, Vec2)> {
+		let island = match self.island_in_cell(cx, cz) {
+			Some(island) => island,
+			None => return None,
+		};
+
+		if hash_to_unit(self.seed, cx, cz, 5) > PIER_CHANCE {
+			return None
+		}
+
+		let angle = hash_to_unit(self.seed, cx, cz, 6) * 2.0 * PI;
+		let dir = Vec2::new(angle.cos(), angle.sin());
+
+		// Start far enough inland to be on the beach whatever the noise does
+		let start = island.centre + dir * island.radius * 0.6;
+		let end = island.centre + dir * (island.radius + PIER_REACH);
+		Some((start, end))
+	}
+}
+
+impl CollisionWorld for IslandGenerator {
+	fn terrain_height(&self, pos: Vec2) -> f32 {
+		self.height_at(pos)
+	}
+
+	/// Regenerates every chunk in reach, so prefer ChunkManager, which keeps
+	/// them around
+	fn obstacles_near(&self, pos: Vec2, radius: f32, out: &mut Vec<Obstacle>) {
+		let ((x0, z0), (x1, z1)) = obstacle_chunks_near(pos, radius);
+
+		for z in z0..=z1 {
+			for x in x0..=x1 {
+				out.extend(self.obstacles_in_chunk((x, z)));
+			}
+		}
+	}
+}
+
+/// A square of heightfield, sampled on a (CHUNK_RESOLUTION + 1)² grid so
+/// neighbouring chunks share their edges
+pub struct TerrainChunk {
+	pub coord: ChunkCoord,
+	pub heights: Vec<f32>,
+}
+
+impl TerrainChunk {
+	/// Whether any of the chunk pokes out of the sea bed far enough to be seen
+	pub fn has_land(&self) -> bool {
+		self.heights.iter().any(|&h| h > SEABED_HEIGHT)
+	}
+
+	pub fn build_mesh(&self, mb: &mut MeshBuilder<ColorVertex>) {
+		let stride = CHUNK_RESOLUTION + 1;
+		let origin = chunk_origin(self.coord);
+		let cell_size = CHUNK_SIZE / CHUNK_RESOLUTION as f32;
+
+		let point = |x: usize, z: usize| {
+			let pos = origin + Vec2::new(x as f32, z as f32) * cell_size;
+			Vec3::new(pos.x, self.heights[z * stride + x], pos.y)
+		};
+
+		for z in 0..CHUNK_RESOLUTION {
+			for x in 0..CHUNK_RESOLUTION {
+				let (p0, p1) = (point(x, z), point(x + 1, z));
+				let (p2, p3) = (point(x + 1, z + 1), point(x, z + 1));
+
+				for &(a, b, c) in [(p0, p3, p2), (p0, p2, p1)].iter() {
+					let top = a.y.max(b.y).max(c.y);
+					if top <= SEABED_HEIGHT { continue }
+
+					mb.add_shaded_tri(a, b, c, terrain_color((a.y + b.y + c.y) / 3.0));
+				}
+			}
+		}
+	}
+}
+
+/// The first and last chunk coords that could own an obstacle within `radius`
+/// of `pos`. Piers belong to the chunk they start in, but can reach well past
+/// it, so this looks as far out as the longest possible pier
+pub fn obstacle_chunks_near(pos: Vec2, radius: f32) -> (ChunkCoord, ChunkCoord) {
+	let reach = Vec2::splat(radius + PIER_REACH + ISLAND_MAX_RADIUS * 0.4);
+	(chunk_coord_at(pos - reach), chunk_coord_at(pos + reach))
+}
+
+pub fn chunk_origin(coord: ChunkCoord) -> Vec2 {
+	Vec2::new(coord.0 as f32, coord.1 as f32) * CHUNK_SIZE
+}
+
+pub fn chunk_coord_at(pos: Vec2) -> ChunkCoord {
+	((pos.x / CHUNK_SIZE).floor() as i32, (pos.y / CHUNK_SIZE).floor() as i32)
+}
+
+fn terrain_color(height: f32) -> Vec3 {
+	let color = if height < SAND_HEIGHT {
+		Color::rgb8(226, 204, 146)
+	} else if height < GRASS_HEIGHT {
+		Color::rgb8(126, 172, 88)
+	} else {
+		Color::rgb8(150, 140, 130)
+	};
+
+	color.into()
+}
+
+/// Cheap integer hash mapped to [0, 1). Used instead of an rng so that
+/// anything placed in the world only depends on where it is
+pub fn hash_to_unit(seed: u32, x: i32, z: i32, salt: u32) -> f32 {
+	let mut h = seed
+		^ (x as u32).wrapping_mul(0x27d4eb2d)
+		^ (z as u32).wrapping_mul(0x165667b1)
+		^ salt.wrapping_mul(0x9e3779b9);
+
+	h ^= h >> 15;
+	h = h.wrapping_mul(0x2c1b3c6d);
+	h ^= h >> 12;
+	h = h.wrapping_mul(0x297a2d39);
+	h ^= h >> 15;
+
+	(h >> 8) as f32 / (1u32 << 24) as f32
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Chunk coords out to `radius` chunks from the origin in every direction
+	fn chunks_within(radius: i32) -> Vec<ChunkCoord> {
+		let mut coords = Vec::new();
+		for z in -radius..=radius {
+			for x in -radius..=radius {
+				coords.push((x, z));
+			}
+		}
+
+		coords
+	}
+
+	#[test]
+	fn same_seed_same_terrain() {
+		// Two separate generators, as if from two runs of the game
+		let (a, b) = (IslandGenerator::new(7), IslandGenerator::new(7));
+		let mut land = 0;
+
+		for coord in chunks_within(6) {
+			let (chunk_a, chunk_b) = (a.generate_chunk(coord), b.generate_chunk(coord));
+
+			assert_eq!(chunk_a.coord, coord);
+			assert_eq!(chunk_a.heights, chunk_b.heights, "chunk {:?} differs", coord);
+			assert_eq!(a.obstacles_in_chunk(coord), b.obstacles_in_chunk(coord), "obstacles in {:?} differ", coord);
+
+			if chunk_a.has_land() { land += 1 }
+		}
+
+		assert!(land > 0, "no land anywhere to compare");
+	}
+
+	#[test]
+	fn different_seeds_different_terrain() {
+		let (a, b) = (IslandGenerator::new(7), IslandGenerator::new(8));
+
+		let differs = chunks_within(6).into_iter()
+			.any(|coord| a.generate_chunk(coord).heights != b.generate_chunk(coord).heights);
+
+		assert!(differs);
+	}
+}
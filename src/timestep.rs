@@ -0,0 +1,164 @@
+/// A frame that falls this far (as a fraction of a step) short of a whole step
+/// still gets it. Frame times that are exact multiples of the step come out a
+/// hair either side once they've been through floating point, and without this
+/// a 60Hz display would stutter between zero and two steps a frame
+const STEP_TOLERANCE: f64 = 1e-6;
+
+/// Turns measured frame times into a whole number of fixed simulation steps,
+/// so the simulation behaves the same regardless of display refresh rate.
+pub struct FixedTimestep {
+	step: f64,
+	max_frame_time: f64,
+
+	accumulator: f64,
+	last_time: Option<f64>,
+}
+
+impl FixedTimestep {
+	pub fn new(step: f64) -> Self {
+		FixedTimestep {
+			step,
+			// Stops a long stall (e.g. a backgrounded tab) from causing a huge
+			// burst of catch-up steps
+			max_frame_time: 0.25,
+
+			accumulator: 0.0,
+			last_time: None,
+		}
+	}
+
+	pub fn step(&self) -> f32 { self.step as f32 }
+
+	/// Takes the current time in seconds and returns how many fixed steps
+	/// should be run this frame
+	pub fn advance_to(&mut self, now: f64) -> u32 {
+		let dt = match self.last_time {
+			Some(last) => (now - last).max(0.0),
+			None => 0.0,
+		};
+
+		self.last_time = Some(now);
+		self.advance(dt)
+	}
+
+	/// Takes a frame delta in seconds and returns how many fixed steps should
+	/// be run this frame
+	pub fn advance(&mut self, dt: f64) -> u32 {
+		self.accumulator += dt.min(self.max_frame_time);
+
+		// Whatever a step is taken early by carries over as a tiny debt, so
+		// nothing drifts
+		let mut steps = 0;
+		while self.accumulator >= self.step * (1.0 - STEP_TOLERANCE) {
+			self.accumulator -= self.step;
+			steps += 1;
+		}
+
+		steps
+	}
+
+	/// How far between the previous and the next fixed step the current frame is
+	pub fn alpha(&self) -> f32 {
+		(self.accumulator / self.step).max(0.0).min(1.0) as f32
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const STEP: f64 = 1.0 / 60.0;
+
+	/// Runs `frames` frames at `hz` through advance_to, returning the steps
+	/// taken each frame
+	fn run_at(timestep: &mut FixedTimestep, hz: f64, frames: usize) -> Vec<u32> {
+		(0..frames + 1)
+			.map(|i| timestep.advance_to(i as f64 / hz))
+			.skip(1)
+			.collect()
+	}
+
+	#[test]
+	fn sixty_hz_steps_once_a_frame() {
+		let mut timestep = FixedTimestep::new(STEP);
+		let steps = run_at(&mut timestep, 60.0, 600);
+
+		assert!(steps.iter().all(|&s| s == 1), "{:?}", steps);
+	}
+
+	#[test]
+	fn one_twenty_hz_steps_every_other_frame() {
+		let mut timestep = FixedTimestep::new(STEP);
+		let steps = run_at(&mut timestep, 120.0, 600);
+
+		assert_eq!(steps.iter().sum::<u32>(), 300);
+		assert!(steps.iter().all(|&s| s <= 1), "{:?}", steps);
+	}
+
+	#[test]
+	fn throttled_frames_catch_up() {
+		let mut timestep = FixedTimestep::new(STEP);
+		let steps = run_at(&mut timestep, 20.0, 100);
+
+		assert!(steps.iter().all(|&s| s == 3), "{:?}", steps);
+	}
+
+	#[test]
+	fn leftover_time_carries_to_the_next_frame() {
+		let mut timestep = FixedTimestep::new(STEP);
+
+		// 1.5 steps, then another 0.5 makes up the second
+		assert_eq!(timestep.advance(1.5 * STEP), 1);
+		assert!((timestep.alpha() - 0.5).abs() < 1e-5);
+
+		assert_eq!(timestep.advance(0.5 * STEP), 1);
+		assert!(timestep.alpha().abs() < 1e-5);
+	}
+
+	#[test]
+	fn uneven_frames_total_the_same() {
+		let mut timestep = FixedTimestep::new(STEP);
+		let mut now = 0.0;
+		let mut total = 0;
+
+		timestep.advance_to(now);
+		for i in 0..300 {
+			// Alternating long and short frames, averaging 60Hz
+			now += if i % 2 == 0 { 0.7 * STEP * 2.0 } else { 0.3 * STEP * 2.0 };
+			total += timestep.advance_to(now);
+		}
+
+		assert!((total as i32 - 300).abs() <= 1, "ran {} steps", total);
+	}
+
+	#[test]
+	fn long_stalls_are_clamped() {
+		let mut timestep = FixedTimestep::new(STEP);
+
+		assert_eq!(timestep.advance(5.0), 15);
+		assert_eq!(timestep.advance(0.25), 15);
+	}
+
+	#[test]
+	fn clock_going_backwards_runs_nothing() {
+		let mut timestep = FixedTimestep::new(STEP);
+
+		timestep.advance_to(10.0);
+		assert_eq!(timestep.advance_to(9.0), 0);
+	}
+
+	#[test]
+	fn alpha_stays_between_zero_and_one() {
+		let mut timestep = FixedTimestep::new(STEP);
+		let mut now = 0.0;
+
+		for i in 0..1000 {
+			// Scatter frame times from 1ms to 0.3s
+			now += 0.001 + (i * 7919 % 300) as f64 * 0.001;
+			timestep.advance_to(now);
+
+			let alpha = timestep.alpha();
+			assert!(alpha >= 0.0 && alpha < 1.0, "alpha {} after frame {}", alpha, i);
+		}
+	}
+}
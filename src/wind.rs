@@ -0,0 +1,52 @@
+use common::*;
+use noise::{NoiseModule, Perlin, Seedable};
+
+const DIRECTION_WANDER: f32 = PI / 2.0;
+const DIRECTION_RATE: f32 = 0.02;
+const STRENGTH_RATE: f32 = 0.05;
+
+/// A slowly shifting wind. Like everything else in the simulation it's only
+/// advanced by step, so the same seed always gives the same weather
+#[derive(Clone)]
+pub struct Wind {
+	noise: Perlin,
+	base_direction: f32,
+	time: f32,
+
+	/// The angle the wind is blowing towards, in the same convention as boat headings
+	pub direction: f32,
+	/// From 0 (dead calm) to 1
+	pub strength: f32,
+}
+
+impl Wind {
+	pub fn new(seed: usize, base_direction: f32) -> Self {
+		let mut wind = Wind {
+			noise: Perlin::new().set_seed(seed),
+			base_direction,
+			time: 0.0,
+
+			direction: base_direction,
+			strength: 0.0,
+		};
+
+		wind.step(0.0);
+		wind
+	}
+
+	pub fn step(&mut self, dt: f32) {
+		self.time += dt;
+
+		let wander: f32 = self.noise.get([self.time * DIRECTION_RATE, 0.0]);
+		let gust: f32 = self.noise.get([self.time * STRENGTH_RATE, 100.0]);
+
+		self.direction = self.base_direction + wander * DIRECTION_WANDER;
+		self.strength = (0.6 + 0.5 * gust).max(0.2).min(1.0);
+	}
+
+	/// Unit vector the wind is blowing along, on the XZ plane
+	pub fn blowing_towards(&self) -> Vec2 {
+		let (s, c) = self.direction.sin_cos();
+		Vec2::new(c, -s)
+	}
+}
@@ -0,0 +1,237 @@
+use std::collections::HashMap;
+
+use common::*;
+
+use rendering::mesh_builder::*;
+use terrain::*;
+use collision::{CollisionWorld, Obstacle};
+
+/// Far off sea sits a little below the animated water around the boat, so
+/// the two never fight where they overlap
+const FAR_SEA_HEIGHT: f32 = -0.3;
+const FAR_SEA_RESOLUTION: usize = 4;
+
+/// Water shallower than this is tinted towards SHALLOW_COLOR
+const SHALLOW_DEPTH: f32 = 0.6;
+
+const ROCK_SIDES: usize = 6;
+const ROCK_BASE_HEIGHT: f32 = -0.5;
+const PIER_DECK_HEIGHT: f32 = 0.35;
+
+pub struct Chunk {
+	pub coord: ChunkCoord,
+	pub sea: Mesh,
+	pub terrain: Option<Mesh>,
+	/// Kept so collision doesn't have to regenerate them every step
+	pub obstacles: Vec<Obstacle>,
+}
+
+/// Keeps a ring of chunks generated and uploaded around a point, and frees
+/// them again once they fall out of range. Only `generation_budget` chunks are
+/// generated per update, nearest first, so moving never causes a hitch
+pub struct ChunkManager {
+	islands: IslandGenerator,
+	chunks: HashMap<ChunkCoord, Chunk>,
+	mb: MeshBuilder<ColorVertex>,
+
+	pub view_radius: i32,
+	pub generation_budget: usize,
+}
+
+impl ChunkManager {
+	pub fn new(seed: usize, view_radius: i32, generation_budget: usize) -> Self {
+		ChunkManager {
+			islands: IslandGenerator::new(seed),
+			chunks: HashMap::new(),
+			mb: MeshBuilder::new(),
+
+			view_radius,
+			generation_budget,
+		}
+	}
+
+	pub fn islands(&self) -> &IslandGenerator { &self.islands }
+
+	pub fn chunks(&self) -> ::std::collections::hash_map::Values<ChunkCoord, Chunk> {
+		self.chunks.values()
+	}
+
+	pub fn num_chunks(&self) -> usize { self.chunks.len() }
+
+	pub fn update(&mut self, centre: Vec2) {
+		let (cx, cz) = chunk_coord_at(centre);
+		let radius = self.view_radius;
+
+		// Chunks are kept around for an extra ring before being freed, so
+		// wiggling back and forth over a chunk border doesn't thrash
+		let stale: Vec<ChunkCoord> = self.chunks.keys()
+			.filter(|&&(x, z)| (x - cx).abs() > radius + 1 || (z - cz).abs() > radius + 1)
+			.cloned()
+			.collect();
+
+		for coord in stale {
+			self.chunks.remove(&coord);
+		}
+
+		let mut missing = Vec::new();
+
+		for z in cz-radius ..= cz+radius {
+			for x in cx-radius ..= cx+radius {
+				if !self.chunks.contains_key(&(x, z)) {
+					missing.push((x, z));
+				}
+			}
+		}
+
+		missing.sort_by_key(|&(x, z)| (x - cx).pow(2) + (z - cz).pow(2));
+
+		for coord in missing.into_iter().take(self.generation_budget) {
+			let chunk = self.generate_chunk(coord);
+			self.chunks.insert(coord, chunk);
+		}
+	}
+
+	fn generate_chunk(&mut self, coord: ChunkCoord) -> Chunk {
+		let terrain_chunk = self.islands.generate_chunk(coord);
+		let obstacles = self.islands.obstacles_in_chunk(coord);
+
+		let terrain = if terrain_chunk.has_land() || !obstacles.is_empty() {
+			self.mb.clear();
+			terrain_chunk.build_mesh(&mut self.mb);
+			build_obstacles(&mut self.mb, &obstacles);
+
+			let mut mesh = Mesh::new();
+			self.mb.upload_to(&mut mesh);
+			Some(mesh)
+		} else {
+			None
+		};
+
+		self.mb.clear();
+		build_far_sea(&mut self.mb, &terrain_chunk);
+
+		let mut sea = Mesh::new();
+		self.mb.upload_to(&mut sea);
+
+		Chunk { coord, sea, terrain, obstacles }
+	}
+}
+
+impl CollisionWorld for ChunkManager {
+	fn terrain_height(&self, pos: Vec2) -> f32 {
+		self.islands.height_at(pos)
+	}
+
+	/// Reads obstacles from the chunks already generated, only falling back
+	/// to generating them for chunks that haven't been yet
+	fn obstacles_near(&self, pos: Vec2, radius: f32, out: &mut Vec<Obstacle>) {
+		let ((x0, z0), (x1, z1)) = obstacle_chunks_near(pos, radius);
+
+		for z in z0..=z1 {
+			for x in x0..=x1 {
+				match self.chunks.get(&(x, z)) {
+					Some(chunk) => out.extend_from_slice(&chunk.obstacles),
+					None => out.extend(self.islands.obstacles_in_chunk((x, z))),
+				}
+			}
+		}
+	}
+}
+
+fn build_far_sea(mb: &mut MeshBuilder<ColorVertex>, terrain_chunk: &TerrainChunk) {
+	let deep: Vec3 = Color::rgb8(122, 158, 198).into();
+	let shallow: Vec3 = Color::rgb8(150, 196, 210).into();
+
+	let origin = chunk_origin(terrain_chunk.coord);
+	let step = CHUNK_RESOLUTION / FAR_SEA_RESOLUTION;
+	let stride = CHUNK_RESOLUTION + 1;
+	let cell_size = CHUNK_SIZE / FAR_SEA_RESOLUTION as f32;
+
+	for z in 0..FAR_SEA_RESOLUTION {
+		for x in 0..FAR_SEA_RESOLUTION {
+			let depth = -terrain_chunk.heights[(z * step + step / 2) * stride + x * step + step / 2];
+			let shallowness = (1.0 - depth / SHALLOW_DEPTH).max(0.0).min(1.0);
+			let color = deep + (shallow - deep) * shallowness;
+
+			let corner = origin + Vec2::new(x as f32, z as f32) * cell_size;
+
+			mb.add_quad(&[
+				ColorVertex(Vec3::new(corner.x, FAR_SEA_HEIGHT, corner.y), color),
+				ColorVertex(Vec3::new(corner.x, FAR_SEA_HEIGHT, corner.y + cell_size), color),
+				ColorVertex(Vec3::new(corner.x + cell_size, FAR_SEA_HEIGHT, corner.y + cell_size), color),
+				ColorVertex(Vec3::new(corner.x + cell_size, FAR_SEA_HEIGHT, corner.y), color),
+			]);
+		}
+	}
+}
+
+fn build_obstacles(mb: &mut MeshBuilder<ColorVertex>, obstacles: &[Obstacle]) {
+	let rock: Vec3 = Color::rgb8(128, 122, 120).into();
+	let wood: Vec3 = Color::rgb8(150, 110, 74).into();
+
+	let at = |p: Vec2, y: f32| Vec3::new(p.x, y, p.y);
+	let rim = |i: usize| {
+		let angle = i as f32 / ROCK_SIDES as f32 * 2.0 * PI;
+		Vec2::new(angle.cos(), angle.sin())
+	};
+
+	for obstacle in obstacles.iter() {
+		match *obstacle {
+			Obstacle::Circle { centre, radius } => {
+				let apex = at(centre, radius * 0.8);
+
+				for i in 0..ROCK_SIDES {
+					let a = centre + rim(i) * radius;
+					let b = centre + rim(i + 1) * radius;
+
+					mb.add_shaded_tri(at(b, ROCK_BASE_HEIGHT), at(a, ROCK_BASE_HEIGHT), apex, rock);
+				}
+			}
+
+			Obstacle::Capsule { a, b, radius } => {
+				let dir = (b - a).normalize();
+				let side = Vec2::new(-dir.y, dir.x) * radius;
+
+				let (p0, p1) = (a - side, a + side);
+				let (p2, p3) = (b + side, b - side);
+
+				// Deck, then the two long sides down into the water
+				mb.add_shaded_tri(at(p0, PIER_DECK_HEIGHT), at(p1, PIER_DECK_HEIGHT), at(p2, PIER_DECK_HEIGHT), wood);
+				mb.add_shaded_tri(at(p0, PIER_DECK_HEIGHT), at(p2, PIER_DECK_HEIGHT), at(p3, PIER_DECK_HEIGHT), wood);
+
+				for &(l, r) in [(p1, p2), (p3, p0), (p2, p3)].iter() {
+					mb.add_shaded_tri(at(l, ROCK_BASE_HEIGHT), at(r, ROCK_BASE_HEIGHT), at(r, PIER_DECK_HEIGHT), wood);
+					mb.add_shaded_tri(at(l, ROCK_BASE_HEIGHT), at(r, PIER_DECK_HEIGHT), at(l, PIER_DECK_HEIGHT), wood);
+				}
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use platform::recording_gl::record_gl;
+
+	#[test]
+	fn cached_obstacles_match_generated_ones() {
+		let mut found = Vec::new();
+
+		record_gl(|| {
+			let mut world = ChunkManager::new(7, 3, 64);
+
+			for &pos in [Vec2::zero(), Vec2::new(30.0, -20.0), Vec2::new(-45.0, 60.0)].iter() {
+				world.update(pos);
+
+				let (mut cached, mut generated) = (Vec::new(), Vec::new());
+				world.obstacles_near(pos, 1.0, &mut cached);
+				world.islands().obstacles_near(pos, 1.0, &mut generated);
+				found.push((cached, generated));
+			}
+		});
+
+		for (cached, generated) in found {
+			assert_eq!(cached, generated);
+		}
+	}
+}